@@ -1,15 +1,301 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{atomic::AtomicU64, Arc};
+use std::time::SystemTime;
+use serde_json::json;
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 use crate::loadtest::LoadTestMonitoringData;
-use crate::tasks::MonitoringData;
+use crate::ping_monitor::PingMonitoringData;
+use crate::tasks::{CircuitBreaker, LearnedThreshold, MonitoringData};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct AppState {
-    /// Indicates whether the monitoring task has been started.
+    /// Indicates whether a monitoring cycle is running: set by a trigger, cleared when the cycle completes.
     pub monitoring_started: bool,
     /// Monitoring data for load tests, organized by workflow name and then by API URL.
     pub load_test_monitoring_data: Arc<Mutex<HashMap<String, HashMap<String, LoadTestMonitoringData>>>>,
     /// Monitoring data for tasks, organized by workflow name and then by API URL.
     pub task_monitoring_data: Arc<Mutex<HashMap<String, HashMap<String, MonitoringData>>>>,
+    /// Aggregate connect latency for ping checks, organized by workflow name and then by task name.
+    pub ping_monitoring_data: Arc<Mutex<HashMap<String, HashMap<String, PingMonitoringData>>>>,
+    /// The verdict of each workflow's latest run, "PASSED" or "FAILED", by workflow name.
+    pub workflow_status: Arc<Mutex<HashMap<String, String>>>,
+    /// The last ETag seen for each task with `track_etag`, organized by workflow name and then by task name.
+    pub etags: Arc<Mutex<HashMap<String, HashMap<String, String>>>>,
+    /// Baselines of tasks with `learn_threshold`, organized by workflow name and then by task name.
+    pub learned_thresholds: Arc<Mutex<HashMap<String, HashMap<String, LearnedThreshold>>>>,
+    /// Consecutive failures of tasks with `circuit_break_after`, organized by workflow name and then by task name.
+    pub circuit_breakers: Arc<Mutex<HashMap<String, HashMap<String, CircuitBreaker>>>>,
+    /// The number of monitoring cycles, i.e. full passes over the triggered workflows, completed so far.
+    pub cycles_completed: AtomicU64,
+    /// The number of monitoring cycles started so far, counted by the trigger that starts each one
+    /// while it holds the state lock; a cycle is in flight while this exceeds `cycles_completed`.
+    pub cycles_started: AtomicU64,
+    /// Cancelled by a drain request to stop the current monitoring run, e.g. to end load test
+    /// retries waiting out their backoff. Each trigger replaces it with a fresh token.
+    pub stop_token: CancellationToken,
+    /// Upper bound on the number of stored task results, and separately on load test results.
+    /// Past it, the least recently updated results are evicted. Unbounded when `None` or zero.
+    pub max_results_entries: Option<usize>,
+    /// The order task results were last updated in, for eviction.
+    pub task_results_order: Arc<Mutex<ResultsOrder>>,
+    /// The order load test results were last updated in, for eviction.
+    pub load_test_results_order: Arc<Mutex<ResultsOrder>>,
+}
+
+/// Remembers the order results were last updated in, so the stalest can be evicted once a
+/// results map grows past its limit.
+///
+/// Every update is stamped with an increasing sequence number, so moving an entry to the back
+/// and evicting the oldest are both logarithmic rather than a scan of every entry.
+#[derive(Debug, Default)]
+pub struct ResultsOrder {
+    /// Entries keyed by the sequence number of their latest update, oldest first.
+    entries: BTreeMap<u64, (String, String)>,
+    /// The sequence number of each entry's latest update.
+    sequences: HashMap<(String, String), u64>,
+    next_sequence: u64,
+}
+
+impl ResultsOrder {
+    /// Records `(workflow_name, task_name)` as the latest update and returns the entries that
+    /// no longer fit within `limit`, oldest first.
+    pub fn touch(&mut self, workflow_name: &str, task_name: &str, limit: Option<usize>) -> Vec<(String, String)> {
+        let key = (workflow_name.to_string(), task_name.to_string());
+        if let Some(previous) = self.sequences.insert(key.clone(), self.next_sequence) {
+            self.entries.remove(&previous);
+        }
+        self.entries.insert(self.next_sequence, key);
+        self.next_sequence += 1;
+
+        let limit = limit.filter(|limit| *limit > 0).unwrap_or(usize::MAX);
+        let mut evicted = Vec::new();
+        while self.entries.len() > limit {
+            let Some((_, key)) = self.entries.pop_first() else { break };
+            self.sequences.remove(&key);
+            evicted.push(key);
+        }
+        evicted
+    }
+}
+
+/// Removes the `evicted` results from `data`, dropping workflows that are left without any.
+pub fn evict_results<T>(data: &mut HashMap<String, HashMap<String, T>>, evicted: &[(String, String)]) {
+    for (workflow_name, task_name) in evicted {
+        if let Some(tasks) = data.get_mut(workflow_name) {
+            tasks.remove(task_name);
+            if tasks.is_empty() {
+                data.remove(workflow_name);
+            }
+        }
+    }
+}
+
+impl AppState {
+    /// Whether a drain is waiting for the running cycle to finish: monitoring is still running,
+    /// but its `stop_token` has been cancelled.
+    pub fn draining(&self) -> bool {
+        self.monitoring_started && self.stop_token.is_cancelled()
+    }
+
+    /// Describes every task and load test whose latest result counts as a failure.
+    ///
+    /// A task fails when its status is anything other than "OK" or "DEGRADED"; a load test fails when
+    /// it has a status such as "TIMEOUT", breached its response time threshold or any of its requests
+    /// failed; a ping check fails when any connect failed, and a workflow when its verdict is "FAILED".
+    /// An empty list means the run passed.
+    pub async fn failures(&self) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        for (workflow_name, status) in self.workflow_status.lock().await.iter() {
+            if status == "FAILED" {
+                failures.push(format!("Workflow '{}' failed", workflow_name));
+            }
+        }
+
+        for (workflow_name, tasks) in self.task_monitoring_data.lock().await.iter() {
+            for (task_name, data) in tasks {
+                if data.status != "OK" && data.status != "DEGRADED" {
+                    failures.push(format!("Task '{}' in workflow '{}' finished with status {}", task_name, workflow_name, data.status));
+                }
+            }
+        }
+
+        for (workflow_name, load_tests) in self.load_test_monitoring_data.lock().await.iter() {
+            for (task_name, data) in load_tests {
+                if let Some(status) = data.status.as_deref().filter(|status| *status != "OK") {
+                    failures.push(format!("Load test '{}' in workflow '{}' finished with status {}", task_name, workflow_name, status));
+                }
+                if data.threshold_breached {
+                    failures.push(format!("Load test '{}' in workflow '{}' had a p95 of {}ms, over its {}ms threshold",
+                        task_name, workflow_name, data.percentile_95th_response_time_ms, data.response_time_threshold_ms.unwrap_or_default()));
+                }
+                if data.failure_count > 0 {
+                    failures.push(format!("Load test '{}' in workflow '{}' had {} failed requests", task_name, workflow_name, data.failure_count));
+                }
+            }
+        }
+
+        for (workflow_name, pings) in self.ping_monitoring_data.lock().await.iter() {
+            for (task_name, data) in pings {
+                if data.failure_count > 0 {
+                    failures.push(format!("Ping check '{}' in workflow '{}' lost {} of {} pings", task_name, workflow_name, data.failure_count, data.sample_count));
+                }
+            }
+        }
+
+        failures.sort();
+        failures
+    }
+
+    /// Snapshots every result of a finished run, with its overall verdict and timing, as JSON.
+    pub async fn run_report(&self, started_at: SystemTime, finished_at: SystemTime) -> serde_json::Value {
+        let failures = self.failures().await;
+        json!({
+            "started_at": httpdate::fmt_http_date(started_at),
+            "finished_at": httpdate::fmt_http_date(finished_at),
+            "duration_ms": finished_at.duration_since(started_at).unwrap_or_default().as_millis() as u64,
+            "passed": failures.is_empty(),
+            "failures": failures,
+            "workflows": *self.workflow_status.lock().await,
+            "tasks": *self.task_monitoring_data.lock().await,
+            "load_tests": *self.load_test_monitoring_data.lock().await,
+            "ping_checks": *self.ping_monitoring_data.lock().await,
+        })
+    }
+
+    /// Writes the `run_report` of a finished run to `path` as pretty-printed JSON.
+    pub async fn write_run_report(&self, path: &Path, started_at: SystemTime, finished_at: SystemTime) -> std::io::Result<()> {
+        let report = self.run_report(started_at, finished_at).await;
+        let contents = serde_json::to_string_pretty(&report).map_err(std::io::Error::other)?;
+        tokio::fs::write(path, contents).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{sample_load_test_data, sample_task_data as task_data};
+
+    #[test]
+    fn test_results_order_evicts_least_recently_updated() {
+        let mut order = ResultsOrder::default();
+        order.touch("Workflow", "A", Some(2));
+        order.touch("Workflow", "B", Some(2));
+        // Updating A again makes B the stalest.
+        order.touch("Workflow", "A", Some(2));
+
+        let evicted = order.touch("Workflow", "C", Some(2));
+
+        assert_eq!(evicted, vec![("Workflow".to_string(), "B".to_string())]);
+        assert!(order.touch("Workflow", "D", None).is_empty());
+    }
+
+    #[test]
+    fn test_results_order_tracks_evicted_entries_that_come_back() {
+        let mut order = ResultsOrder::default();
+        order.touch("Workflow", "A", Some(1));
+        assert_eq!(order.touch("Workflow", "B", Some(1)), vec![("Workflow".to_string(), "A".to_string())]);
+
+        let evicted = order.touch("Workflow", "A", Some(1));
+
+        assert_eq!(evicted, vec![("Workflow".to_string(), "B".to_string())]);
+        assert_eq!(order.touch("Workflow", "A", Some(1)), Vec::<(String, String)>::new());
+    }
+
+    #[tokio::test]
+    async fn test_failures_empty_when_all_tasks_ok() {
+        let state = AppState::default();
+        state.task_monitoring_data.lock().await.insert(
+            "Workflow".to_string(),
+            HashMap::from([
+                ("Fetch".to_string(), task_data("OK")),
+                ("Search".to_string(), task_data("DEGRADED")),
+            ]),
+        );
+
+        assert!(state.failures().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failures_report_errored_tasks() {
+        let state = AppState::default();
+        state.task_monitoring_data.lock().await.insert(
+            "Workflow".to_string(),
+            HashMap::from([
+                ("Fetch".to_string(), task_data("OK")),
+                ("Create".to_string(), task_data("ERROR")),
+            ]),
+        );
+
+        assert_eq!(state.failures().await, vec!["Task 'Create' in workflow 'Workflow' finished with status ERROR".to_string()]);
+    }
+
+    fn passing_load_test() -> LoadTestMonitoringData {
+        LoadTestMonitoringData { success_count: 4, failure_count: 0, ..sample_load_test_data() }
+    }
+
+    #[tokio::test]
+    async fn test_failures_report_breached_load_test_threshold() {
+        let state = AppState::default();
+        let breached = LoadTestMonitoringData {
+            percentile_95th_response_time_ms: 30,
+            response_time_threshold_ms: Some(20),
+            threshold_breached: true,
+            ..passing_load_test()
+        };
+        state.load_test_monitoring_data.lock().await.insert(
+            "Workflow".to_string(),
+            HashMap::from([("Fetch".to_string(), passing_load_test()), ("Search".to_string(), breached)]),
+        );
+
+        assert_eq!(state.failures().await, vec!["Load test 'Search' in workflow 'Workflow' had a p95 of 30ms, over its 20ms threshold".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_failures_report_load_tests_without_results() {
+        let state = AppState::default();
+        let timed_out = LoadTestMonitoringData { status: Some("TIMEOUT".to_string()), ..LoadTestMonitoringData::default() };
+        state.load_test_monitoring_data.lock().await.insert(
+            "Workflow".to_string(),
+            HashMap::from([("Fetch".to_string(), passing_load_test()), ("Search".to_string(), timed_out)]),
+        );
+
+        assert_eq!(state.failures().await, vec!["Load test 'Search' in workflow 'Workflow' finished with status TIMEOUT".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_failures_report_failed_workflows() {
+        let state = AppState::default();
+        state.workflow_status.lock().await.extend([
+            ("Passing".to_string(), "PASSED".to_string()),
+            ("Failing".to_string(), "FAILED".to_string()),
+        ]);
+
+        assert_eq!(state.failures().await, vec!["Workflow 'Failing' failed".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_run_report_is_written_with_summary_and_results() {
+        let state = AppState::default();
+        state.task_monitoring_data.lock().await.insert(
+            "Workflow".to_string(),
+            HashMap::from([("Create".to_string(), task_data("ERROR"))]),
+        );
+        let path = std::env::temp_dir().join(format!("thunderhawk-report-{}.json", uuid::Uuid::new_v4()));
+        let started_at = SystemTime::now();
+        let finished_at = started_at + std::time::Duration::from_millis(1500);
+
+        state.write_run_report(&path, started_at, finished_at).await.unwrap();
+        let report: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut keys: Vec<&str> = report.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["duration_ms", "failures", "finished_at", "load_tests", "passed", "ping_checks", "started_at", "tasks", "workflows"]);
+        assert_eq!(report["duration_ms"], 1500);
+        assert_eq!(report["passed"], false);
+        assert_eq!(report["tasks"]["Workflow"]["Create"]["status"], "ERROR");
+    }
 }
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::str::FromStr;
+use reqwest::header::{HeaderName, HeaderValue};
+use crate::utils::http_client::parse_resolve_address;
+use crate::utils::interpolate::interpolate_string;
 
 // src/cli.rs
 use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
@@ -6,7 +9,7 @@ use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
 
 pub fn build_cli() -> Command {
     Command::new("Workflow Runner")
-        .version("1.0")
+        .version(env!("CARGO_PKG_VERSION"))
         .author("Richard Chukwu <richinex@gmail.com>")
         .about("Runs configured workflows")
         .arg(
@@ -26,6 +29,14 @@ pub fn build_cli() -> Command {
                 .action(ArgAction::Set)
                 .num_args(1),
         )
+        .arg(
+            Arg::new("config-glob")
+                .long("config-glob")
+                .value_name("PATTERN")
+                .help("Selects config files in the config directory, e.g. '**/*.yml' to include subdirectories (default '*.yml'; can be used multiple times)")
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
         .arg(
             Arg::new("monitoring_interval_seconds")
                 .long("monitoring-interval-seconds")
@@ -50,6 +61,14 @@ pub fn build_cli() -> Command {
                 .action(ArgAction::Set)
                 .num_args(1),
         )
+        .arg(
+            Arg::new("http_connect_timeout_seconds")
+                .long("http-connect-timeout-seconds")
+                .value_name("SECONDS")
+                .help("Sets the timeout in seconds for establishing a connection, separately from the HTTP timeout")
+                .action(ArgAction::Set)
+                .num_args(1),
+        )
         .arg(Arg::new("http_proxy_url")
             .long("http-proxy-url")
             .value_name("URL")
@@ -63,19 +82,319 @@ pub fn build_cli() -> Command {
             .action(ArgAction::Append)
             .num_args(1)
             .value_parser(value_parser!(String)))
+        .arg(Arg::new("http_headers_file")
+            .long("http-headers-file")
+            .value_name("PATH")
+            .help("Reads default HTTP headers from a file of KEY: VALUE lines or a JSON object; --http-default-header wins on conflicts")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("min_tls_version")
+            .long("min-tls-version")
+            .value_name("VERSION")
+            .help("Refuses connections below this TLS version: 1.0, 1.1, 1.2 or 1.3")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("resolve")
+            .long("resolve")
+            .value_name("HOST:ADDRESS")
+            .help("Resolves HOST to ADDRESS (an IP, optionally with :port) instead of using DNS (can be used multiple times)")
+            .action(ArgAction::Append)
+            .num_args(1)
+            .value_parser(value_parser!(String)))
+        .arg(Arg::new("inject_request_id")
+            .long("inject-request-id")
+            .help("Attaches a unique request ID header to every outbound request")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("request_id_header")
+            .long("request-id-header")
+            .value_name("NAME")
+            .help("Sets the header name used for injected request IDs (default: X-Request-Id)")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("otlp_endpoint")
+            .long("otlp-endpoint")
+            .value_name("URL")
+            .help("Exports monitoring spans to this OTLP/HTTP endpoint (requires the `otel` feature)")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("statsd_addr")
+            .long("statsd-addr")
+            .value_name("HOST:PORT")
+            .help("Sends task and load test metrics over UDP to the StatsD agent at this address")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("start_delay_secs")
+            .long("start-delay-secs")
+            .value_name("SECONDS")
+            .help("Delays the start of monitoring after a trigger by this many seconds")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("workflow_start_jitter_ms")
+            .long("workflow-start-jitter-ms")
+            .value_name("MILLISECONDS")
+            .help("Staggers workflow starts by a random delay of up to this many milliseconds")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("max_concurrent_workflows")
+            .long("max-concurrent-workflows")
+            .value_name("COUNT")
+            .help("Runs at most this many workflows at the same time, queuing the rest")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("max_response_bytes")
+            .long("max-response-bytes")
+            .value_name("BYTES")
+            .help("Stops reading a response body after this many bytes")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("max_results_entries")
+            .long("max-results-entries")
+            .value_name("COUNT")
+            .help("Keeps at most this many task results, and as many load test results, evicting the least recently updated")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("no_connection_reuse")
+            .long("no-connection-reuse")
+            .help("Opens a fresh connection for every request instead of reusing idle ones")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("api_token")
+            .long("api-token")
+            .value_name("TOKEN")
+            .help("Requires `Authorization: Bearer TOKEN` on the routes that trigger or stop monitoring")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("allowed_host")
+            .long("allowed-host")
+            .value_name("HOST_OR_CIDR")
+            .help("Only allows task URLs targeting this hostname, IP address or CIDR range (can be used multiple times)")
+            .action(ArgAction::Append)
+            .num_args(1)
+            .value_parser(value_parser!(String)))
+        .arg(Arg::new("no_compression")
+            .long("no-compression")
+            .help("Stops requesting compressed responses and leaves response bodies undecoded")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("user_agent")
+            .long("user-agent")
+            .value_name("STRING")
+            .help("Sets the User-Agent for outbound requests (default: thunderhawk/<version>)")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("print_schema")
+            .long("print-schema")
+            .help("Prints a JSON Schema for workflow config files and exits")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("run_once")
+            .long("run-once")
+            .help("Runs every loaded workflow once and exits instead of starting the server")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("fail_on_error")
+            .long("fail-on-error")
+            .help("With --run-once, exits with code 1 if any task or load test failed")
+            .action(ArgAction::SetTrue))
+        .arg(Arg::new("report_file")
+            .long("report-file")
+            .value_name("PATH")
+            .help("With --run-once, writes a JSON report of every result to this file when the run completes")
+            .action(ArgAction::Set)
+            .num_args(1))
+        .arg(Arg::new("bind")
+            .long("bind")
+            .value_name("ADDR")
+            .help("TCP address the HTTP server listens on (default: 127.0.0.1:8080)")
+            .action(ArgAction::Set)
+            .num_args(1)
+            .conflicts_with("unix_socket"))
+        .arg(Arg::new("unix_socket")
+            .long("unix-socket")
+            .value_name("PATH")
+            .help("Serves the HTTP API on this Unix domain socket instead of TCP")
+            .action(ArgAction::Set)
+            .num_args(1))
 }
 
 
-pub fn process_http_default_headers(matches: &ArgMatches) -> Result<HashMap<String, String>, String> {
-    matches.get_many::<String>("http_default_header")
+/// Parses every `--http-default-header KEY:VALUE` argument into a list of headers.
+///
+/// Repeated keys (e.g. several `Accept` values) are kept in the order given. Keys and
+/// values are trimmed and must form a legal HTTP header, so a typo fails at startup with
+/// a descriptive error instead of being silently dropped later on.
+///
+/// Headers from `--http-headers-file` come first; a header given on the command line
+/// replaces every value the file set for it.
+pub fn process_http_default_headers(matches: &ArgMatches) -> Result<Vec<(String, String)>, String> {
+    let cli_headers = matches.get_many::<String>("http_default_header")
+        .unwrap_or_default()
+        .map(|header| parse_header(header))
+        .collect::<Result<Vec<_>, _>>()?; // Propagates the first Err encountered, if any.
+
+    let mut headers = match matches.get_one::<String>("http_headers_file") {
+        Some(path) => read_headers_file(path)?,
+        None => Vec::new(),
+    };
+    headers.retain(|(key, _)| !cli_headers.iter().any(|(cli_key, _)| cli_key.eq_ignore_ascii_case(key)));
+    headers.extend(cli_headers);
+    Ok(headers)
+}
+
+/// Reads headers from a file of `Key: Value` lines, skipping blank lines and `#` comments,
+/// or from a JSON object of header names to values. `${VAR}` placeholders are replaced with
+/// environment variables.
+fn read_headers_file(path: &str) -> Result<Vec<(String, String)>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read headers file {}: {}", path, e))?;
+
+    if contents.trim_start().starts_with('{') {
+        let map: std::collections::BTreeMap<String, String> = serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid JSON in headers file {}: {}", path, e))?;
+        return map.iter()
+            .map(|(key, value)| parse_header(&format!("{}:{}", key, interpolate_string(value))))
+            .collect();
+    }
+
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| parse_header(&interpolate_string(line)))
+        .collect()
+}
+
+fn parse_header(header: &str) -> Result<(String, String), String> {
+    let (key, value) = header.split_once(':')
+        .ok_or_else(|| format!("Invalid header format (expected KEY:VALUE): {}", header))?;
+    let (key, value) = (key.trim(), value.trim());
+
+    if key.is_empty() {
+        return Err(format!("Header name is empty: {}", header));
+    }
+    HeaderName::from_str(key).map_err(|_| format!("Invalid header name '{}' in: {}", key, header))?;
+    HeaderValue::from_str(value).map_err(|_| format!("Invalid value for header '{}' in: {}", key, header))?;
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Parses every `--resolve HOST:ADDRESS` argument into `(host, address)` DNS overrides.
+///
+/// The address is an IP, or an `ip:port`; it is validated here so a typo fails at startup.
+pub fn process_resolve_overrides(matches: &ArgMatches) -> Result<Vec<(String, String)>, String> {
+    matches.get_many::<String>("resolve")
         .unwrap_or_default()
-        .map(|header| {
-            let parts: Vec<&str> = header.splitn(2, ':').collect();
-            if parts.len() == 2 {
-                Ok((parts[0].trim().to_string(), parts[1].trim().to_string()))
-            } else {
-                Err(format!("Invalid header format: {}", header))
+        .map(|entry| {
+            let (host, address) = entry.split_once(':')
+                .ok_or_else(|| format!("Invalid resolve format (expected HOST:ADDRESS): {}", entry))?;
+            let (host, address) = (host.trim(), address.trim());
+            if host.is_empty() {
+                return Err(format!("Host is empty: {}", entry));
             }
+            parse_resolve_address(address).ok_or_else(|| format!("Invalid address '{}' in: {}", address, entry))?;
+            Ok((host.to_string(), address.to_string()))
         })
-        .collect::<Result<HashMap<_, _>, _>>() // Collects into a Result<HashMap, String>, propagating the first Err encountered, if any.
-}
\ No newline at end of file
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers_from(args: &[&str]) -> Result<Vec<(String, String)>, String> {
+        let mut argv = vec!["thunderhawk"];
+        for arg in args {
+            argv.push("--http-default-header");
+            argv.push(arg);
+        }
+        process_http_default_headers(&build_cli().try_get_matches_from(argv).unwrap())
+    }
+
+    #[test]
+    fn test_bind_and_unix_socket_are_mutually_exclusive() {
+        let result = build_cli().try_get_matches_from(["thunderhawk", "--bind", "0.0.0.0:9000", "--unix-socket", "/tmp/thunderhawk.sock"]);
+
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_valid_headers_are_trimmed() {
+        let headers = headers_from(&[" Authorization : Bearer abc ", "X-Trace:1"]).unwrap();
+        assert_eq!(headers, vec![
+            ("Authorization".to_string(), "Bearer abc".to_string()),
+            ("X-Trace".to_string(), "1".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_repeated_header_keeps_every_value() {
+        let headers = headers_from(&["Accept:application/json", "Accept:text/plain"]).unwrap();
+        assert_eq!(headers, vec![
+            ("Accept".to_string(), "application/json".to_string()),
+            ("Accept".to_string(), "text/plain".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_header_name_is_rejected() {
+        let err = headers_from(&["Bad Header:value"]).unwrap_err();
+        assert!(err.contains("Invalid header name 'Bad Header'"), "{}", err);
+    }
+
+    #[test]
+    fn test_missing_colon_is_rejected() {
+        let err = headers_from(&["NoColonHere"]).unwrap_err();
+        assert!(err.contains("expected KEY:VALUE"), "{}", err);
+    }
+
+    #[test]
+    fn test_headers_file_merges_with_cli_headers() {
+        std::env::set_var("THUNDERHAWK_TEST_HEADER_TOKEN", "tok-456");
+        let path = std::env::temp_dir().join(format!("thunderhawk-headers-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "# shared headers\nAuthorization: Bearer ${THUNDERHAWK_TEST_HEADER_TOKEN}\n\nx-tenant: file\nAccept: text/plain\n").unwrap();
+
+        let matches = build_cli().try_get_matches_from([
+            "thunderhawk",
+            "--http-headers-file", path.to_str().unwrap(),
+            "--http-default-header", "X-Tenant:cli",
+        ]).unwrap();
+        let headers = process_http_default_headers(&matches);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(headers.unwrap(), vec![
+            ("Authorization".to_string(), "Bearer tok-456".to_string()),
+            ("Accept".to_string(), "text/plain".to_string()),
+            ("X-Tenant".to_string(), "cli".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_headers_file_accepts_json_object() {
+        let path = std::env::temp_dir().join(format!("thunderhawk-headers-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"{"X-Api-Key": "abc", "Accept": "application/json"}"#).unwrap();
+
+        let matches = build_cli().try_get_matches_from(["thunderhawk", "--http-headers-file", path.to_str().unwrap()]).unwrap();
+        let headers = process_http_default_headers(&matches);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(headers.unwrap(), vec![
+            ("Accept".to_string(), "application/json".to_string()),
+            ("X-Api-Key".to_string(), "abc".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_resolve_overrides_are_parsed_and_validated() {
+        let matches = build_cli().try_get_matches_from(["thunderhawk", "--resolve", "api.example.com:10.0.0.5", "--resolve", "db.example.com:[::1]:5432"]).unwrap();
+        assert_eq!(process_resolve_overrides(&matches).unwrap(), vec![
+            ("api.example.com".to_string(), "10.0.0.5".to_string()),
+            ("db.example.com".to_string(), "[::1]:5432".to_string()),
+        ]);
+
+        let matches = build_cli().try_get_matches_from(["thunderhawk", "--resolve", "api.example.com:not-an-ip"]).unwrap();
+        let err = process_resolve_overrides(&matches).unwrap_err();
+        assert!(err.contains("Invalid address 'not-an-ip'"), "{}", err);
+    }
+
+    #[test]
+    fn test_empty_name_is_rejected() {
+        let err = headers_from(&[" :value"]).unwrap_err();
+        assert!(err.contains("Header name is empty"), "{}", err);
+    }
+}
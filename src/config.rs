@@ -1,18 +1,107 @@
 use config::ConfigError;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, env, path::PathBuf};
+use std::{collections::HashMap, env, net::IpAddr, path::PathBuf, sync::Arc, time::Duration};
 use glob::glob;
 use std::fs::File;
-use crate::utils::interpolate::interpolate_config;
+use crate::utils::{http_client::HttpClientConfig, interpolate::interpolate_config, template::BodyTemplate};
 use anyhow::{Context, Result};
+use bytes::Bytes;
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
 pub enum HttpMethod {
-    GET, POST, PUT, DELETE, // Add more as needed
+    #[default]
+    GET, POST, PUT, PATCH, DELETE, // Add more as needed
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// What kind of check an `ApiConfig` describes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckType {
+    /// A regular HTTP request (the default).
+    Http,
+    /// A TCP connect to the `host:port` in `url`.
+    Tcp,
+    /// A DNS lookup of the host in `url`.
+    Dns,
+    /// Repeated TCP connects to the `host:port` in `url`, reporting min/avg/max latency.
+    Ping,
+}
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 pub struct LoadTestConfig {
+    pub initial_load: Option<usize>,
+    pub max_load: Option<usize>,
+    pub spawn_rate: Option<usize>,
+    /// How many more times a failed load test is run. An attempt fails when any of its requests
+    /// failed, none of them succeeded or its p95 breached `response_time_threshold_ms`.
+    pub retry_count: Option<usize>,
+    pub max_duration_secs: Option<usize>,
+    /// Wall-clock budget, measured from the first attempt, after which no further retries start.
+    /// When every attempt failed, the results of the one with the fewest failed requests are kept.
+    pub overall_deadline_secs: Option<usize>,
+    /// Upper bounds, in milliseconds, of the latency histogram buckets.
+    pub latency_buckets_ms: Option<Vec<u128>>,
+    /// 95th percentile response time, in milliseconds, above which the load test is flagged as breached
+    /// and fails.
+    pub response_time_threshold_ms: Option<u64>,
+    /// Response time percentiles to report, e.g. `[50.0, 99.9]`.
+    pub report_percentiles: Option<Vec<f64>>,
+    /// Fewest responses for the percentiles to be considered reliable. Defaults to `DEFAULT_MIN_SAMPLES`.
+    pub min_samples: Option<usize>,
+    /// Staged load as `(seconds, target users)` points, interpolated linearly in between. Each
+    /// second, users still running are topped up to the target, so concurrency falls with it too.
+    /// When set, it replaces `initial_load`, `max_load` and `spawn_rate`, and runs for as long as the schedule
+    /// unless `max_duration_secs` cuts it short.
+    pub load_schedule: Option<Vec<(usize, usize)>>,
+    /// A label stored on the results, to tell runs apart when comparing them. Usually set per
+    /// run through the webhook's `run_label`.
+    pub run_label: Option<String>,
+    /// Most spawned users that may be outstanding at once. A tick at the cap spawns no one, so a
+    /// slow target backs the ramp off instead of piling up waiting tasks.
+    pub max_in_flight: Option<usize>,
+    /// Requests to start per second, paced evenly for `max_duration_secs`. Each request runs as
+    /// its own user, so concurrency grows as the target slows down; `initial_load`, `max_load`,
+    /// `spawn_rate` and `load_schedule` are ignored, while `max_in_flight` still caps it.
+    pub target_rps: Option<f64>,
+    /// Latency budget, in milliseconds, past which a request is abandoned without reading its
+    /// body and recorded as a slow failure. Shorter than the client timeout to be useful.
+    pub abandon_after_ms: Option<u64>,
+}
+
+/// Latency histogram bucket bounds used when `latency_buckets_ms` is not configured.
+pub const DEFAULT_LATENCY_BUCKETS_MS: [u128; 10] = [10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+/// Fewest responses for load test percentiles to be marked reliable when `min_samples` is not configured.
+pub const DEFAULT_MIN_SAMPLES: usize = 30;
+
+/// Warmup runs a learned response time threshold is based on when `learn_cycles` is not configured.
+pub const DEFAULT_LEARN_CYCLES: usize = 10;
+
+/// How long a task's open circuit skips it when `circuit_cooldown_secs` is not configured.
+pub const DEFAULT_CIRCUIT_COOLDOWN_SECS: u64 = 60;
+
+/// Multiple of the learned baseline p95 allowed when `threshold_factor` is not configured.
+pub const DEFAULT_THRESHOLD_FACTOR: f64 = 1.5;
+
+/// Percentiles reported when `report_percentiles` is not configured.
+pub const DEFAULT_REPORT_PERCENTILES: [f64; 4] = [50.0, 90.0, 95.0, 99.0];
+
+impl LoadTestConfig {
+    /// The configured latency bucket bounds, or the defaults.
+    pub fn latency_buckets(&self) -> &[u128] {
+        self.latency_buckets_ms.as_deref().unwrap_or(&DEFAULT_LATENCY_BUCKETS_MS)
+    }
+
+    /// The configured percentiles to report, or the defaults.
+    pub fn report_percentiles(&self) -> &[f64] {
+        self.report_percentiles.as_deref().unwrap_or(&DEFAULT_REPORT_PERCENTILES)
+    }
+}
+
+/// Per-run replacements for `LoadTestConfig` values; unset fields keep the configured value.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LoadTestOverride {
     pub initial_load: Option<usize>,
     pub max_load: Option<usize>,
     pub spawn_rate: Option<usize>,
@@ -20,6 +109,18 @@ pub struct LoadTestConfig {
     pub max_duration_secs: Option<usize>,
 }
 
+impl LoadTestConfig {
+    /// Replaces every value the override sets.
+    pub fn apply_override(&mut self, load_test_override: &LoadTestOverride) {
+        let LoadTestOverride { initial_load, max_load, spawn_rate, retry_count, max_duration_secs } = load_test_override;
+        self.initial_load = initial_load.or(self.initial_load);
+        self.max_load = max_load.or(self.max_load);
+        self.spawn_rate = spawn_rate.or(self.spawn_rate);
+        self.retry_count = retry_count.or(self.retry_count);
+        self.max_duration_secs = max_duration_secs.or(self.max_duration_secs);
+    }
+}
+
 impl Default for LoadTestConfig {
     fn default() -> Self {
         LoadTestConfig {
@@ -28,29 +129,262 @@ impl Default for LoadTestConfig {
             spawn_rate: Some(1),
             retry_count: Some(0),
             max_duration_secs: Some(60),
+            overall_deadline_secs: None,
+            latency_buckets_ms: None,
+            response_time_threshold_ms: None,
+            report_percentiles: None,
+            min_samples: None,
+            load_schedule: None,
+            run_label: None,
+            max_in_flight: None,
+            target_rps: None,
+            abandon_after_ms: None,
         }
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 pub struct ApiConfig {
     pub name: String,
     pub task_order: Option<usize>,
+    /// The URL requested. May be left out when `urls` is set, in which case it becomes the
+    /// first of `urls` at load time.
+    #[serde(default)]
     pub url: String,
+    /// Replica URLs a load test cycles through round-robin. Takes precedence over `url` when set.
+    pub urls: Option<Vec<String>>,
     pub headers: HashMap<String, String>,
+    /// Headers whose values are read from environment variables when each request is built,
+    /// mapping header name to variable name. Headers whose variable is unset are left out.
+    pub headers_from_env: Option<HashMap<String, String>>,
+    /// Headers whose value is picked at random from a list of candidates for each request, e.g. to
+    /// spread load tests across cache keys. Repeating a candidate makes it proportionally more likely.
+    pub random_headers: Option<HashMap<String, Vec<String>>>,
     pub expected_field: String,
+    /// Media type a successful response must declare in `Content-Type`, e.g. `application/json`.
+    /// Matched as a case-insensitive prefix of the declared type, ignoring parameters like `charset`.
+    pub expected_content_type: Option<String>,
+    /// Response time, in milliseconds, above which a task fails. `response_time_fail_ms` takes
+    /// precedence; zero disables the check.
     pub response_time_threshold: u64,
+    /// Response time, in milliseconds, above which a task is recorded as "DEGRADED" without failing.
+    pub response_time_warn_ms: Option<u64>,
+    /// Response time, in milliseconds, above which a task is recorded as "ERROR".
+    pub response_time_fail_ms: Option<u64>,
+    /// When true, the first `learn_cycles` successful runs measure a baseline p95, and later runs
+    /// fail when slower than `baseline_p95 * threshold_factor`, on top of the configured limits.
+    pub learn_threshold: Option<bool>,
+    /// Number of warmup runs the baseline is learned from. Defaults to `DEFAULT_LEARN_CYCLES`.
+    pub learn_cycles: Option<usize>,
+    /// Multiple of the learned baseline p95 a response may take. Defaults to `DEFAULT_THRESHOLD_FACTOR`.
+    pub threshold_factor: Option<f64>,
     pub method: HttpMethod,
     pub body: Option<String>,
     pub body_file: Option<String>,
+    /// Whether `${VAR}` placeholders in a UTF-8 `body_file` are substituted. Defaults to true;
+    /// set to false for large or binary files that must be sent untouched.
+    pub interpolate_body_file: Option<bool>,
+    /// Fields sent as an `application/x-www-form-urlencoded` body. Can't be combined with `body` or `body_file`.
+    pub form: Option<HashMap<String, String>>,
+    /// Request bodies a load test cycles through, one per request, so the target can't serve
+    /// repeats from a cache. Replaces `body`; a plain task sends the first variant.
+    pub body_variants: Option<Vec<String>>,
+    /// Path to a Handlebars template rendered into the body of every request, load test requests
+    /// included. See `BodyTemplate` for the values it can use.
+    pub body_template: Option<String>,
+    /// Values available to `body_template` as `vars`, e.g. lists to loop over.
+    pub body_template_vars: Option<serde_json::Value>,
+    /// Names of the environment variables `body_template` can read as `env`; no others are exposed.
+    pub body_template_env: Option<Vec<String>>,
+    /// Whether a `Content-Type` is guessed from the request body when `headers` doesn't set one.
+    /// Defaults to true.
+    pub auto_content_type: Option<bool>,
     pub load_test: Option<bool>,
     pub load_test_config: Option<LoadTestConfig>,
+    /// When true, a snippet of the response body is stored alongside a failed task result.
+    pub capture_body_on_error: Option<bool>,
+    /// Upper bound, in bytes, on the captured error body snippet. Defaults to 1024.
+    pub error_body_max_bytes: Option<usize>,
+    /// When true, a successful response whose body is empty or only whitespace is recorded as an error.
+    pub fail_on_empty_body: Option<bool>,
+    /// When true, the response ETag is sent back as `If-None-Match` on the next run, and a 304
+    /// counts as success with the content recorded as unchanged.
+    pub track_etag: Option<bool>,
+    /// When true, every POST, PUT and PATCH request carries a freshly generated `Idempotency-Key` header.
+    pub generate_idempotency_key: Option<bool>,
+    /// Set to false to keep an API in the config without monitoring it. Defaults to true.
+    pub enabled: Option<bool>,
+    /// Status codes that count as success for tasks and load tests. Defaults to any 2xx.
+    pub expected_status_codes: Option<Vec<u16>>,
+    /// How many times a task retries after a `429 Too Many Requests` response. Defaults to 0.
+    pub retry_count: Option<usize>,
+    /// Relative share of virtual users sent to this API in a weighted load test. Defaults to 1.
+    pub weight: Option<u32>,
+    /// GraphQL query to POST as `{"query": ...}`. Takes precedence over `method`, `body` and `body_file`.
+    pub graphql_query: Option<String>,
+    /// Variables sent alongside `graphql_query`.
+    pub graphql_variables: Option<serde_json::Value>,
+    /// The kind of check to run. Defaults to an HTTP request.
+    pub check_type: Option<CheckType>,
+    /// Number of connects made by a ping check. Defaults to 4.
+    pub ping_count: Option<usize>,
+    /// Consecutive failures after which an HTTP task's circuit opens: the task is skipped, and
+    /// recorded as "CIRCUIT_OPEN", until `circuit_cooldown_secs` pass and a probe run is let through.
+    pub circuit_break_after: Option<usize>,
+    /// How long an open circuit skips the task. Defaults to `DEFAULT_CIRCUIT_COOLDOWN_SECS`.
+    pub circuit_cooldown_secs: Option<u64>,
+    /// The request body, resolved once from `body` or `body_file` when monitors are built.
+    #[serde(skip)]
+    pub resolved_body: Option<Bytes>,
+    /// `body_template`, compiled when the config is validated.
+    #[serde(skip)]
+    pub compiled_body_template: Option<BodyTemplate>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+impl ApiConfig {
+    /// One copy of this config per `body_variants` entry, each sending that body, or just this
+    /// config when there are no variants.
+    pub fn body_variant_configs(self: &Arc<Self>) -> Vec<Arc<ApiConfig>> {
+        match self.body_variants.as_deref() {
+            Some(variants) if !variants.is_empty() => variants.iter()
+                .map(|body| Arc::new(ApiConfig { resolved_body: Some(Bytes::from(body.clone())), ..(**self).clone() }))
+                .collect(),
+            _ => vec![self.clone()],
+        }
+    }
+
+    /// Whether the API is monitored at all; only an explicit `enabled: false` turns it off.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// The URLs requests are sent to: `urls` when configured, otherwise just `url`.
+    pub fn target_urls(&self) -> Vec<String> {
+        match &self.urls {
+            Some(urls) if !urls.is_empty() => urls.clone(),
+            _ => vec![self.url.clone()],
+        }
+    }
+
+    /// Whether `status` counts as success under `expected_status_codes`.
+    pub fn is_expected_status(&self, status: u16) -> bool {
+        is_expected_status(self.expected_status_codes.as_deref(), status)
+    }
+
+    /// Whether `body` contains `expected_field`: a JSON Pointer (RFC 6901) such as `/data/0/id`
+    /// when it starts with `/`, otherwise a top-level key. An empty `expected_field` always matches.
+    pub fn has_expected_field(&self, body: &serde_json::Value) -> bool {
+        let field = self.expected_field.as_str();
+        if field.is_empty() {
+            true
+        } else if field.starts_with('/') {
+            body.pointer(field).is_some()
+        } else {
+            body.get(field).is_some()
+        }
+    }
+
+    /// Whether a response's `Content-Type` satisfies `expected_content_type`. Always true when none is expected.
+    pub fn has_expected_content_type(&self, content_type: Option<&str>) -> bool {
+        let Some(expected) = &self.expected_content_type else {
+            return true;
+        };
+        let media_type = |value: &str| value.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        content_type.is_some_and(|content_type| media_type(content_type).starts_with(&media_type(expected)))
+    }
+
+    /// The response time above which a task fails: `response_time_fail_ms`, falling back to
+    /// `response_time_threshold`. `None` when the resulting limit is zero.
+    pub fn response_time_fail_limit(&self) -> Option<u64> {
+        Some(self.response_time_fail_ms.unwrap_or(self.response_time_threshold)).filter(|limit| *limit > 0)
+    }
+
+    /// How long an open circuit skips the task before letting a probe run through.
+    pub fn circuit_cooldown(&self) -> Duration {
+        Duration::from_secs(self.circuit_cooldown_secs.unwrap_or(DEFAULT_CIRCUIT_COOLDOWN_SECS))
+    }
+
+    /// "OK", "DEGRADED" (over the warn threshold) or "ERROR" (over `fail_limit`, usually
+    /// `response_time_fail_limit`) for a response time.
+    pub fn response_time_status(&self, response_time: u64, fail_limit: Option<u64>) -> &'static str {
+        if fail_limit.is_some_and(|limit| response_time > limit) {
+            "ERROR"
+        } else if self.response_time_warn_ms.is_some_and(|limit| response_time > limit) {
+            "DEGRADED"
+        } else {
+            "OK"
+        }
+    }
+}
+
+/// Whether `status` is one of `expected_status_codes`, or any 2xx when none are configured.
+pub fn is_expected_status(expected_status_codes: Option<&[u16]>, status: u16) -> bool {
+    match expected_status_codes {
+        Some(codes) => codes.contains(&status),
+        None => (200..300).contains(&status),
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
 pub struct Workflow {
     pub name: String, // Add this to identify each workflow
     pub apis: Vec<ApiConfig>,
+    /// When set, the load-test-enabled APIs are driven together as one weighted mix
+    /// using this configuration instead of being load tested one by one.
+    pub weighted_load_test: Option<LoadTestConfig>,
+    /// Upper bound, in seconds, on a single run of the workflow. Unfinished tasks are recorded as
+    /// "TIMEOUT", load tests among the load test results and everything else among the task results.
+    pub max_run_secs: Option<usize>,
+    /// Cap on outbound HTTP requests per second across the whole workflow. Every task request,
+    /// 429 retry and load test request waits its turn; TCP, DNS and ping checks aren't throttled.
+    pub max_requests_per_second: Option<f64>,
+    /// Number of task failures tolerated before the workflow as a whole counts as failed. Defaults to 0.
+    pub max_task_failures: Option<usize>,
+    /// When true, load tests run one at a time in `task_order` sequence, even within an order group.
+    pub sequential_load_tests: Option<bool>,
+    /// Pause, in milliseconds, between one `task_order` group finishing and the next starting.
+    pub inter_group_delay_ms: Option<u64>,
+    /// A request checked before each run; when it doesn't pass, the run is skipped and recorded as "SKIPPED".
+    pub gate: Option<GateConfig>,
+    /// How success and latency are weighed in the workflow's health score.
+    pub health_score_weights: Option<HealthScoreWeights>,
+}
+
+/// The relative weights of success rate and latency in a workflow's health score.
+#[derive(Debug, Deserialize, Clone, Default, JsonSchema)]
+pub struct HealthScoreWeights {
+    /// Weight of the success rate. Defaults to 0.7.
+    pub success: Option<f64>,
+    /// Weight of the latency score. Defaults to 0.3.
+    pub latency: Option<f64>,
+}
+
+impl HealthScoreWeights {
+    /// The configured success weight, or the default.
+    pub fn success(&self) -> f64 {
+        self.success.unwrap_or(DEFAULT_SUCCESS_WEIGHT)
+    }
+
+    /// The configured latency weight, or the default.
+    pub fn latency(&self) -> f64 {
+        self.latency.unwrap_or(DEFAULT_LATENCY_WEIGHT)
+    }
+}
+
+const DEFAULT_SUCCESS_WEIGHT: f64 = 0.7;
+const DEFAULT_LATENCY_WEIGHT: f64 = 0.3;
+
+/// A feature flag or maintenance check a workflow must pass before it runs.
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+pub struct GateConfig {
+    /// The URL requested with GET.
+    pub url: String,
+    /// The status code the gate must answer with. Any 2xx when unset.
+    pub expected_status: Option<u16>,
+    /// A field that must be present in the JSON body: a top-level key, or a JSON Pointer when it starts with `/`.
+    pub expected_field: Option<String>,
+    /// The value `expected_field` must hold, e.g. `true` for a feature flag.
+    pub expected_value: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -58,8 +392,67 @@ pub struct Settings {
     pub monitoring_interval_seconds: u64,
     pub log_level: String,
     pub http_timeout_seconds: u64,
+    /// Timeout, in seconds, for establishing a connection, separate from `http_timeout_seconds`.
+    pub http_connect_timeout_seconds: Option<u64>,
     pub http_proxy_url: Option<String>,
-    pub http_default_headers: HashMap<String, String>,
+    /// Headers sent with every request; a key may appear more than once.
+    pub http_default_headers: Vec<(String, String)>,
+    /// When true, every outbound request carries a freshly generated request ID.
+    pub inject_request_id: Option<bool>,
+    /// Name of the header carrying the request ID. Defaults to `X-Request-Id`.
+    pub request_id_header: Option<String>,
+    /// Delay, in seconds, between triggering monitoring and the first request going out.
+    pub start_delay_secs: Option<u64>,
+    /// Custom `User-Agent` for outbound requests. Defaults to `thunderhawk/<version>`.
+    pub user_agent: Option<String>,
+    /// Upper bound, in milliseconds, on a random per-workflow delay applied at the start of each run.
+    pub workflow_start_jitter_ms: Option<u64>,
+    /// Maximum number of workflows run at the same time; the rest wait their turn. Unbounded by default.
+    pub max_concurrent_workflows: Option<usize>,
+    /// Maximum number of response body bytes read per request; the rest is discarded. Unbounded by default.
+    pub max_response_bytes: Option<usize>,
+    /// Whether HTTP connections are kept alive and reused. Defaults to true.
+    pub reuse_connections: Option<bool>,
+    /// DNS overrides as `(host, address)` pairs, applied to every request.
+    pub resolve: Option<Vec<(String, String)>>,
+    /// Upper bound on stored task results, and separately on load test results; the least
+    /// recently updated are evicted past it. Unbounded by default.
+    pub max_results_entries: Option<usize>,
+    /// Oldest TLS version outbound connections may use, e.g. "1.2".
+    pub min_tls_version: Option<String>,
+    /// Whether compressed responses are requested and decompressed. Defaults to true.
+    pub accept_compression: Option<bool>,
+    /// Bearer token required on the server's workflow control routes. Unset leaves them open.
+    pub api_token: Option<String>,
+    /// Hostnames, IP addresses and CIDR ranges that task URLs may target. Unset allows any host.
+    pub allowed_hosts: Option<Vec<String>>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            monitoring_interval_seconds: 60,
+            log_level: "info".to_string(),
+            http_timeout_seconds: 20,
+            http_connect_timeout_seconds: None,
+            http_proxy_url: None,
+            http_default_headers: Vec::new(),
+            inject_request_id: None,
+            request_id_header: None,
+            start_delay_secs: None,
+            user_agent: None,
+            workflow_start_jitter_ms: None,
+            max_concurrent_workflows: None,
+            max_response_bytes: None,
+            reuse_connections: None,
+            resolve: None,
+            max_results_entries: None,
+            min_tls_version: None,
+            accept_compression: None,
+            api_token: None,
+            allowed_hosts: None,
+        }
+    }
 }
 
 impl Settings {
@@ -67,20 +460,160 @@ impl Settings {
         env::set_var("RUST_LOG", &self.log_level);
         env_logger::init();
     }
+
+    /// Returns the header name to use for request IDs, or `None` when injection is disabled.
+    pub fn request_id_header(&self) -> Option<&str> {
+        if self.inject_request_id.unwrap_or(false) {
+            Some(self.request_id_header.as_deref().unwrap_or("X-Request-Id"))
+        } else {
+            None
+        }
+    }
+
+    /// Builds the HTTP client configuration described by these settings.
+    pub fn http_client_config(&self) -> HttpClientConfig {
+        HttpClientConfig {
+            timeout_seconds: self.http_timeout_seconds,
+            connect_timeout_seconds: self.http_connect_timeout_seconds,
+            proxy_url: self.http_proxy_url.clone(),
+            default_headers: self.http_default_headers.clone(),
+            user_agent: self.user_agent.clone(),
+            reuse_connections: self.reuse_connections,
+            resolve: self.resolve.clone(),
+            min_tls_version: self.min_tls_version.clone(),
+            accept_compression: self.accept_compression,
+            decompress: None,
+            allowed_hosts: self.allowed_hosts.clone(),
+        }
+    }
+
+    /// Whether `url` targets a host in `allowed_hosts`; every host is allowed without an allowlist.
+    pub fn is_host_allowed(&self, url: &str) -> bool {
+        self.allowed_hosts.as_ref().is_none_or(|allowed_hosts| host_allowed(allowed_hosts, url))
+    }
+
+    /// Returns the configured start delay, or `None` when monitoring should start immediately.
+    pub fn start_delay(&self) -> Option<Duration> {
+        self.start_delay_secs
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+
+    /// Fails with a message naming the first task, or gate, whose URL targets a host outside
+    /// `allowed_hosts`. Hostnames are compared as written, not resolved, so a hostname is only
+    /// allowed by listing it, and a CIDR range only allows URLs that use an IP address.
+    pub fn check_allowed_hosts(&self, workflows: &[Workflow]) -> Result<(), String> {
+        let Some(allowed_hosts) = &self.allowed_hosts else {
+            return Ok(());
+        };
+        for entry in allowed_hosts {
+            if let Some((address, prefix)) = entry.split_once('/') {
+                let max_prefix = match address.parse::<IpAddr>() {
+                    Ok(IpAddr::V4(_)) => 32,
+                    Ok(IpAddr::V6(_)) => 128,
+                    Err(_) => return Err(format!("Invalid allowed host '{}': '{}' is not an IP address.", entry, address)),
+                };
+                if prefix.parse::<u8>().map_or(true, |prefix| prefix > max_prefix) {
+                    return Err(format!("Invalid allowed host '{}': the prefix length must be between 0 and {}.", entry, max_prefix));
+                }
+            }
+        }
+
+        for workflow in workflows {
+            let targets = workflow.apis.iter()
+                .flat_map(|api| api.target_urls().into_iter().map(move |url| (api.name.as_str(), url)))
+                .chain(workflow.gate.iter().map(|gate| ("gate", gate.url.clone())));
+            for (name, url) in targets {
+                if !host_allowed(allowed_hosts, &url) {
+                    return Err(format!("'{}' in workflow '{}' targets {}, whose host '{}' is not in the allowed hosts.", name, workflow.name, url, target_host(&url)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the maximum workflow start jitter, or `None` when workflows start together.
+    pub fn workflow_start_jitter(&self) -> Option<Duration> {
+        self.workflow_start_jitter_ms
+            .filter(|ms| *ms > 0)
+            .map(Duration::from_millis)
+    }
+}
+
+
+/// Whether `url` targets a host matching one of the `allowed_hosts` entries.
+pub(crate) fn host_allowed(allowed_hosts: &[String], url: &str) -> bool {
+    let host = target_host(url);
+    allowed_hosts.iter().any(|entry| host_matches(&host, entry))
+}
+
+/// The host a task URL targets: the host of a URL with a scheme, or else the `host[:port]`
+/// that TCP, ping and DNS checks accept, without its port.
+fn target_host(url: &str) -> String {
+    if url.contains("://") {
+        if let Some(host) = reqwest::Url::parse(url).ok().and_then(|parsed| parsed.host_str().map(str::to_string)) {
+            return host.trim_start_matches('[').trim_end_matches(']').to_string();
+        }
+    }
+    match url.rsplit_once(':') {
+        Some((host, port)) if !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()) => host.trim_start_matches('[').trim_end_matches(']').to_string(),
+        _ => url.to_string(),
+    }
+}
+
+/// Whether `host` is the hostname or IP address `entry`, or an IP address in the CIDR range `entry`.
+fn host_matches(host: &str, entry: &str) -> bool {
+    let Some((network, prefix)) = entry.split_once('/') else {
+        return match (host.parse::<IpAddr>(), entry.parse::<IpAddr>()) {
+            (Ok(host), Ok(entry)) => host == entry,
+            _ => host.eq_ignore_ascii_case(entry),
+        };
+    };
+    let (Ok(host), Ok(network), Ok(prefix)) = (host.parse::<IpAddr>(), network.parse::<IpAddr>(), prefix.parse::<u32>()) else {
+        return false;
+    };
+    match (host, network) {
+        (IpAddr::V4(host), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix.min(32)).unwrap_or(0);
+            u32::from(host) & mask == u32::from(network) & mask
+        },
+        (IpAddr::V6(host), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix.min(128)).unwrap_or(0);
+            u128::from(host) & mask == u128::from(network) & mask
+        },
+        _ => false,
+    }
 }
 
+/// Builds a JSON Schema describing a workflow config file, for editor autocomplete and validation.
+/// Derived from `Workflow` and the types it contains, so it always matches what is accepted.
+pub fn workflow_schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Workflow)).expect("a JSON Schema always serializes")
+}
+
+/// Pattern, relative to the config directory, used to find workflow files when no `--config-glob` is given.
+pub const DEFAULT_CONFIG_GLOB: &str = "*.yml";
 
-pub async fn load_workflow(config_file: Option<String>, config_dir: Option<String>) -> Result<Vec<Workflow>, Box<dyn std::error::Error>> {
+/// Loads workflows from `config_file`, or else from every file in the config directory matching
+/// one of `config_globs` (`DEFAULT_CONFIG_GLOB` when empty). A file matched by several globs is loaded once.
+pub async fn load_workflow(config_file: Option<String>, config_dir: Option<String>, config_globs: &[String]) -> Result<Vec<Workflow>, Box<dyn std::error::Error>> {
     let mut workflows = Vec::new();
 
     let config_paths = if let Some(file_path) = config_file {
         vec![PathBuf::from(file_path)]
     } else {
         let config_directory = config_dir.unwrap_or_else(|| env::var("CONFIG_DIR").unwrap_or_else(|_| "./config".to_string()));
-        glob(&format!("{}/*.yml", config_directory))
-            .map_err(|e| anyhow::anyhow!("Failed to read glob pattern: {}", e))?
-            .filter_map(Result::ok)
-            .collect::<Vec<PathBuf>>()
+        let default_globs = [DEFAULT_CONFIG_GLOB.to_string()];
+        let config_globs = if config_globs.is_empty() { &default_globs[..] } else { config_globs };
+        let mut paths = Vec::new();
+        for config_glob in config_globs {
+            paths.extend(glob(&format!("{}/{}", config_directory, config_glob))
+                .map_err(|e| anyhow::anyhow!("Failed to read glob pattern '{}': {}", config_glob, e))?
+                .filter_map(Result::ok));
+        }
+        paths.sort();
+        paths.dedup();
+        paths
     };
 
     // Process each configuration file...
@@ -100,13 +633,319 @@ pub async fn load_workflow(config_file: Option<String>, config_dir: Option<Strin
 fn validate_settings(workflow: &mut Workflow) -> Result<(), ConfigError> {
     for api in workflow.apis.iter_mut() {
         if api.url.is_empty() {
-            return Err(ConfigError::Message(format!("API URL is missing in the configuration for '{}'.", api.name)));
+            match api.urls.as_ref().and_then(|urls| urls.first()) {
+                Some(first) => api.url = first.clone(),
+                None => return Err(ConfigError::Message(format!("API URL is missing in the configuration for '{}': set url or urls.", api.name))),
+            }
+        }
+        if api.form.is_some() && (api.body.is_some() || api.body_file.is_some()) {
+            return Err(ConfigError::Message(format!("'{}' can't set form together with body or body_file.", api.name)));
+        }
+        if let Some(path) = &api.body_template {
+            if api.body.is_some() || api.body_file.is_some() || api.body_variants.is_some() || api.form.is_some() {
+                return Err(ConfigError::Message(format!("'{}' can't set body_template together with body, body_file, body_variants or form.", api.name)));
+            }
+            let env_names = api.body_template_env.as_deref().unwrap_or_default();
+            api.compiled_body_template = Some(BodyTemplate::from_file(path, env_names).map_err(ConfigError::Message)?);
+        }
+        // Like a template, a body file that can't be read fails loading rather than every run.
+        // Opening it is enough to check; the body itself is only read when monitors are built.
+        if let Some(path) = &api.body_file {
+            let metadata = std::fs::File::open(path).and_then(|file| file.metadata())
+                .map_err(|e| ConfigError::Message(format!("Error reading request body from file '{}' for '{}': {}", path, api.name, e)))?;
+            if !metadata.is_file() {
+                return Err(ConfigError::Message(format!("Error reading request body from file '{}' for '{}': not a file", path, api.name)));
+            }
+        }
+        if let Some(variants) = &api.body_variants {
+            if variants.is_empty() {
+                return Err(ConfigError::Message(format!("body_variants for '{}' needs at least one body.", api.name)));
+            }
+            if api.form.is_some() || api.body_file.is_some() {
+                return Err(ConfigError::Message(format!("'{}' can't set body_variants together with form or body_file.", api.name)));
+            }
+        }
+        if api.threshold_factor.is_some_and(|factor| !factor.is_finite() || factor <= 0.0) {
+            return Err(ConfigError::Message(format!("threshold_factor for '{}' must be a positive number.", api.name)));
+        }
+        if api.circuit_break_after.is_some() && !matches!(api.check_type, None | Some(CheckType::Http)) {
+            return Err(ConfigError::Message(format!("circuit_break_after for '{}' only applies to HTTP tasks.", api.name)));
+        }
+        if api.circuit_break_after == Some(0) {
+            return Err(ConfigError::Message(format!("circuit_break_after for '{}' must be at least 1.", api.name)));
         }
         if api.load_test.unwrap_or(false) && api.load_test_config.is_none() {
             log::warn!("Missing load_test_config for '{}'. Using default values.", api.name);
             api.load_test_config = Some(LoadTestConfig::default());
         }
+        if let Some(load_test_config) = api.load_test_config.as_mut() {
+            validate_load_test_config(&api.name, load_test_config)?;
+        }
+    }
+    if let Some(load_test_config) = workflow.weighted_load_test.as_mut() {
+        validate_load_test_config(&format!("weighted load test of {}", workflow.name), load_test_config)?;
+    }
+    if let Some(rate) = workflow.max_requests_per_second {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(ConfigError::Message(format!("max_requests_per_second in '{}' must be a positive number.", workflow.name)));
+        }
+    }
+    if let Some(weights) = &workflow.health_score_weights {
+        let (success, latency) = (weights.success(), weights.latency());
+        if !(success.is_finite() && latency.is_finite() && success >= 0.0 && latency >= 0.0 && success + latency > 0.0) {
+            return Err(ConfigError::Message(format!("health_score_weights in '{}' must be non-negative numbers, not both zero.", workflow.name)));
+        }
+    }
+    if workflow.weighted_load_test.is_some() {
+        let weights: Vec<u32> = workflow.apis.iter()
+            .filter(|api| api.load_test.unwrap_or(false))
+            .map(|api| api.weight.unwrap_or(1))
+            .collect();
+        if weights.iter().all(|weight| *weight == 0) {
+            return Err(ConfigError::Message(format!("Weighted load test in '{}' needs at least one load test API with a non-zero weight.", workflow.name)));
+        }
     }
     Ok(())
 }
 
+/// Rejects a zero spawn rate and clamps `initial_load` and `spawn_rate` to `max_load` with a warning.
+fn validate_load_test_config(name: &str, config: &mut LoadTestConfig) -> Result<(), ConfigError> {
+    if config.spawn_rate == Some(0) {
+        return Err(ConfigError::Message(format!("spawn_rate for '{}' must be at least 1.", name)));
+    }
+    if config.max_in_flight == Some(0) {
+        return Err(ConfigError::Message(format!("max_in_flight for '{}' must be at least 1.", name)));
+    }
+    if config.target_rps.is_some_and(|target_rps| !(target_rps.is_finite() && target_rps > 0.0)) {
+        return Err(ConfigError::Message(format!("target_rps for '{}' must be a positive number.", name)));
+    }
+    if config.load_schedule.as_ref().is_some_and(|schedule| schedule.is_empty()) {
+        return Err(ConfigError::Message(format!("load_schedule for '{}' needs at least one point.", name)));
+    }
+    let Some(max_load) = config.max_load else {
+        return Ok(());
+    };
+    if let Some(initial_load) = config.initial_load.filter(|initial_load| *initial_load > max_load) {
+        log::warn!("initial_load {} for '{}' exceeds max_load {}; clamping it to {}.", initial_load, name, max_load, max_load);
+        config.initial_load = Some(max_load);
+    }
+    if let Some(spawn_rate) = config.spawn_rate.filter(|spawn_rate| *spawn_rate > max_load) {
+        log::warn!("spawn_rate {} for '{}' exceeds max_load {}; clamping it to {}.", spawn_rate, name, max_load, max_load);
+        config.spawn_rate = Some(max_load);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load_test_workflow(load_test_config: &str) -> Workflow {
+        let yaml = format!(
+            r#"
+name: "Load Workflow"
+apis:
+  - name: "Busy Endpoint"
+    url: "http://localhost/busy"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000
+    load_test: true
+    load_test_config: {}
+"#,
+            load_test_config
+        );
+        serde_yaml::from_str(&yaml).expect("Failed to parse YAML")
+    }
+
+    fn validated_load_test_config(load_test_config: &str) -> LoadTestConfig {
+        let mut workflow = load_test_workflow(load_test_config);
+        validate_settings(&mut workflow).expect("config should be accepted");
+        workflow.apis[0].load_test_config.clone().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_recursive_config_glob_finds_nested_workflows() {
+        let dir = std::env::temp_dir().join(format!("thunderhawk-configs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("teams/payments")).unwrap();
+        let workflow_yaml = |name: &str| format!(
+            "name: \"{}\"\napis:\n  - name: \"Health\"\n    url: \"http://localhost/health\"\n    method: GET\n    headers: {{}}\n    expected_field: \"\"\n    response_time_threshold: 2000\n",
+            name
+        );
+        std::fs::write(dir.join("root.yml"), workflow_yaml("Root")).unwrap();
+        std::fs::write(dir.join("teams/payments/checkout.yml"), workflow_yaml("Checkout")).unwrap();
+        std::fs::write(dir.join("teams/notes.txt"), "not a workflow").unwrap();
+        let config_dir = Some(dir.to_string_lossy().into_owned());
+
+        let names = |workflows: Vec<Workflow>| workflows.into_iter().map(|workflow| workflow.name).collect::<Vec<_>>();
+        let top_level = load_workflow(None, config_dir.clone(), &[]).await.unwrap();
+        let recursive = load_workflow(None, config_dir.clone(), &["**/*.yml".to_string(), "*.yml".to_string()]).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(names(top_level), vec!["Root"]);
+        assert_eq!(names(recursive), vec!["Root", "Checkout"]);
+    }
+
+    fn workflow_targeting(url: &str) -> Workflow {
+        let yaml = format!(
+            "name: \"Guarded\"\napis:\n  - name: \"Health\"\n    url: \"{}\"\n    method: GET\n    headers: {{}}\n    expected_field: \"\"\n    response_time_threshold: 2000\n",
+            url
+        );
+        serde_yaml::from_str(&yaml).expect("Failed to parse YAML")
+    }
+
+    #[test]
+    fn test_allowed_hosts_accept_listed_hostnames_and_cidr_ranges() {
+        let settings = Settings {
+            allowed_hosts: Some(vec!["staging.internal".to_string(), "10.20.0.0/16".to_string(), "fd00::/8".to_string()]),
+            ..Settings::default()
+        };
+
+        for url in ["https://STAGING.internal/health?x=1", "http://10.20.3.4:8080/health", "tcp://10.20.255.1:5432", "staging.internal:443", "http://[fd12::1]/health"] {
+            assert_eq!(settings.check_allowed_hosts(&[workflow_targeting(url)]), Ok(()), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_allowed_hosts_reject_other_hosts() {
+        let settings = Settings {
+            allowed_hosts: Some(vec!["staging.internal".to_string(), "10.20.0.0/16".to_string()]),
+            ..Settings::default()
+        };
+
+        for url in ["https://api.production.com/health", "http://10.21.0.1/health", "https://staging.internal.evil.com/"] {
+            let error = settings.check_allowed_hosts(&[workflow_targeting(url)]).unwrap_err();
+            assert!(error.contains("not in the allowed hosts"), "{}", error);
+        }
+        let invalid = Settings { allowed_hosts: Some(vec!["10.0.0.0/33".to_string()]), ..Settings::default() };
+        assert!(invalid.check_allowed_hosts(&[]).is_err());
+    }
+
+    #[test]
+    fn test_workflow_schema_describes_config_fields() {
+        let schema = workflow_schema();
+        let properties = |definition: &serde_json::Value| -> Vec<String> {
+            definition["properties"].as_object().expect("properties").keys().cloned().collect()
+        };
+
+        let workflow = properties(&schema);
+        let api = properties(&schema["$defs"]["ApiConfig"]);
+        let load_test = properties(&schema["$defs"]["LoadTestConfig"]);
+
+        for field in ["name", "apis", "weighted_load_test", "gate"] {
+            assert!(workflow.contains(&field.to_string()), "Workflow is missing {}", field);
+        }
+        for field in ["url", "method", "headers", "body_template", "load_test_config", "check_type"] {
+            assert!(api.contains(&field.to_string()), "ApiConfig is missing {}", field);
+        }
+        for field in ["max_load", "spawn_rate", "target_rps", "abandon_after_ms"] {
+            assert!(load_test.contains(&field.to_string()), "LoadTestConfig is missing {}", field);
+        }
+        // Fields filled in at load time are not part of the file format.
+        assert!(!api.contains(&"resolved_body".to_string()));
+        assert_eq!(schema["required"], serde_json::json!(["name", "apis"]));
+    }
+
+    #[test]
+    fn test_initial_load_above_max_load_is_clamped() {
+        let config = validated_load_test_config("{ initial_load: 20, max_load: 5, spawn_rate: 1 }");
+        assert_eq!(config.initial_load, Some(5));
+        assert_eq!(config.spawn_rate, Some(1));
+    }
+
+    #[test]
+    fn test_spawn_rate_above_max_load_is_clamped() {
+        let config = validated_load_test_config("{ initial_load: 0, max_load: 5, spawn_rate: 50 }");
+        assert_eq!(config.initial_load, Some(0));
+        assert_eq!(config.spawn_rate, Some(5));
+    }
+
+    #[test]
+    fn test_zero_spawn_rate_is_rejected() {
+        let mut workflow = load_test_workflow("{ initial_load: 0, max_load: 5, spawn_rate: 0 }");
+        assert!(validate_settings(&mut workflow).is_err());
+    }
+
+    #[test]
+    fn test_body_template_syntax_error_fails_validation() {
+        let path = std::env::temp_dir().join(format!("thunderhawk-template-{}.hbs", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "{{#each vars.items}}{{this}}").unwrap();
+        let mut workflow = load_test_workflow("{}");
+        workflow.apis[0].body_template = Some(path.to_string_lossy().into_owned());
+
+        let result = validate_settings(&mut workflow);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(ConfigError::Message(message)) if message.contains("Invalid body template")));
+    }
+
+    #[test]
+    fn test_non_positive_threshold_factor_is_rejected() {
+        for factor in [0.0, -1.5, f64::NAN, f64::INFINITY] {
+            let mut workflow = load_test_workflow("{}");
+            workflow.apis[0].threshold_factor = Some(factor);
+            assert!(validate_settings(&mut workflow).is_err(), "{}", factor);
+        }
+
+        let mut workflow = load_test_workflow("{}");
+        workflow.apis[0].threshold_factor = Some(0.5);
+        assert!(validate_settings(&mut workflow).is_ok());
+    }
+
+    #[test]
+    fn test_circuit_break_after_is_rejected_on_non_http_checks() {
+        for (check_type, accepted) in [(None, true), (Some(CheckType::Http), true), (Some(CheckType::Tcp), false), (Some(CheckType::Dns), false), (Some(CheckType::Ping), false)] {
+            let mut workflow = load_test_workflow("{}");
+            workflow.apis[0].check_type = check_type;
+            workflow.apis[0].circuit_break_after = Some(3);
+            assert_eq!(validate_settings(&mut workflow).is_ok(), accepted, "{:?}", workflow.apis[0].check_type);
+        }
+    }
+
+    #[test]
+    fn test_unreadable_body_file_fails_validation() {
+        let mut workflow = load_test_workflow("{}");
+        workflow.apis[0].body_file = Some("/nonexistent/thunderhawk/body.bin".to_string());
+
+        let result = validate_settings(&mut workflow);
+
+        assert!(matches!(result, Err(ConfigError::Message(message)) if message.contains("/nonexistent/thunderhawk/body.bin")));
+    }
+
+    #[test]
+    fn test_directory_body_file_fails_validation() {
+        let directory = std::env::temp_dir();
+        let mut workflow = load_test_workflow("{}");
+        workflow.apis[0].body_file = Some(directory.display().to_string());
+
+        let result = validate_settings(&mut workflow);
+
+        assert!(matches!(result, Err(ConfigError::Message(message)) if message.ends_with("not a file")));
+    }
+
+    #[test]
+    fn test_url_may_be_left_out_when_urls_is_set() {
+        let yaml = |targets: &str| format!(
+            "name: \"Replicas\"\napis:\n  - name: \"Browse\"\n{}    method: GET\n    headers: {{}}\n    expected_field: \"\"\n    response_time_threshold: 2000\n",
+            targets
+        );
+
+        let mut workflow: Workflow = serde_yaml::from_str(&yaml("    urls: [\"http://replica-a/\", \"http://replica-b/\"]\n")).unwrap();
+        validate_settings(&mut workflow).unwrap();
+        assert_eq!(workflow.apis[0].url, "http://replica-a/");
+        assert_eq!(workflow.apis[0].target_urls(), ["http://replica-a/", "http://replica-b/"]);
+
+        for targets in ["", "    urls: []\n"] {
+            let mut workflow: Workflow = serde_yaml::from_str(&yaml(targets)).unwrap();
+            assert!(validate_settings(&mut workflow).is_err(), "{:?}", targets);
+        }
+    }
+
+    #[test]
+    fn test_consistent_load_test_config_is_unchanged() {
+        let config = validated_load_test_config("{ initial_load: 1, max_load: 10, spawn_rate: 2 }");
+        assert_eq!((config.initial_load, config.max_load, config.spawn_rate), (Some(1), Some(10), Some(2)));
+    }
+}
+
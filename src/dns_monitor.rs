@@ -0,0 +1,161 @@
+use std::{sync::Arc, time::{Duration, Instant}};
+use log::{error, info};
+use reqwest::{Client, Url};
+use tokio::{net::lookup_host, sync::Mutex};
+use crate::{appstate::AppState, config::{ApiConfig, Settings}, factory::{ApiMonitor, MonitorError}, telemetry::MonitorSpan};
+use crate::tasks::{update_app_state, MonitoringData, MonitoringDataType};
+
+/// Resolves a hostname without connecting to it, recording the lookup latency and the resolved addresses.
+pub struct DnsMonitor {
+    /// Configuration whose `url` is either a bare hostname or a URL whose host is resolved.
+    pub api_config: Arc<ApiConfig>,
+    /// A reference to the shared application state for recording monitoring data.
+    pub app_state: Arc<Mutex<AppState>>,
+    /// Global settings; the HTTP timeout doubles as the lookup timeout.
+    pub settings: Arc<Settings>,
+}
+
+#[async_trait::async_trait]
+impl ApiMonitor for DnsMonitor {
+    async fn execute(&self, _client: &Client, workflow_name: &str) -> Result<(), MonitorError> {
+        let mut span = MonitorSpan::start("dns", workflow_name, &self.api_config.name, &self.api_config.url, &self.api_config.method);
+        let host = dns_host(&self.api_config.url);
+        let timeout = Duration::from_secs(self.settings.http_timeout_seconds);
+
+        let start = Instant::now();
+        let result = resolve(&host, timeout).await;
+        let duration = start.elapsed();
+
+        let status = if result.is_ok() { "OK" } else { "ERROR" };
+        let monitoring_data = MonitoringData {
+            api_url: self.api_config.url.clone(),
+            status: status.to_string(),
+            response_time: duration.as_millis() as u64,
+            response_time_micros: duration.as_micros() as u64,
+            status_code: None, // DNS checks have no status code
+            method: self.api_config.method.clone(),
+            resolved_ips: result.as_ref().ok().cloned(),
+            ..Default::default()
+        };
+        update_app_state(&self.app_state, workflow_name, &self.api_config.name, MonitoringDataType::Task, monitoring_data).await;
+        span.record_status(status, None);
+
+        match result {
+            Ok(ips) => {
+                info!("'{}' resolved {} to {:?} in {:?}", self.api_config.name, host, ips, duration);
+                Ok(())
+            },
+            Err(e) => {
+                let error = e.context(format!("'{}' failed to resolve {}", self.api_config.name, host));
+                error!("{}", error);
+                Err(error)
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("DNS check for {}", self.api_config.name)
+    }
+
+    fn response_time_threshold(&self) -> Option<u64> {
+        None
+    }
+
+    fn get_task_order(&self) -> usize {
+        self.api_config.task_order.unwrap_or(usize::MAX)
+    }
+
+    fn api_configs(&self) -> Vec<Arc<ApiConfig>> {
+        vec![self.api_config.clone()]
+    }
+}
+
+/// Extracts the hostname to resolve from a full URL, or returns a bare hostname unchanged.
+fn dns_host(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) if url.contains("://") => parsed.host_str().unwrap_or(url).to_string(),
+        _ => url.to_string(),
+    }
+}
+
+/// Resolves `host` to its distinct IP addresses, in the order the resolver returned them.
+async fn resolve(host: &str, timeout: Duration) -> Result<Vec<String>, MonitorError> {
+    let addresses = match tokio::time::timeout(timeout, lookup_host((host, 0))).await {
+        Ok(Ok(addresses)) => addresses,
+        Ok(Err(e)) => return Err(MonitorError::Request(e.to_string())),
+        Err(_) => return Err(MonitorError::Timeout(format!("timed out after {:?}", timeout))),
+    };
+
+    let mut ips: Vec<String> = Vec::new();
+    for address in addresses {
+        let ip = address.ip().to_string();
+        if !ips.contains(&ip) {
+            ips.push(ip);
+        }
+    }
+    if ips.is_empty() {
+        return Err(MonitorError::Request("no addresses returned".to_string()));
+    }
+    Ok(ips)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dns_config(url: &str) -> ApiConfig {
+        let yaml = format!(
+            r#"
+name: "Resolve API Host"
+url: "{}"
+method: GET
+headers: {{}}
+expected_field: ""
+response_time_threshold: 2000
+check_type: dns
+"#,
+            url
+        );
+        serde_yaml::from_str(&yaml).expect("Failed to parse YAML")
+    }
+
+    async fn run_check(url: &str) -> (Result<(), MonitorError>, MonitoringData) {
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let monitor = DnsMonitor {
+            api_config: Arc::new(dns_config(url)),
+            app_state: app_state.clone(),
+            settings: Arc::new(Settings { http_timeout_seconds: 5, ..Settings::default() }),
+        };
+        let result = monitor.execute(&Client::new(), "Sample Workflow").await;
+
+        let state = app_state.lock().await;
+        let task_data = state.task_monitoring_data.lock().await;
+        (result, task_data["Sample Workflow"]["Resolve API Host"].clone())
+    }
+
+    #[test]
+    fn test_dns_host_accepts_urls_and_bare_hostnames() {
+        assert_eq!(dns_host("https://api.example.com:8443/health"), "api.example.com");
+        assert_eq!(dns_host("api.example.com"), "api.example.com");
+    }
+
+    #[tokio::test]
+    async fn test_localhost_resolves_to_loopback() {
+        let (result, data) = run_check("localhost").await;
+
+        assert!(result.is_ok());
+        assert_eq!(data.status, "OK");
+        let ips = data.resolved_ips.expect("resolved IPs should be recorded");
+        assert!(ips.iter().any(|ip| ip == "127.0.0.1" || ip == "::1"), "unexpected IPs: {:?}", ips);
+    }
+
+    #[tokio::test]
+    async fn test_unresolvable_name_records_error() {
+        // `.invalid` is reserved and never resolves.
+        let (result, data) = run_check("thunderhawk.invalid").await;
+
+        assert!(result.is_err());
+        assert_eq!(data.status, "ERROR");
+        assert_eq!(data.resolved_ips, None);
+    }
+}
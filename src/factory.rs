@@ -1,33 +1,137 @@
 use log::info;
 
 use futures::future::join_all;
-use std::collections::HashMap;
-use std::collections::VecDeque;
-use std::sync::Arc;
+use futures::stream::{self, StreamExt};
+use futures::FutureExt;
+use std::panic::AssertUnwindSafe;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
 use tokio::sync::Mutex;
-use crate::config::{Settings, Workflow};
+use crate::config::{GateConfig, Settings, Workflow};
 use crate::appstate::AppState;
-use crate::loadtest::LoadTest;
-use crate::tasks::Task;
-use crate::utils::http_client::{self, HttpClientConfig};
+use crate::loadtest::{load_test_client, record_load_test_status, LoadTest, LoadTestMonitoringData, WeightedLoadTest};
+use crate::ping_monitor::PingMonitoringData;
+use crate::tasks::{record_task_status, MonitoringData, Task};
+use crate::tcp_monitor::TcpMonitor;
+use crate::dns_monitor::DnsMonitor;
+use crate::ping_monitor::PingMonitor;
+use crate::utils::http_client;
+use crate::utils::interpolate::interpolate_string;
+use crate::utils::template::BodyTemplate;
 use std::{fs, str::FromStr};
+use bytes::Bytes;
 use reqwest::{Client, RequestBuilder};
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
-use crate::config::{ApiConfig, HttpMethod};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use crate::config::{ApiConfig, CheckType, HttpMethod};
 use reqwest::Client as HttpClient;
+use rand::{seq::SliceRandom, Rng};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
 
 
+/// Why a monitor failed, so alerting can tell kinds of failure apart without parsing messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorError {
+    /// The request could not be sent or the target could not be reached.
+    Request(String),
+    /// The target did not answer in time.
+    Timeout(String),
+    /// The target answered, but not as expected, e.g. with an unexpected status code.
+    Assertion(String),
+    /// The monitor's configuration could not be turned into a request.
+    Config(String),
+    /// The monitor panicked while running.
+    Panic(String),
+}
+
+impl MonitorError {
+    /// The description of the failure, without its kind.
+    pub fn message(&self) -> &str {
+        match self {
+            MonitorError::Request(message)
+            | MonitorError::Timeout(message)
+            | MonitorError::Assertion(message)
+            | MonitorError::Config(message)
+            | MonitorError::Panic(message) => message,
+        }
+    }
+
+    /// Prefixes the message with `context`, keeping the kind of failure.
+    pub fn context(self, context: impl std::fmt::Display) -> Self {
+        let wrap = |message: String| format!("{}: {}", context, message);
+        match self {
+            MonitorError::Request(message) => MonitorError::Request(wrap(message)),
+            MonitorError::Timeout(message) => MonitorError::Timeout(wrap(message)),
+            MonitorError::Assertion(message) => MonitorError::Assertion(wrap(message)),
+            MonitorError::Config(message) => MonitorError::Config(wrap(message)),
+            MonitorError::Panic(message) => MonitorError::Panic(wrap(message)),
+        }
+    }
+}
+
+impl std::fmt::Display for MonitorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for MonitorError {}
 
 #[async_trait::async_trait]
 pub trait ApiMonitor {
-    async fn execute(&self, client: &reqwest::Client, workflow_name: &str) -> Result<(), String>;
+    async fn execute(&self, client: &reqwest::Client, workflow_name: &str) -> Result<(), MonitorError>;
     fn describe(&self) -> String;
     fn response_time_threshold(&self) -> Option<u64>; // Threshold in seconds
     fn get_task_order(&self) -> usize;
+    /// The API configurations this monitor exercises, used to record results on its behalf.
+    fn api_configs(&self) -> Vec<Arc<ApiConfig>>;
+    /// Whether this monitor is a load test, which `sequential_load_tests` runs one at a time.
+    fn is_load_test(&self) -> bool {
+        false
+    }
+}
+
+
+type BoxedMonitor = Box<dyn ApiMonitor + Send + Sync>;
+
+/// Header carrying the generated idempotency key of a write request.
+const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// A request ready to be sent, along with any identifiers generated while building it.
+pub struct PreparedRequest {
+    pub builder: RequestBuilder,
+    /// The request ID attached to the request, when request ID injection is enabled.
+    pub request_id: Option<String>,
+    /// The `Idempotency-Key` attached to the request, when key generation is enabled for a write.
+    pub idempotency_key: Option<String>,
 }
 
+impl PreparedRequest {
+    /// Sends `key` as the `Idempotency-Key` in place of the one generated for this request, so
+    /// a retry is recognised as the same operation. Requests without a key are left as they are.
+    pub fn with_idempotency_key(mut self, key: &str) -> Self {
+        if self.idempotency_key.is_some() {
+            let value = HeaderValue::from_str(key).expect("keys taken from an earlier request are valid header values");
+            // `headers` replaces values, where `header` would send both keys.
+            self.builder = self.builder.headers(HeaderMap::from_iter([(IDEMPOTENCY_KEY, value)]));
+            self.idempotency_key = Some(key.to_string());
+        }
+        self
+    }
+}
+
+/// Builds the request `api_config` describes. `settings` decide whether a request ID is attached
+/// and which default headers the client adds, so those aren't overridden.
+pub fn create_request_builder(client: &Client, api_config: &ApiConfig, settings: &Settings) -> Result<PreparedRequest, String> {
+    create_request_builder_for_url(client, api_config, &api_config.url, settings)
+}
 
-pub fn create_request_builder(client: &Client, api_config: &ApiConfig) -> Result<RequestBuilder, String> {
+/// Like `create_request_builder`, but sends the request to `url` instead of `api_config.url`.
+pub fn create_request_builder_for_url(client: &Client, api_config: &ApiConfig, url: &str, settings: &Settings) -> Result<PreparedRequest, String> {
+    // Settings built outside the CLI skip its startup check, so every request is checked here.
+    if !settings.is_host_allowed(url) {
+        return Err(format!("'{}' targets {}, whose host is not in the allowed hosts", api_config.name, url));
+    }
     let mut headers = HeaderMap::new();
     for (key, value) in &api_config.headers {
         match (HeaderName::from_str(key), HeaderValue::from_str(value)) {
@@ -37,44 +141,289 @@ pub fn create_request_builder(client: &Client, api_config: &ApiConfig) -> Result
             _ => return Err(format!("Invalid header: {}: {}", key, value)),
         }
     }
+    for (key, env_var) in api_config.headers_from_env.iter().flatten() {
+        let Ok(value) = std::env::var(env_var) else {
+            log::warn!("Environment variable '{}' for header '{}' of '{}' is not set; leaving the header out", env_var, key, api_config.name);
+            continue;
+        };
+        match (HeaderName::from_str(key), HeaderValue::from_str(&value)) {
+            (Ok(header_name), Ok(header_value)) => {
+                headers.insert(header_name, header_value);
+            },
+            _ => return Err(format!("Invalid header from environment variable '{}': {}", env_var, key)),
+        }
+    }
+    for (key, candidates) in api_config.random_headers.iter().flatten() {
+        let Some(value) = candidates.choose(&mut rand::thread_rng()) else {
+            continue;
+        };
+        match (HeaderName::from_str(key), HeaderValue::from_str(value)) {
+            (Ok(header_name), Ok(header_value)) => {
+                headers.insert(header_name, header_value);
+            },
+            _ => return Err(format!("Invalid random header: {}: {}", key, value)),
+        }
+    }
+
+    // Each request gets its own ID so it can be correlated with logs on the target side.
+    let request_id = match settings.request_id_header() {
+        Some(header) => {
+            let header_name = HeaderName::from_str(header)
+                .map_err(|_| format!("Invalid request ID header name: {}", header))?;
+            let request_id = uuid::Uuid::new_v4().to_string();
+            headers.insert(header_name, HeaderValue::from_str(&request_id).expect("UUIDs are valid header values"));
+            Some(request_id)
+        },
+        None => None,
+    };
 
-    let body_content = if let Some(body_file_path) = &api_config.body_file {
-        fs::read_to_string(body_file_path)
-            .map_err(|e| format!("Error reading request body from file '{}': {}", body_file_path, e))?
+    // Keys are generated per build, so every load test request gets its own; a retry keeps
+    // the first attempt's key through `PreparedRequest::with_idempotency_key`.
+    let is_write = api_config.graphql_query.is_some()
+        || matches!(api_config.method, HttpMethod::POST | HttpMethod::PUT | HttpMethod::PATCH);
+    let idempotency_key = if api_config.generate_idempotency_key.unwrap_or(false) && is_write {
+        let key = uuid::Uuid::new_v4().to_string();
+        headers.insert(IDEMPOTENCY_KEY, HeaderValue::from_str(&key).expect("UUIDs are valid header values"));
+        Some(key)
     } else {
-        api_config.body.clone().unwrap_or_default()
+        None
+    };
+
+    // GraphQL queries are always POSTed as a JSON envelope.
+    if let Some(query) = &api_config.graphql_query {
+        let builder = client.post(url).headers(headers).json(&graphql_body(query, api_config.graphql_variables.as_ref()));
+        return Ok(PreparedRequest { builder, request_id, idempotency_key });
+    }
+
+    // Form fields are encoded by reqwest, which also sets the form content type.
+    if let Some(form) = &api_config.form {
+        let builder = match &api_config.method {
+            HttpMethod::POST => client.post(url),
+            HttpMethod::PUT => client.put(url),
+            HttpMethod::PATCH => client.patch(url),
+            HttpMethod::DELETE => client.delete(url),
+            HttpMethod::GET => client.get(url),
+        };
+        return Ok(PreparedRequest { builder: builder.headers(headers).form(form), request_id, idempotency_key });
+    }
+
+    // Templates are rendered per request; monitors built by `create_monitor_tasks` carry any
+    // other body already, and anything else reads it now.
+    let body_content = if api_config.body_template.is_some() {
+        Some(Bytes::from(render_body_template(api_config)?))
+    } else {
+        match &api_config.resolved_body {
+            Some(body) => Some(body.clone()),
+            None => read_body(api_config)?,
+        }
     };
 
-    let request_builder = match &api_config.method {
-        HttpMethod::POST => Ok(client.post(&api_config.url).headers(headers).body(body_content)),
-        HttpMethod::PUT => Ok(client.put(&api_config.url).headers(headers).body(body_content)),
-        HttpMethod::DELETE => Ok(client.delete(&api_config.url).headers(headers)),
-        HttpMethod::GET => Ok(client.get(&api_config.url).headers(headers)),
+    // A Content-Type from the task or from `--http-default-header` is left as configured; the
+    // client only adds its defaults to headers the request doesn't set.
+    let content_type_configured = headers.contains_key(CONTENT_TYPE)
+        || settings.http_default_headers.iter().any(|(key, _)| key.trim().eq_ignore_ascii_case(CONTENT_TYPE.as_str()));
+    if api_config.auto_content_type.unwrap_or(true) && !content_type_configured {
+        if let Some(body) = body_content.as_ref().filter(|body| !body.is_empty()) {
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static(detect_content_type(body)));
+        }
+    }
+
+    let builder = match &api_config.method {
+        HttpMethod::POST => client.post(url).headers(headers).body(body_content.unwrap_or_default()),
+        HttpMethod::PUT => client.put(url).headers(headers).body(body_content.unwrap_or_default()),
+        HttpMethod::PATCH => client.patch(url).headers(headers).body(body_content.unwrap_or_default()),
+        // GET and DELETE only carry a body when one is configured (e.g. Elasticsearch `_search`).
+        HttpMethod::DELETE => with_optional_body(client.delete(url).headers(headers), body_content),
+        HttpMethod::GET => with_optional_body(client.get(url).headers(headers), body_content),
         // Extend this match to handle other HTTP methods as needed
     };
 
-    request_builder
+    Ok(PreparedRequest { builder, request_id, idempotency_key })
+}
+
+/// Reads the request body from `body_file`, the first of `body_variants` or the inline `body`.
+///
+/// Files are read as raw bytes so binary payloads survive unchanged.
+fn read_body(api_config: &ApiConfig) -> Result<Option<Bytes>, String> {
+    if let Some(body) = api_config.body_variants.as_ref().and_then(|variants| variants.first()) {
+        Ok(Some(Bytes::from(body.clone())))
+    } else if let Some(body_file_path) = &api_config.body_file {
+        fs::read(body_file_path)
+            .map(|bytes| Some(interpolate_body_file(api_config, bytes)))
+            .map_err(|e| format!("Error reading request body from file '{}': {}", body_file_path, e))
+    } else {
+        Ok(api_config.body.clone().map(Bytes::from))
+    }
+}
+
+/// Renders `body_template`, compiling it first when the config didn't go through validation.
+fn render_body_template(api_config: &ApiConfig) -> Result<String, String> {
+    let vars = api_config.body_template_vars.as_ref();
+    match (&api_config.compiled_body_template, &api_config.body_template) {
+        (Some(template), _) => template.render(vars),
+        (None, Some(path)) => BodyTemplate::from_file(path, api_config.body_template_env.as_deref().unwrap_or_default())?.render(vars),
+        (None, None) => Ok(String::new()),
+    }
+}
+
+/// Substitutes `${VAR}` placeholders in body file contents, as `interpolate_config` does for the
+/// inline `body`. Files that aren't UTF-8, or that opt out, are passed through byte for byte.
+fn interpolate_body_file(api_config: &ApiConfig, bytes: Vec<u8>) -> Bytes {
+    if !api_config.interpolate_body_file.unwrap_or(true) {
+        return Bytes::from(bytes);
+    }
+    match String::from_utf8(bytes) {
+        Ok(text) => Bytes::from(interpolate_string(&text)),
+        Err(e) => Bytes::from(e.into_bytes()),
+    }
+}
+
+/// Guesses the media type of a request body: JSON, then form-encoded, then plain text.
+/// Bodies that aren't UTF-8 are sent as `application/octet-stream`.
+fn detect_content_type(body: &[u8]) -> &'static str {
+    if serde_json::from_slice::<serde_json::Value>(body).is_ok() {
+        return "application/json";
+    }
+    let Ok(text) = std::str::from_utf8(body) else {
+        return "application/octet-stream";
+    };
+    let is_form = !text.contains(char::is_whitespace)
+        && text.split('&').all(|pair| pair.split_once('=').is_some_and(|(key, _)| !key.is_empty()));
+    if is_form { "application/x-www-form-urlencoded" } else { "text/plain" }
+}
+
+/// Clones `api_config` with its request body resolved, so load tests don't re-read `body_file` per request.
+fn with_resolved_body(api_config: &ApiConfig) -> Result<Arc<ApiConfig>, String> {
+    let mut resolved = api_config.clone();
+    resolved.resolved_body = read_body(api_config)?;
+    Ok(Arc::new(resolved))
+}
+
+/// Wraps a GraphQL query and its optional variables in the standard request envelope.
+fn graphql_body(query: &str, variables: Option<&serde_json::Value>) -> serde_json::Value {
+    let mut body = serde_json::json!({ "query": query });
+    if let Some(variables) = variables {
+        body["variables"] = variables.clone();
+    }
+    body
 }
 
-pub fn create_monitor_tasks(cfg: &Workflow, app_state: Arc<Mutex<AppState>>) -> VecDeque<Box<dyn ApiMonitor + Send + Sync>> {
+fn with_optional_body(builder: RequestBuilder, body: Option<Bytes>) -> RequestBuilder {
+    match body {
+        Some(body) => builder.body(body),
+        None => builder,
+    }
+}
+
+/// Builds the monitors for `cfg`'s enabled APIs. Load tests send their requests through
+/// `load_client`, which is built once per cycle by `load_test_client` and shared by them all.
+pub fn create_monitor_tasks(cfg: &Workflow, app_state: Arc<Mutex<AppState>>, settings: Arc<Settings>, load_client: HttpClient) -> VecDeque<Box<dyn ApiMonitor + Send + Sync>> {
     let mut tasks: VecDeque<Box<dyn ApiMonitor + Send + Sync>> = VecDeque::new();
 
-    for api_config in cfg.apis.iter() {
+    // Disabled APIs stay in the config but get no monitor, not even a share of a weighted load test.
+    let apis: Vec<&ApiConfig> = cfg.apis.iter()
+        .filter(|api_config| {
+            if !api_config.is_enabled() {
+                info!("Skipping disabled API '{}'", api_config.name);
+            }
+            api_config.is_enabled()
+        })
+        .collect();
+
+    // Every task, retry and load test request of the workflow waits its turn on the same limiter.
+    let limiter: Option<SharedRateLimiter> = cfg.max_requests_per_second.and_then(rate_limiter).map(Arc::new);
+
+    // In weighted mode every load-test-enabled API joins a single mixed load test.
+    if let Some(weighted_config) = &cfg.weighted_load_test {
+        let api_configs: Vec<Arc<ApiConfig>> = apis.iter()
+            .filter(|api_config| api_config.load_test.unwrap_or(false))
+            .filter_map(|api_config| match with_resolved_body(api_config) {
+                Ok(api_config) => Some(api_config),
+                Err(e) => {
+                    log::error!("Skipping '{}' in weighted load test: {}", api_config.name, e);
+                    None
+                }
+            })
+            .collect();
+        if !api_configs.is_empty() {
+            info!("Configuring weighted load test across {} APIs", api_configs.len());
+            tasks.push_back(Box::new(WeightedLoadTest {
+                api_configs,
+                app_state: app_state.clone(),
+                load_test_config: weighted_config.clone(),
+                settings: settings.clone(),
+                rate_limiter: limiter.clone(),
+                client: load_client.clone(),
+            }));
+        }
+    }
+
+    for api_config in apis {
+        // Non-HTTP checks get their own monitors and never take part in load tests.
+        match api_config.check_type {
+            Some(CheckType::Tcp) => {
+                info!("Configuring TCP check '{}'", api_config.name);
+                tasks.push_back(Box::new(TcpMonitor {
+                    api_config: Arc::new(api_config.clone()),
+                    app_state: app_state.clone(),
+                    settings: settings.clone(),
+                }));
+                continue;
+            },
+            Some(CheckType::Dns) => {
+                info!("Configuring DNS check '{}'", api_config.name);
+                tasks.push_back(Box::new(DnsMonitor {
+                    api_config: Arc::new(api_config.clone()),
+                    app_state: app_state.clone(),
+                    settings: settings.clone(),
+                }));
+                continue;
+            },
+            Some(CheckType::Ping) => {
+                info!("Configuring ping check '{}'", api_config.name);
+                tasks.push_back(Box::new(PingMonitor {
+                    api_config: Arc::new(api_config.clone()),
+                    app_state: app_state.clone(),
+                    settings: settings.clone(),
+                }));
+                continue;
+            },
+            Some(CheckType::Http) | None => {},
+        }
+
+        if api_config.load_test.unwrap_or(false) && cfg.weighted_load_test.is_some() {
+            continue; // Already part of the weighted load test
+        }
+
+        // Resolve the body once here rather than on every request.
+        let resolved_config = match with_resolved_body(api_config) {
+            Ok(resolved_config) => resolved_config,
+            Err(e) => {
+                log::error!("Skipping '{}': {}", api_config.name, e);
+                continue;
+            }
+        };
+
         // Use the task's name in logging
         if api_config.load_test.unwrap_or(false) {
             if let Some(load_test_config) = &api_config.load_test_config {
                 info!("Configuring progressive load test '{}'", api_config.name); // Changed from url to name
                 tasks.push_back(Box::new(LoadTest {
-                    api_config: Arc::new(api_config.clone()),
+                    api_config: resolved_config,
                     app_state: app_state.clone(),
                     load_test_config: load_test_config.clone(),
+                    settings: settings.clone(),
+                    rate_limiter: limiter.clone(),
+                    client: load_client.clone(),
                 }));
             }
         } else {
             info!("Configuring task '{}'", api_config.name); // Log task configuration with name
             tasks.push_back(Box::new(Task {
-                api_config: Arc::new(api_config.clone()),
+                api_config: resolved_config,
                 app_state: app_state.clone(),
+                settings: settings.clone(),
+                rate_limiter: limiter.clone(),
             }));
         }
     }
@@ -83,56 +432,1177 @@ pub fn create_monitor_tasks(cfg: &Workflow, app_state: Arc<Mutex<AppState>>) ->
 }
 
 
-async fn monitor_single_workflow(workflow: Arc<Workflow>, app_state: Arc<Mutex<AppState>>, client: HttpClient) {
+async fn monitor_single_workflow(workflow: Arc<Workflow>, app_state: Arc<Mutex<AppState>>, settings: Arc<Settings>, client: HttpClient, load_client: HttpClient) {
+    if let Some(gate) = &workflow.gate {
+        let gate_result = if settings.is_host_allowed(&gate.url) {
+            check_gate(gate, &client).await
+        } else {
+            Err("its host is not in the allowed hosts".to_string())
+        };
+        if let Err(reason) = gate_result {
+            info!("Skipping workflow '{}': gate {} did not pass: {}", workflow.name, gate.url, reason);
+            let state = app_state.lock().await;
+            state.workflow_status.lock().await.insert(workflow.name.clone(), "SKIPPED".to_string());
+            return;
+        }
+    }
+    let tasks = create_monitor_tasks(&workflow, app_state.clone(), settings, load_client);
+    run_workflow_tasks(&workflow, tasks, app_state, client).await;
+}
+
+/// Requests the gate and checks its status and, when configured, the field and value in its JSON body.
+async fn check_gate(gate: &GateConfig, client: &HttpClient) -> Result<(), String> {
+    let response = client.get(&gate.url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status().as_u16();
+    let status_ok = match gate.expected_status {
+        Some(expected) => status == expected,
+        None => (200..300).contains(&status),
+    };
+    if !status_ok {
+        return Err(format!("responded with HTTP status {}", status));
+    }
+
+    let Some(field) = &gate.expected_field else {
+        return Ok(());
+    };
+    let body: serde_json::Value = response.json().await.map_err(|e| format!("body is not JSON: {}", e))?;
+    let value = if field.starts_with('/') { body.pointer(field) } else { body.get(field) };
+    match (value, &gate.expected_value) {
+        (None, _) => Err(format!("response has no field '{}'", field)),
+        (Some(value), Some(expected)) if value != expected => Err(format!("'{}' is {} rather than {}", field, value, expected)),
+        _ => Ok(()),
+    }
+}
+
+/// Runs a workflow's monitors group by group in `task_order`, then records the workflow verdict.
+async fn run_workflow_tasks(workflow: &Workflow, tasks: VecDeque<BoxedMonitor>, app_state: Arc<Mutex<AppState>>, client: HttpClient) {
     let workflow_name = &workflow.name;
-    let tasks = create_monitor_tasks(&workflow, app_state);
 
-    let mut grouped_tasks: HashMap<usize, Vec<Box<dyn ApiMonitor + Send + Sync>>> = HashMap::new();
-    for task in tasks {
+    // Tasks are numbered so the ones that never finished can be identified after a timeout.
+    let mut grouped_tasks: HashMap<usize, Vec<(usize, BoxedMonitor)>> = HashMap::new();
+    for (index, task) in tasks.into_iter().enumerate() {
         let order = task.get_task_order(); // Assume this exists and is correct
-        grouped_tasks.entry(order).or_insert_with(Vec::new).push(task);
+        grouped_tasks.entry(order).or_default().push((index, task));
     }
 
     let mut order_keys: Vec<&usize> = grouped_tasks.keys().collect();
     order_keys.sort();
 
-    for order_key in order_keys {
-        if let Some(task_group) = grouped_tasks.get(order_key) {
-            let futures: Vec<_> = task_group.iter().map(|task| {
-                let client_clone = client.clone();
-                async move {
-                    info!("Starting '{}'", task.describe());
-                    match task.execute(&client_clone, workflow_name).await {
-                        Ok(_) => info!("Successfully completed '{}'", task.describe()),
-                        Err(e) => log::error!("Task '{}' failed: {}", task.describe(), e),
+    let completed = std::sync::Mutex::new(HashSet::new());
+    let failures = AtomicUsize::new(0);
+    let started = Instant::now();
+    // Groups run in order anyway; within a group, load tests queue on this lock in task order.
+    let sequential_load_tests = workflow.sequential_load_tests.unwrap_or(false);
+    let load_test_turn = Mutex::new(());
+    let inter_group_delay = workflow.inter_group_delay_ms.filter(|ms| *ms > 0).map(Duration::from_millis);
+
+    let run = async {
+        for (position, order_key) in order_keys.iter().enumerate() {
+            // Lets downstream systems settle before the next phase starts.
+            if let Some(delay) = inter_group_delay.filter(|_| position > 0) {
+                info!("Waiting {:?} before task order group {} of '{}'", delay, order_key, workflow_name);
+                tokio::time::sleep(delay).await;
+            }
+            if let Some(task_group) = grouped_tasks.get(*order_key) {
+                let futures: Vec<_> = task_group.iter().map(|(index, task)| {
+                    let client_clone = client.clone();
+                    let completed = &completed;
+                    let failures = &failures;
+                    let load_test_turn = &load_test_turn;
+                    let app_state = &app_state;
+                    async move {
+                        let _turn = if sequential_load_tests && task.is_load_test() {
+                            Some(load_test_turn.lock().await)
+                        } else {
+                            None
+                        };
+                        info!("Starting '{}'", task.describe());
+                        let task_started = Instant::now();
+                        // Catching the unwind in place (rather than spawning) keeps tasks cancellable by `max_run_secs`.
+                        let result = AssertUnwindSafe(task.execute(&client_clone, workflow_name)).catch_unwind().await
+                            .unwrap_or_else(|panic| Err(MonitorError::Panic(panic_message(&*panic).to_string())));
+                        match result {
+                            Ok(_) => info!("Successfully completed '{}'", task.describe()),
+                            Err(MonitorError::Panic(message)) => {
+                                log::error!("Task '{}' panicked: {}", task.describe(), message);
+                                failures.fetch_add(1, Ordering::Relaxed);
+                                let elapsed = task_started.elapsed();
+                                record_unfinished_task(app_state, workflow_name, task.as_ref(), "PANIC", elapsed).await;
+                            },
+                            Err(e) => {
+                                log::error!("Task '{}' failed: {}", task.describe(), e);
+                                failures.fetch_add(1, Ordering::Relaxed);
+                            },
+                        }
+                        completed.lock().unwrap().insert(*index);
                     }
-                }
-            }).collect();
+                }).collect();
+
+                join_all(futures).await; // Execute concurrently within the same order group
+            }
+        }
+    };
+
+    let timed_out = match workflow.max_run_secs {
+        Some(secs) => {
+            let max_run = Duration::from_secs(secs as u64);
+            let timed_out = tokio::time::timeout(max_run, run).await.is_err();
+            if timed_out {
+                log::error!("Workflow '{}' exceeded its maximum run time of {:?}; abandoning unfinished tasks", workflow_name, max_run);
+            }
+            timed_out
+        },
+        None => {
+            run.await;
+            false
+        },
+    };
+
+    if timed_out {
+        let completed = completed.into_inner().unwrap();
+        let elapsed = started.elapsed();
+        for (index, task) in grouped_tasks.values().flatten() {
+            if completed.contains(index) {
+                continue;
+            }
+            failures.fetch_add(1, Ordering::Relaxed);
+            record_unfinished_task(&app_state, workflow_name, task.as_ref(), "TIMEOUT", elapsed).await;
+        }
+    }
+
+    record_workflow_status(&app_state, workflow, failures.into_inner()).await;
+}
 
-            join_all(futures).await; // Execute concurrently within the same order group
+/// Records `status` for every API of a task that produced no results, among the load test
+/// results for a load test and among the task results otherwise.
+async fn record_unfinished_task(app_state: &Arc<Mutex<AppState>>, workflow_name: &str, task: &(dyn ApiMonitor + Send + Sync), status: &str, elapsed: Duration) {
+    for api_config in task.api_configs() {
+        if task.is_load_test() {
+            record_load_test_status(app_state, workflow_name, &api_config, status).await;
+        } else {
+            record_task_status(app_state, workflow_name, &api_config, status, elapsed).await;
         }
     }
 }
 
+/// The message a panic was raised with, when it carried one.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    panic.downcast_ref::<&str>().copied()
+        .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Records whether the workflow passed: it fails once more tasks failed than `max_task_failures` allows.
+async fn record_workflow_status(app_state: &Arc<Mutex<AppState>>, workflow: &Workflow, failures: usize) {
+    let allowed = workflow.max_task_failures.unwrap_or(0);
+    let status = if failures > allowed { "FAILED" } else { "PASSED" };
+    if failures > allowed {
+        log::error!("Workflow '{}' failed: {} task failures exceed the allowed {}", workflow.name, failures, allowed);
+    } else {
+        info!("Workflow '{}' passed with {} task failures (allowed {})", workflow.name, failures, allowed);
+    }
+
+    let state = app_state.lock().await;
+    state.workflow_status.lock().await.insert(workflow.name.clone(), status.to_string());
+}
+
+/// The results of one workflow run by `run_workflow`, keyed by task name.
+#[derive(Debug, Clone)]
+pub struct WorkflowResult {
+    pub workflow_name: String,
+    /// "PASSED" or "FAILED", as reported by `/api/v1/result/workflow`.
+    pub status: String,
+    pub tasks: HashMap<String, MonitoringData>,
+    pub load_tests: HashMap<String, LoadTestMonitoringData>,
+    pub pings: HashMap<String, PingMonitoringData>,
+}
+
+/// Runs a single workflow to completion and returns its results, without the HTTP server.
+///
+/// Builds HTTP clients from `settings` and records into a fresh `AppState`, so runs don't
+/// share results. Fails only when the clients can't be built.
+pub async fn run_workflow(workflow: Workflow, settings: &Settings) -> Result<WorkflowResult, http_client::ClientError> {
+    let client = http_client::get_client(Some(settings.http_client_config()))?;
+    let load_client = load_test_client(settings)?;
+    let app_state = Arc::new(Mutex::new(AppState::default()));
+    let workflow_name = workflow.name.clone();
+
+    monitor_single_workflow(Arc::new(workflow), app_state.clone(), Arc::new(settings.clone()), client, load_client).await;
 
+    let state = app_state.lock().await;
+    let status = state.workflow_status.lock().await.remove(&workflow_name).unwrap_or_default();
+    let tasks = state.task_monitoring_data.lock().await.remove(&workflow_name).unwrap_or_default();
+    let load_tests = state.load_test_monitoring_data.lock().await.remove(&workflow_name).unwrap_or_default();
+    let pings = state.ping_monitoring_data.lock().await.remove(&workflow_name).unwrap_or_default();
+    Ok(WorkflowResult { workflow_name, status, tasks, load_tests, pings })
+}
 
 // Updated function signature to accept a vector of workflows
 pub async fn start_monitoring(settings: Arc<Settings>, workflows: Vec<Arc<Workflow>>, app_state: Arc<Mutex<AppState>>) {
-    let http_config = HttpClientConfig {
-        timeout_seconds: settings.http_timeout_seconds,
-        proxy_url: settings.http_proxy_url.clone(),
-        default_headers: settings.http_default_headers.clone(),
+    run_cycle(settings, workflows, &app_state).await;
+
+    // Every started cycle is marked complete, even one that couldn't run, so a drain never waits on
+    // it. Once the cycle is over, monitoring can be triggered again.
+    let mut state = app_state.lock().await;
+    let cycles = state.cycles_completed.fetch_add(1, Ordering::Relaxed) + 1;
+    info!("Completed monitoring cycle {}", cycles);
+    if state.draining() {
+        info!("Drained after monitoring cycle {}", cycles);
+    }
+    state.monitoring_started = false;
+}
+
+/// Runs one monitoring cycle: every workflow once, after the configured start delay.
+async fn run_cycle(settings: Arc<Settings>, workflows: Vec<Arc<Workflow>>, app_state: &Arc<Mutex<AppState>>) {
+    // Hold off until the scheduled start so runs can be coordinated across systems.
+    if let Some(delay) = settings.start_delay() {
+        info!("Delaying start of monitoring by {:?}", delay);
+        tokio::time::sleep(delay).await;
+    }
+
+    let client = match http_client::get_client(Some(settings.http_client_config())) {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Failed to create HTTP client, not starting monitoring: {}", e);
+            return;
+        }
+    };
+    // Load tests read bodies undecoded, so they share a client of their own.
+    let load_client = match load_test_client(&settings) {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Failed to create load test HTTP client, not starting monitoring: {}", e);
+            return;
+        }
     };
 
-    let client = http_client::get_client(Some(http_config)).expect("Failed to create HTTP client");
+    // Stagger the workflows so they don't all hit their targets at the same instant.
+    let offsets = start_offsets(workflows.len(), settings.workflow_start_jitter());
 
     // Iterate over workflows and spawn a new async task for each
-    let futures: Vec<_> = workflows.into_iter().map(|workflow| {
+    let futures: Vec<_> = workflows.into_iter().zip(offsets).map(|(workflow, offset)| {
         let app_state_clone = app_state.clone();
         let client_clone = client.clone();
-        monitor_single_workflow(workflow, app_state_clone, client_clone)
+        let load_client_clone = load_client.clone();
+        let settings_clone = settings.clone();
+        async move {
+            if !offset.is_zero() {
+                info!("Delaying workflow {} by {:?}", workflow.name, offset);
+                tokio::time::sleep(offset).await;
+            }
+            monitor_single_workflow(workflow, app_state_clone, settings_clone, client_clone, load_client_clone).await
+        }
     }).collect();
 
-    // Wait for all spawned tasks to complete
-    join_all(futures).await;
+    // Wait for all spawned tasks to complete, running at most the configured number at once
+    run_limited(futures, settings.max_concurrent_workflows).await;
+}
+
+/// Drives `futures` to completion with at most `limit` in flight at a time; unbounded when `None` or zero.
+async fn run_limited<F>(futures: Vec<F>, limit: Option<usize>)
+where
+    F: std::future::Future<Output = ()>,
+{
+    match limit.filter(|limit| *limit > 0) {
+        Some(limit) => stream::iter(futures).buffer_unordered(limit).collect::<Vec<_>>().await,
+        None => join_all(futures).await,
+    };
+}
+
+/// A workflow's `max_requests_per_second` limiter, shared by every monitor that sends its HTTP requests.
+pub type SharedRateLimiter = Arc<DefaultDirectRateLimiter>;
+
+/// Builds a limiter allowing one request every `1 / requests_per_second` seconds, without bursts.
+pub(crate) fn rate_limiter(requests_per_second: f64) -> Option<DefaultDirectRateLimiter> {
+    let period = Duration::try_from_secs_f64(1.0 / requests_per_second).ok()?;
+    Quota::with_period(period).map(RateLimiter::direct)
+}
+
+/// Picks a random start offset in `0..=jitter` for each of `count` workflows.
+fn start_offsets(count: usize, jitter: Option<Duration>) -> Vec<Duration> {
+    let Some(jitter) = jitter else {
+        return vec![Duration::ZERO; count];
+    };
+    let mut rng = rand::thread_rng();
+    let max_ms = jitter.as_millis() as u64;
+    (0..count).map(|_| Duration::from_millis(rng.gen_range(0..=max_ms))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_config(method: &str) -> ApiConfig {
+        let yaml = format!(
+            r#"
+name: "Create Todo"
+url: "http://localhost/todos"
+method: {}
+headers: {{}}
+expected_field: "id"
+response_time_threshold: 2000
+"#,
+            method
+        );
+        serde_yaml::from_str(&yaml).expect("Failed to parse YAML")
+    }
+
+    #[test]
+    fn test_request_id_header_is_attached_and_returned() {
+        let client = Client::new();
+        let settings = Settings { inject_request_id: Some(true), ..Settings::default() };
+        let prepared = create_request_builder(&client, &api_config("GET"), &settings).unwrap();
+        let request = prepared.builder.build().unwrap();
+
+        let request_id = prepared.request_id.expect("request ID should be generated");
+        assert_eq!(request.headers().get("X-Request-Id").unwrap(), request_id.as_str());
+    }
+
+    #[test]
+    fn test_request_id_is_omitted_when_disabled() {
+        let client = Client::new();
+        let prepared = create_request_builder(&client, &api_config("GET"), &Settings::default()).unwrap();
+        let request = prepared.builder.build().unwrap();
+
+        assert!(prepared.request_id.is_none());
+        assert!(request.headers().get("X-Request-Id").is_none());
+    }
+
+    #[test]
+    fn test_headers_from_env_pick_up_variable_values() {
+        std::env::set_var("THUNDERHAWK_TEST_TOKEN", "s3cret");
+        let mut config = api_config("GET");
+        config.headers_from_env = Some(HashMap::from([
+            ("X-Token".to_string(), "THUNDERHAWK_TEST_TOKEN".to_string()),
+            ("X-Missing".to_string(), "THUNDERHAWK_TEST_UNSET_VARIABLE".to_string()),
+        ]));
+
+        let request = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap().builder.build().unwrap();
+
+        assert_eq!(request.headers().get("X-Token").unwrap(), "s3cret");
+        assert!(request.headers().get("X-Missing").is_none());
+    }
+
+    #[test]
+    fn test_random_headers_vary_between_builds() {
+        let mut config = api_config("GET");
+        let candidates: Vec<String> = (0..4).map(|i| format!("bust-{}", i)).collect();
+        config.random_headers = Some(HashMap::from([("X-Cache-Bust".to_string(), candidates.clone())]));
+
+        let client = Client::new();
+        let values: HashSet<String> = (0..50).map(|_| {
+            let request = create_request_builder(&client, &config, &Settings::default()).unwrap().builder.build().unwrap();
+            request.headers().get("X-Cache-Bust").unwrap().to_str().unwrap().to_string()
+        }).collect();
+
+        assert!(values.len() > 1, "every build picked {:?}", values);
+        assert!(values.iter().all(|value| candidates.contains(value)));
+    }
+
+    #[test]
+    fn test_idempotency_keys_are_unique_per_build() {
+        let mut config = api_config("PATCH");
+        config.generate_idempotency_key = Some(true);
+
+        let first = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap();
+        let second = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap();
+
+        let first_key = first.idempotency_key.expect("PATCH requests should get a key");
+        let second_key = second.idempotency_key.expect("PATCH requests should get a key");
+        assert_ne!(first_key, second_key);
+        assert_eq!(first.builder.build().unwrap().headers().get("Idempotency-Key").unwrap(), first_key.as_str());
+    }
+
+    #[test]
+    fn test_idempotency_key_is_not_sent_with_reads() {
+        let mut config = api_config("GET");
+        config.generate_idempotency_key = Some(true);
+
+        let prepared = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap();
+
+        assert!(prepared.idempotency_key.is_none());
+        assert!(prepared.builder.build().unwrap().headers().get("Idempotency-Key").is_none());
+    }
+
+    #[test]
+    fn test_disabled_api_gets_no_monitor_task() {
+        let workflow: Workflow = serde_yaml::from_str(r#"
+name: "Partly Disabled Workflow"
+apis:
+  - name: "Flaky Endpoint"
+    url: "http://localhost/flaky"
+    method: GET
+    headers: {}
+    expected_field: ""
+    response_time_threshold: 2000
+    enabled: false
+  - name: "Stable Endpoint"
+    url: "http://localhost/stable"
+    method: GET
+    headers: {}
+    expected_field: ""
+    response_time_threshold: 2000
+"#).unwrap();
+
+        let tasks = create_monitor_tasks(&workflow, Arc::new(Mutex::new(AppState::default())), Arc::new(Settings::default()), Client::new());
+
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].api_configs()[0].name, "Stable Endpoint");
+    }
+
+    #[test]
+    fn test_delete_with_configured_body_attaches_it() {
+        let mut config = api_config("DELETE");
+        config.body = Some(r#"{"ids": [1, 2]}"#.to_string());
+
+        let request = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap().builder.build().unwrap();
+
+        assert_eq!(request.body().and_then(|body| body.as_bytes()), Some(&br#"{"ids": [1, 2]}"#[..]));
+    }
+
+    #[test]
+    fn test_get_without_configured_body_has_none() {
+        let request = create_request_builder(&Client::new(), &api_config("GET"), &Settings::default()).unwrap().builder.build().unwrap();
+
+        assert!(request.body().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_binary_body_file_is_sent_byte_for_byte() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        // Not valid UTF-8, so reading it as a string would fail.
+        let payload: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0x00, 0xff, 0xfe, 0x80];
+        let path = std::env::temp_dir().join(format!("thunderhawk-body-{}.bin", uuid::Uuid::new_v4()));
+        fs::write(&path, &payload).unwrap();
+
+        let mut config = api_config("POST");
+        config.url = server.uri();
+        config.body_file = Some(path.to_string_lossy().into_owned());
+        let result = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap().builder.send().await;
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.unwrap().status().is_success());
+        let received = server.received_requests().await.unwrap();
+        assert_eq!(received[0].body, payload);
+    }
+
+    #[test]
+    fn test_body_file_placeholders_are_interpolated() {
+        std::env::set_var("THUNDERHAWK_TEST_BODY_TOKEN", "tok-123");
+        let path = std::env::temp_dir().join(format!("thunderhawk-body-{}.json", uuid::Uuid::new_v4()));
+        fs::write(&path, r#"{"token": "${THUNDERHAWK_TEST_BODY_TOKEN}"}"#).unwrap();
+
+        let mut config = api_config("POST");
+        config.body_file = Some(path.to_string_lossy().into_owned());
+        let interpolated = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap().builder.build().unwrap();
+        config.interpolate_body_file = Some(false);
+        let untouched = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap().builder.build().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(interpolated.body().and_then(|body| body.as_bytes()), Some(&br#"{"token": "tok-123"}"#[..]));
+        assert_eq!(untouched.body().and_then(|body| body.as_bytes()), Some(&br#"{"token": "${THUNDERHAWK_TEST_BODY_TOKEN}"}"#[..]));
+    }
+
+    #[test]
+    fn test_content_type_is_detected_from_body() {
+        let content_type = |body: &str, extra: &dyn Fn(&mut ApiConfig)| {
+            let mut config = api_config("POST");
+            config.body = Some(body.to_string());
+            extra(&mut config);
+            let request = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap().builder.build().unwrap();
+            request.headers().get(CONTENT_TYPE).map(|value| value.to_str().unwrap().to_string())
+        };
+
+        assert_eq!(content_type(r#"{"title": "foo"}"#, &|_| {}).as_deref(), Some("application/json"));
+        assert_eq!(content_type("name=foo&tag=bar", &|_| {}).as_deref(), Some("application/x-www-form-urlencoded"));
+        assert_eq!(content_type("hello, world", &|_| {}).as_deref(), Some("text/plain"));
+        assert_eq!(content_type("hello", &|config| {
+            config.headers.insert("content-type".to_string(), "application/xml".to_string());
+        }).as_deref(), Some("application/xml"));
+        assert_eq!(content_type("hello", &|config| config.auto_content_type = Some(false)), None);
+    }
+
+    #[test]
+    fn test_requests_to_hosts_outside_the_allowlist_are_refused() {
+        let settings = Settings { allowed_hosts: Some(vec!["localhost".to_string()]), ..Settings::default() };
+        let config = api_config("GET");
+
+        assert!(create_request_builder(&Client::new(), &config, &settings).is_ok());
+        let result = create_request_builder_for_url(&Client::new(), &config, "http://api.production.com/todos", &settings);
+        assert!(matches!(result, Err(error) if error.contains("not in the allowed hosts")));
+    }
+
+    #[tokio::test]
+    async fn test_default_header_content_type_is_not_overridden() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        let settings = Settings {
+            http_default_headers: vec![("Content-Type".to_string(), "application/vnd.api+json".to_string())],
+            ..Settings::default()
+        };
+        let mut config = api_config("POST");
+        config.url = server.uri();
+        config.body = Some(r#"{"title": "foo"}"#.to_string());
+
+        let client = http_client::get_client(Some(settings.http_client_config())).unwrap();
+        create_request_builder(&client, &config, &settings).unwrap().builder.send().await.unwrap();
+
+        let received = server.received_requests().await.unwrap();
+        assert_eq!(received[0].headers.get_all(CONTENT_TYPE).iter().collect::<Vec<_>>(), ["application/vnd.api+json"]);
+    }
+
+    #[test]
+    fn test_resolved_body_file_is_not_reread_per_request() {
+        let path = std::env::temp_dir().join(format!("thunderhawk-body-{}.json", uuid::Uuid::new_v4()));
+        fs::write(&path, r#"{"title": "cached"}"#).unwrap();
+
+        let mut config = api_config("POST");
+        config.body_file = Some(path.to_string_lossy().into_owned());
+        let resolved = with_resolved_body(&config).unwrap();
+        // Any further read of the file would now fail.
+        fs::remove_file(&path).unwrap();
+
+        let client = Client::new();
+        for _ in 0..100 {
+            let request = create_request_builder(&client, &resolved, &Settings::default()).unwrap().builder.build().unwrap();
+            assert_eq!(request.body().and_then(|body| body.as_bytes()), Some(&br#"{"title": "cached"}"#[..]));
+        }
+        assert!(create_request_builder(&client, &config, &Settings::default()).is_err());
+    }
+
+    #[test]
+    fn test_body_template_renders_loops_and_conditionals() {
+        let path = std::env::temp_dir().join(format!("thunderhawk-template-{}.hbs", uuid::Uuid::new_v4()));
+        fs::write(&path, r#"{"items": [{{#each vars.items}}{{#if @index}}, {{/if}}"{{this}}"{{/each}}]{{#if env.THUNDERHAWK_TEST_TEMPLATE_FLAG}}, "flag": "{{env.THUNDERHAWK_TEST_TEMPLATE_FLAG}}"{{/if}}}"#).unwrap();
+        std::env::set_var("THUNDERHAWK_TEST_TEMPLATE_FLAG", "on");
+
+        let mut config = api_config("POST");
+        config.body_template = Some(path.to_string_lossy().into_owned());
+        config.body_template_vars = Some(serde_json::json!({ "items": ["a", "b", "c"] }));
+        config.body_template_env = Some(vec!["THUNDERHAWK_TEST_TEMPLATE_FLAG".to_string()]);
+        let request = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap().builder.build().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            request.body().and_then(|body| body.as_bytes()),
+            Some(&br#"{"items": ["a", "b", "c"], "flag": "on"}"#[..])
+        );
+        assert_eq!(request.headers().get(CONTENT_TYPE).unwrap(), "application/json");
+    }
+
+    #[test]
+    fn test_unreadable_body_file_skips_monitor_at_construction() {
+        let yaml = r#"
+name: "Broken Workflow"
+apis:
+  - name: "Upload"
+    url: "http://localhost/upload"
+    method: POST
+    headers: {}
+    expected_field: "id"
+    response_time_threshold: 2000
+    body_file: "/nonexistent/thunderhawk/body.bin"
+"#;
+        let workflow: Workflow = serde_yaml::from_str(yaml).unwrap();
+
+        let tasks = create_monitor_tasks(&workflow, Arc::new(Mutex::new(AppState::default())), Arc::new(Settings::default()), Client::new());
+
+        assert!(tasks.is_empty());
+    }
+
+    #[test]
+    fn test_graphql_query_is_posted_as_json_envelope() {
+        let mut config = api_config("GET");
+        config.graphql_query = Some("query($id: ID!) { todo(id: $id) { title } }".to_string());
+        config.graphql_variables = Some(serde_json::json!({ "id": 1 }));
+
+        let request = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap().builder.build().unwrap();
+
+        assert_eq!(request.method(), reqwest::Method::POST);
+        assert_eq!(request.headers().get("content-type").unwrap(), "application/json");
+        let body: serde_json::Value = serde_json::from_slice(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        assert_eq!(body, serde_json::json!({
+            "query": "query($id: ID!) { todo(id: $id) { title } }",
+            "variables": { "id": 1 },
+        }));
+    }
+
+    #[test]
+    fn test_form_fields_are_sent_url_encoded() {
+        let mut config = api_config("POST");
+        config.form = Some(HashMap::from([
+            ("username".to_string(), "alice".to_string()),
+            ("note".to_string(), "a&b c".to_string()),
+        ]));
+
+        let request = create_request_builder(&Client::new(), &config, &Settings::default()).unwrap().builder.build().unwrap();
+
+        assert_eq!(request.headers().get(CONTENT_TYPE).unwrap(), "application/x-www-form-urlencoded");
+        let body = std::str::from_utf8(request.body().unwrap().as_bytes().unwrap()).unwrap();
+        let mut fields: Vec<&str> = body.split('&').collect();
+        fields.sort();
+        assert_eq!(fields, vec!["note=a%26b+c", "username=alice"]);
+    }
+
+    #[test]
+    fn test_graphql_body_omits_missing_variables() {
+        assert_eq!(graphql_body("{ todos { id } }", None), serde_json::json!({ "query": "{ todos { id } }" }));
+    }
+
+    #[tokio::test]
+    async fn test_start_delay_is_respected_before_first_request() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let yaml = format!(
+            r#"
+name: "Delayed Workflow"
+apis:
+  - name: "Fetch Todo"
+    url: "{}"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000
+"#,
+            server.uri()
+        );
+        let workflow: Workflow = serde_yaml::from_str(&yaml).unwrap();
+        let settings = Settings { start_delay_secs: Some(1), ..Settings::default() };
+
+        let started = std::time::Instant::now();
+        let monitoring = tokio::spawn(start_monitoring(
+            Arc::new(settings),
+            vec![Arc::new(workflow)],
+            Arc::new(Mutex::new(AppState::default())),
+        ));
+
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        assert!(server.received_requests().await.unwrap().is_empty());
+
+        monitoring.await.unwrap();
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+        assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_completed_cycles_are_counted() {
+        let workflow: Workflow = serde_yaml::from_str("name: \"Empty Workflow\"\napis: []").unwrap();
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+
+        for expected in 1..=2 {
+            start_monitoring(Arc::new(Settings::default()), vec![Arc::new(workflow.clone())], app_state.clone()).await;
+            assert_eq!(app_state.lock().await.cycles_completed.load(Ordering::Relaxed), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drain_lets_running_cycle_complete() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": 1 })).set_delay(Duration::from_millis(500)))
+            .mount(&server).await;
+
+        let workflow = |name: &str| -> Workflow {
+            let yaml = format!(
+                r#"
+name: "{}"
+apis:
+  - name: "Fetch Todo"
+    url: "{}"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000
+"#,
+                name, server.uri()
+            );
+            serde_yaml::from_str(&yaml).unwrap()
+        };
+        // One workflow at a time, so the second is still queued when the drain arrives; it runs anyway.
+        let settings = Settings { max_concurrent_workflows: Some(1), ..Settings::default() };
+        let app_state = Arc::new(Mutex::new(AppState { monitoring_started: true, ..AppState::default() }));
+
+        let monitoring = tokio::spawn(start_monitoring(
+            Arc::new(settings),
+            vec![Arc::new(workflow("First")), Arc::new(workflow("Second"))],
+            app_state.clone(),
+        ));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        app_state.lock().await.stop_token.cancel();
+        monitoring.await.unwrap();
+
+        let state = app_state.lock().await;
+        let task_data = state.task_monitoring_data.lock().await;
+        assert_eq!(task_data["First"]["Fetch Todo"].status, "OK");
+        assert_eq!(task_data["Second"]["Fetch Todo"].status, "OK");
+        assert_eq!(state.cycles_completed.load(Ordering::Relaxed), 1);
+        assert!(!state.monitoring_started);
+        assert!(!state.draining());
+    }
+
+    #[tokio::test]
+    async fn test_completed_cycle_can_be_triggered_again() {
+        let app_state = Arc::new(Mutex::new(AppState { monitoring_started: true, ..AppState::default() }));
+
+        start_monitoring(Arc::new(Settings::default()), Vec::new(), app_state.clone()).await;
+
+        let state = app_state.lock().await;
+        assert_eq!(state.cycles_completed.load(Ordering::Relaxed), 1);
+        assert!(!state.monitoring_started);
+    }
+
+    #[tokio::test]
+    async fn test_cycle_that_cannot_build_a_client_still_completes() {
+        let settings = Settings { min_tls_version: Some("0.9".to_string()), ..Settings::default() };
+        let app_state = Arc::new(Mutex::new(AppState { monitoring_started: true, ..AppState::default() }));
+        app_state.lock().await.stop_token.cancel();
+
+        start_monitoring(Arc::new(settings), Vec::new(), app_state.clone()).await;
+
+        let state = app_state.lock().await;
+        assert_eq!(state.cycles_completed.load(Ordering::Relaxed), 1);
+        assert!(!state.draining());
+        assert!(!state.monitoring_started);
+    }
+
+    #[tokio::test]
+    async fn test_task_requests_stay_under_rate_cap() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let api = |name: &str| format!(
+            r#"
+  - name: "{}"
+    url: "{}"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000"#,
+            name, server.uri()
+        );
+        let yaml = format!(
+            "name: \"Throttled Workflow\"\nmax_requests_per_second: 10\napis:{}{}{}{}{}\n",
+            api("One"), api("Two"), api("Three"), api("Four"), api("Five")
+        );
+        let workflow: Workflow = serde_yaml::from_str(&yaml).unwrap();
+
+        let started = Instant::now();
+        monitor_single_workflow(Arc::new(workflow), Arc::new(Mutex::new(AppState::default())), Arc::new(Settings::default()), Client::new(), Client::new()).await;
+        let elapsed = started.elapsed();
+
+        // Five requests at 10/s: the first goes immediately, the rest are spaced 100ms apart.
+        assert_eq!(server.received_requests().await.unwrap().len(), 5);
+        assert!(elapsed >= Duration::from_millis(380), "5 requests took only {:?}", elapsed);
+    }
+
+    async fn run_workflow_with_failures(failing_tasks: usize, max_task_failures: usize) -> String {
+        use wiremock::{matchers::{method, path}, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).and(path("/ok")).respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#)).mount(&server).await;
+        Mock::given(method("GET")).and(path("/fail")).respond_with(ResponseTemplate::new(500)).mount(&server).await;
+
+        let apis: String = (0..3).map(|i| format!(
+            r#"
+  - name: "Task {}"
+    url: "{}/{}"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000"#,
+            i, server.uri(), if i < failing_tasks { "fail" } else { "ok" }
+        )).collect();
+        let yaml = format!("name: \"Verdict Workflow\"\nmax_task_failures: {}\napis:{}\n", max_task_failures, apis);
+        let workflow: Workflow = serde_yaml::from_str(&yaml).unwrap();
+
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        monitor_single_workflow(Arc::new(workflow), app_state.clone(), Arc::new(Settings::default()), Client::new(), Client::new()).await;
+
+        let state = app_state.lock().await;
+        let status = state.workflow_status.lock().await["Verdict Workflow"].clone();
+        status
+    }
+
+    #[tokio::test]
+    async fn test_run_workflow_returns_owned_results() {
+        use wiremock::{matchers::{method, path}, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).and(path("/todos")).respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#)).mount(&server).await;
+        Mock::given(method("GET")).and(path("/users")).respond_with(ResponseTemplate::new(503)).mount(&server).await;
+
+        let yaml = format!(
+            r#"
+name: "Embedded Workflow"
+max_task_failures: 1
+apis:
+  - name: "Todos"
+    url: "{0}/todos"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000
+  - name: "Users"
+    url: "{0}/users"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000
+"#,
+            server.uri()
+        );
+        let workflow: Workflow = serde_yaml::from_str(&yaml).unwrap();
+
+        let result = run_workflow(workflow, &Settings::default()).await.unwrap();
+
+        assert_eq!(result.workflow_name, "Embedded Workflow");
+        assert_eq!(result.status, "PASSED");
+        assert_eq!(result.tasks["Todos"].status, "OK");
+        assert_eq!(result.tasks["Users"].status, "ERROR");
+        assert_eq!(result.tasks["Users"].status_code, Some(503));
+        assert!(result.load_tests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failing_gate_skips_workflow_tasks() {
+        use wiremock::{matchers::{method, path}, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).and(path("/flags/checkout"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"enabled": false}"#))
+            .mount(&server).await;
+        Mock::given(method("GET")).and(path("/flags/search"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"enabled": true}"#))
+            .mount(&server).await;
+        Mock::given(method("GET")).and(path("/todos"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#))
+            .mount(&server).await;
+
+        let run = |flag: &'static str| {
+            let yaml = format!(
+                r#"
+name: "Gated Workflow"
+gate:
+  url: "{0}/flags/{1}"
+  expected_field: "enabled"
+  expected_value: true
+apis:
+  - name: "Todos"
+    url: "{0}/todos"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000
+"#,
+                server.uri(), flag
+            );
+            let workflow: Workflow = serde_yaml::from_str(&yaml).unwrap();
+            async move { run_workflow(workflow, &Settings::default()).await.unwrap() }
+        };
+
+        let skipped = run("checkout").await;
+        assert_eq!(skipped.status, "SKIPPED");
+        assert!(skipped.tasks.is_empty());
+        let task_requests = server.received_requests().await.unwrap().iter().filter(|request| request.url.path() == "/todos").count();
+        assert_eq!(task_requests, 0);
+
+        let passed = run("search").await;
+        assert_eq!(passed.status, "PASSED");
+        assert_eq!(passed.tasks["Todos"].status, "OK");
+    }
+
+    #[tokio::test]
+    async fn test_workflow_passes_with_failures_within_threshold() {
+        assert_eq!(run_workflow_with_failures(1, 1).await, "PASSED");
+    }
+
+    #[tokio::test]
+    async fn test_workflow_fails_with_failures_above_threshold() {
+        assert_eq!(run_workflow_with_failures(2, 1).await, "FAILED");
+    }
+
+    #[tokio::test]
+    async fn test_run_limited_caps_concurrent_workflows() {
+        let in_flight = AtomicUsize::new(0);
+        let max_in_flight = AtomicUsize::new(0);
+        let finished = AtomicUsize::new(0);
+
+        let futures: Vec<_> = (0..6).map(|_| async {
+            let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            max_in_flight.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            in_flight.fetch_sub(1, Ordering::SeqCst);
+            finished.fetch_add(1, Ordering::SeqCst);
+        }).collect();
+
+        run_limited(futures, Some(2)).await;
+
+        assert_eq!(finished.load(Ordering::SeqCst), 6);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_start_offsets_are_staggered_within_jitter() {
+        let jitter = Duration::from_millis(1000);
+        let offsets = start_offsets(20, Some(jitter));
+
+        assert_eq!(offsets.len(), 20);
+        assert!(offsets.iter().all(|offset| *offset <= jitter));
+        assert!(offsets.iter().any(|offset| *offset != offsets[0]), "offsets should not all be equal: {:?}", offsets);
+    }
+
+    #[test]
+    fn test_start_offsets_are_zero_without_jitter() {
+        assert_eq!(start_offsets(3, None), vec![Duration::ZERO; 3]);
+        assert_eq!(Settings { workflow_start_jitter_ms: Some(0), ..Settings::default() }.workflow_start_jitter(), None);
+    }
+
+    #[tokio::test]
+    async fn test_workflow_timeout_records_unfinished_tasks() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let yaml = format!(
+            r#"
+name: "Slow Workflow"
+max_run_secs: 1
+apis:
+  - name: "Slow Endpoint"
+    url: "{}"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000
+"#,
+            server.uri()
+        );
+        let workflow: Workflow = serde_yaml::from_str(&yaml).unwrap();
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+
+        let started = std::time::Instant::now();
+        monitor_single_workflow(Arc::new(workflow), app_state.clone(), Arc::new(Settings::default()), Client::new(), Client::new()).await;
+        assert!(started.elapsed() < std::time::Duration::from_secs(3));
+
+        let state = app_state.lock().await;
+        let task_data = state.task_monitoring_data.lock().await;
+        assert_eq!(task_data["Slow Workflow"]["Slow Endpoint"].status, "TIMEOUT");
+    }
+
+    #[tokio::test]
+    async fn test_workflow_timeout_records_unfinished_load_tests_with_load_test_results() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let yaml = format!(
+            r#"
+name: "Slow Workflow"
+max_run_secs: 1
+apis:
+  - name: "Slow Load Test"
+    url: "{}"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000
+    load_test: true
+    load_test_config:
+      initial_load: 0
+      max_load: 1
+      spawn_rate: 1
+      max_duration_secs: 10
+"#,
+            server.uri()
+        );
+        let workflow: Workflow = serde_yaml::from_str(&yaml).unwrap();
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+
+        monitor_single_workflow(Arc::new(workflow), app_state.clone(), Arc::new(Settings::default()), Client::new(), Client::new()).await;
+
+        let state = app_state.lock().await;
+        assert!(state.task_monitoring_data.lock().await.is_empty());
+        let load_test_data = state.load_test_monitoring_data.lock().await;
+        assert_eq!(load_test_data["Slow Workflow"]["Slow Load Test"].status.as_deref(), Some("TIMEOUT"));
+    }
+
+    #[tokio::test]
+    async fn test_sequential_load_tests_do_not_overlap() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_millis(400)))
+            .mount(&server)
+            .await;
+
+        let run = |sequential: bool| {
+            let load_test = |name: &str| format!(
+                r#"
+  - name: "{}"
+    url: "{}"
+    method: GET
+    headers: {{}}
+    expected_field: "id"
+    response_time_threshold: 2000
+    load_test: true
+    load_test_config:
+      initial_load: 0
+      max_load: 1
+      spawn_rate: 1
+      max_duration_secs: 5
+"#,
+                name, server.uri()
+            );
+            let yaml = format!("name: \"Stress Workflow\"\nsequential_load_tests: {}\napis:{}{}", sequential, load_test("First Service"), load_test("Second Service"));
+            let workflow: Workflow = serde_yaml::from_str(&yaml).unwrap();
+            async move {
+                let started = std::time::Instant::now();
+                monitor_single_workflow(Arc::new(workflow), Arc::new(Mutex::new(AppState::default())), Arc::new(Settings::default()), Client::new(), Client::new()).await;
+                started.elapsed()
+            }
+        };
+
+        // Each load test sends one 400ms request: back to back they take twice as long.
+        assert!(run(true).await >= std::time::Duration::from_millis(800));
+        assert!(run(false).await < std::time::Duration::from_millis(800));
+    }
+
+    /// Records when it started, so tests can check the spacing between order groups.
+    struct RecordingMonitor {
+        api_config: Arc<ApiConfig>,
+        order: usize,
+        starts: Arc<std::sync::Mutex<Vec<(usize, Instant)>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl ApiMonitor for RecordingMonitor {
+        async fn execute(&self, _client: &Client, _workflow_name: &str) -> Result<(), MonitorError> {
+            self.starts.lock().unwrap().push((self.order, Instant::now()));
+            Ok(())
+        }
+
+        fn describe(&self) -> String {
+            format!("Recording monitor {}", self.order)
+        }
+
+        fn response_time_threshold(&self) -> Option<u64> {
+            None
+        }
+
+        fn get_task_order(&self) -> usize {
+            self.order
+        }
+
+        fn api_configs(&self) -> Vec<Arc<ApiConfig>> {
+            vec![self.api_config.clone()]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inter_group_delay_spaces_order_groups() {
+        let workflow: Workflow = serde_yaml::from_str("name: \"Phased Workflow\"\ninter_group_delay_ms: 300\napis: []").unwrap();
+        let starts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let tasks: VecDeque<BoxedMonitor> = [1, 2].into_iter()
+            .map(|order| Box::new(RecordingMonitor { api_config: Arc::new(api_config("GET")), order, starts: starts.clone() }) as BoxedMonitor)
+            .collect();
+
+        run_workflow_tasks(&workflow, tasks, Arc::new(Mutex::new(AppState::default())), Client::new()).await;
+
+        let starts = starts.lock().unwrap();
+        assert_eq!(starts.iter().map(|(order, _)| *order).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(starts[1].1 - starts[0].1 >= Duration::from_millis(300));
+    }
+
+    struct PanickingMonitor {
+        api_config: Arc<ApiConfig>,
+    }
+
+    #[async_trait::async_trait]
+    impl ApiMonitor for PanickingMonitor {
+        async fn execute(&self, _client: &Client, _workflow_name: &str) -> Result<(), MonitorError> {
+            panic!("monitor blew up");
+        }
+
+        fn describe(&self) -> String {
+            format!("Panicking monitor for {}", self.api_config.name)
+        }
+
+        fn response_time_threshold(&self) -> Option<u64> {
+            None
+        }
+
+        fn get_task_order(&self) -> usize {
+            0
+        }
+
+        fn api_configs(&self) -> Vec<Arc<ApiConfig>> {
+            vec![self.api_config.clone()]
+        }
+    }
+
+    #[test]
+    fn test_monitor_error_context_keeps_kind() {
+        let error = MonitorError::Timeout("timed out after 2s".to_string()).context("'Fetch Todo' failed");
+
+        assert!(matches!(error, MonitorError::Timeout(_)));
+        assert_eq!(error.to_string(), "'Fetch Todo' failed: timed out after 2s");
+    }
+
+    #[tokio::test]
+    async fn test_panicking_task_is_recorded_without_stopping_the_workflow() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+
+        let workflow: Workflow = serde_yaml::from_str(&format!(
+            r#"
+name: "Panicky Workflow"
+apis:
+  - name: "Healthy Endpoint"
+    url: "{}"
+    method: GET
+    headers: {{}}
+    expected_field: ""
+    response_time_threshold: 2000
+"#,
+            server.uri()
+        )).unwrap();
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let mut tasks = create_monitor_tasks(&workflow, app_state.clone(), Arc::new(Settings::default()), Client::new());
+        tasks.push_back(Box::new(PanickingMonitor { api_config: Arc::new(api_config("GET")) }));
+
+        run_workflow_tasks(&workflow, tasks, app_state.clone(), Client::new()).await;
+
+        let state = app_state.lock().await;
+        let task_data = state.task_monitoring_data.lock().await;
+        assert_eq!(task_data["Panicky Workflow"]["Create Todo"].status, "PANIC");
+        assert_eq!(task_data["Panicky Workflow"]["Healthy Endpoint"].status, "OK");
+        assert_eq!(state.workflow_status.lock().await["Panicky Workflow"], "FAILED");
+    }
 }
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use serde::Serialize;
+use crate::{config::HealthScoreWeights, loadtest::LoadTestMonitoringData, tasks::MonitoringData};
+
+/// A workflow's latest results folded into one number, computed by `workflow_health`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkflowHealth {
+    /// The weighted mean of `success_rate` and `latency_score`, scaled to 0–100.
+    pub health_score: f64,
+    /// The mean success rate of the workflow's tasks and load tests, from 0 to 1.
+    pub success_rate: f64,
+    /// The mean latency score of the workflow's tasks and load tests, from 0 to 1.
+    pub latency_score: f64,
+    /// The number of task results that were scored.
+    pub task_count: usize,
+    /// The number of load test results that were scored.
+    pub load_test_count: usize,
+}
+
+/// Scores a workflow's latest task and load test results, or returns `None` when it has none.
+///
+/// Every task and every load test contributes one sample with two parts, each from 0 to 1:
+///
+/// - success: 1 for a task whose status is "OK" or "DEGRADED" and 0 otherwise; for a load test,
+///   its successful requests over all requests sent, including those that got no response.
+/// - latency: 1 when the response time (a load test's p95) is within its limit, and
+///   `limit / response time` past it, so twice the limit scores 0.5. Without a limit it is 1.
+///   Tasks are held to their `response_time_fail_ms`, load tests to `response_time_threshold_ms`.
+///
+/// Both parts are averaged over the samples, and
+/// `health_score = 100 * (success_weight * success_rate + latency_weight * latency_score) / (success_weight + latency_weight)`.
+pub fn workflow_health(
+    tasks: Option<&HashMap<String, MonitoringData>>,
+    load_tests: Option<&HashMap<String, LoadTestMonitoringData>>,
+    weights: &HealthScoreWeights,
+) -> Option<WorkflowHealth> {
+    let task_samples = tasks.into_iter().flat_map(HashMap::values).map(|data| {
+        let success = if matches!(data.status.as_str(), "OK" | "DEGRADED") { 1.0 } else { 0.0 };
+        (success, latency_score(u128::from(data.response_time), data.response_time_fail_ms))
+    });
+    let load_test_samples = load_tests.into_iter().flat_map(HashMap::values).filter_map(|data| {
+        let no_response = data.status_code_distribution.get(&0).copied().unwrap_or(0);
+        let attempts = data.total_requests + no_response;
+        (attempts > 0).then(|| (
+            data.success_count as f64 / attempts as f64,
+            latency_score(data.percentile_95th_response_time_ms, data.response_time_threshold_ms),
+        ))
+    });
+
+    let task_count = tasks.map_or(0, HashMap::len);
+    let samples: Vec<(f64, f64)> = task_samples.chain(load_test_samples).collect();
+    if samples.is_empty() {
+        return None;
+    }
+
+    let success_rate = samples.iter().map(|(success, _)| success).sum::<f64>() / samples.len() as f64;
+    let latency_score = samples.iter().map(|(_, latency)| latency).sum::<f64>() / samples.len() as f64;
+    let (success_weight, latency_weight) = (weights.success(), weights.latency());
+    let health_score = 100.0 * (success_weight * success_rate + latency_weight * latency_score) / (success_weight + latency_weight);
+
+    Some(WorkflowHealth {
+        health_score,
+        success_rate,
+        latency_score,
+        task_count,
+        load_test_count: samples.len() - task_count,
+    })
+}
+
+/// 1 within `limit_ms`, decaying as `limit / observed` past it.
+fn latency_score(observed_ms: u128, limit_ms: Option<u64>) -> f64 {
+    match limit_ms {
+        Some(limit_ms) if observed_ms > u128::from(limit_ms) => limit_ms as f64 / observed_ms as f64,
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{sample_load_test_data, sample_task_data};
+
+    fn task(status: &str, response_time: u64, response_time_fail_ms: Option<u64>) -> MonitoringData {
+        MonitoringData {
+            response_time,
+            response_time_micros: response_time * 1000,
+            response_time_fail_ms,
+            ..sample_task_data(status)
+        }
+    }
+
+    #[test]
+    fn test_known_results_produce_known_score() {
+        let tasks = HashMap::from([
+            // Succeeded within its limit: success 1, latency 1.
+            ("Fast".to_string(), task("OK", 100, Some(200))),
+            // Succeeded at twice its limit: success 1, latency 0.5.
+            ("Slow".to_string(), task("DEGRADED", 400, Some(200))),
+            // Failed, with no limit: success 0, latency 1.
+            ("Broken".to_string(), task("ERROR", 50, None)),
+        ]);
+        let load_test = LoadTestMonitoringData {
+            total_requests: 90,
+            success_count: 75,
+            failure_count: 15,
+            // 10 requests got no response, so 75 of 100 succeeded.
+            status_code_distribution: HashMap::from([(0, 10), (200, 75), (500, 15)]),
+            // A p95 of 400ms against 300ms: latency 0.75.
+            percentile_95th_response_time_ms: 400,
+            response_time_threshold_ms: Some(300),
+            threshold_breached: true,
+            ..sample_load_test_data()
+        };
+        let load_tests = HashMap::from([("Busy".to_string(), load_test)]);
+
+        let health = workflow_health(Some(&tasks), Some(&load_tests), &HealthScoreWeights::default()).unwrap();
+
+        // success: (1 + 1 + 0 + 0.75) / 4 = 0.6875; latency: (1 + 0.5 + 1 + 0.75) / 4 = 0.8125
+        assert_eq!((health.task_count, health.load_test_count), (3, 1));
+        assert!((health.success_rate - 0.6875).abs() < 1e-9, "{}", health.success_rate);
+        assert!((health.latency_score - 0.8125).abs() < 1e-9, "{}", health.latency_score);
+        // 100 * (0.7 * 0.6875 + 0.3 * 0.8125) = 72.5
+        assert!((health.health_score - 72.5).abs() < 1e-9, "{}", health.health_score);
+
+        let success_only = HealthScoreWeights { success: Some(1.0), latency: Some(0.0) };
+        let health = workflow_health(Some(&tasks), Some(&load_tests), &success_only).unwrap();
+        assert!((health.health_score - 68.75).abs() < 1e-9, "{}", health.health_score);
+    }
+
+    #[test]
+    fn test_workflow_without_results_has_no_score() {
+        assert_eq!(workflow_health(None, None, &HealthScoreWeights::default()), None);
+    }
+}
@@ -0,0 +1,24 @@
+//! Monitors and load tests HTTP APIs described by YAML workflows.
+//!
+//! The `thunderhawk` binary serves these over HTTP; `run_workflow` runs a workflow directly
+//! from another program.
+
+pub mod appstate;
+pub mod config;
+pub mod utils;
+pub mod factory;
+pub mod loadtest;
+pub mod tasks;
+pub mod tcp_monitor;
+pub mod dns_monitor;
+pub mod ping_monitor;
+pub mod cli;
+pub mod telemetry;
+pub mod statsd;
+pub mod openapi;
+pub mod health;
+
+#[cfg(test)]
+mod test_support;
+
+pub use factory::{run_workflow, WorkflowResult};
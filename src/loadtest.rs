@@ -1,14 +1,15 @@
 
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Serializer, Deserialize};
 use futures::future::join_all;
 use async_trait::async_trait;
-use reqwest::{Client, StatusCode};
-use std::{collections::HashMap, sync::Arc};
+use reqwest::{header::CONTENT_ENCODING, Client, StatusCode};
+use std::{collections::{BTreeMap, HashMap}, sync::{atomic::{AtomicUsize, Ordering}, Arc}};
 use tokio::sync::{Mutex, Semaphore};
 use std::time::Duration;
+use rand::distributions::{Distribution, WeightedIndex};
 use tokio::time::Instant;
 
-use crate::{appstate::AppState, config::{ApiConfig, HttpMethod, LoadTestConfig}, factory::{create_request_builder, ApiMonitor}};
+use crate::{appstate::{evict_results, AppState}, config::{is_expected_status, ApiConfig, DEFAULT_MIN_SAMPLES, HttpMethod, LoadTestConfig, Settings}, factory::{create_request_builder_for_url, ApiMonitor, MonitorError, SharedRateLimiter}, statsd, telemetry::MonitorSpan, utils::http_client::{decoded_len, get_client, read_body_limited, ClientError, HttpClientConfig}};
 
 
 /// Monitors and executes load tests for a specific API endpoint.
@@ -24,6 +25,12 @@ pub struct LoadTest {
     pub app_state: Arc<Mutex<AppState>>,
     /// Configuration specifying the parameters of the load test.
     pub load_test_config: LoadTestConfig,
+    /// Global settings that influence how requests are built.
+    pub settings: Arc<Settings>,
+    /// The workflow's request rate limiter, waited on before every request.
+    pub rate_limiter: Option<SharedRateLimiter>,
+    /// The client requests go through, built once by `load_test_client` and reused by every run.
+    pub client: Client,
 }
 
 /// Represents the aggregated results of a load test.
@@ -31,7 +38,7 @@ pub struct LoadTest {
 /// This struct captures various metrics collected during the execution of a load test,
 /// including counts of successful and failed requests, response time statistics,
 /// and distribution of response status codes.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LoadTestMonitoringData {
     pub api_url: String,
     /// The total number of requests made during the load test.
@@ -44,18 +51,67 @@ pub struct LoadTestMonitoringData {
     pub median_response_time_ms: u128,
     /// The average response time in milliseconds.
     pub average_response_time_ms: u128,
+    /// The median response time in microseconds, for endpoints that answer in under a millisecond.
+    pub median_response_time_micros: u128,
+    /// The average response time in microseconds.
+    pub average_response_time_micros: u128,
     /// The minimum response time in milliseconds observed during the test.
     pub min_response_time_ms: u128,
     /// The maximum response time in milliseconds observed during the test.
     pub max_response_time_ms: u128,
-    /// A distribution of response status codes received.
+    /// A distribution of response status codes received, with requests that got no response
+    /// counted under `0`. Serialized with sorted string keys and the `"0"` bucket always present.
+    #[serde(serialize_with = "serialize_status_code_distribution")]
     pub status_code_distribution: HashMap<u16, usize>,
     /// The 95th percentile response time in milliseconds.
     pub percentile_95th_response_time_ms: u128,
+    /// Response times in milliseconds for each of `report_percentiles`, keyed by label (e.g. "p99.9").
+    pub percentiles: HashMap<String, u128>,
     /// The rate of requests per second.
     pub requests_per_second: f64,
-    /// The average size of the response in bytes (placeholder for actual data collection).
+    /// The average response size in bytes after any content decoding, i.e. the size of the body
+    /// the API returned.
     pub average_bytes_per_response: u128,
+    /// The average on-the-wire response size in bytes, i.e. the body as received, before any
+    /// content decoding.
+    pub average_wire_bytes: u128,
+    /// Response counts per latency bucket as `(upper_bound_ms, count)`. Each bucket counts
+    /// responses slower than the previous bound and no slower than its own; the final
+    /// overflow bucket, bounded by `u128::MAX`, counts everything slower than the last bound.
+    pub latency_histogram: Vec<(u128, usize)>,
+    /// Median, average and 95th percentile latency for the responses of each status code.
+    pub latency_by_status: HashMap<u16, LatencyStats>,
+    /// Statistics for each URL, present when the load test round-robins across several `urls`.
+    pub url_breakdown: Option<HashMap<String, LoadTestStats>>,
+    /// The configured `response_time_threshold_ms` the 95th percentile was compared against.
+    pub response_time_threshold_ms: Option<u64>,
+    /// Whether the 95th percentile response time exceeded `response_time_threshold_ms`.
+    pub threshold_breached: bool,
+    /// The number of response bodies that were cut off at `max_response_bytes`.
+    pub truncated_responses: usize,
+    /// Up to `MAX_SAMPLE_ERRORS` distinct messages from requests that got no response.
+    pub sample_errors: Vec<String>,
+    /// The number of requests abandoned past `abandon_after_ms`. They got no response, so they
+    /// are also counted under status `0`.
+    pub slow_failures: usize,
+    /// The number of requests still in flight when the load test reached its `max_duration_secs`
+    /// and that were cut off there. They got no response, so they are also counted under status `0`.
+    pub cut_off_requests: usize,
+    /// False when `total_requests` is below `min_samples`, so the percentiles should be treated with caution.
+    pub reliable: bool,
+    /// The most requests that were in flight at once, to check the generator kept up with `max_load`.
+    /// A weighted load test reports the peak across its whole mix on every endpoint.
+    pub peak_concurrency: usize,
+    /// The configured `target_rps`, if the load test was paced to a request rate.
+    pub target_rps: Option<f64>,
+    /// The rate at which requests were actually started, present when `target_rps` is set.
+    /// Falls short of the target when `max_in_flight` held requests back or the generator lagged.
+    pub achieved_rps: Option<f64>,
+    /// The `run_label` of the run that produced these results, if it had one.
+    pub run_label: Option<String>,
+    /// Why the load test has no results, e.g. "TIMEOUT" when the workflow's `max_run_secs`
+    /// cut it off or "PANIC". Unset for a load test that ran.
+    pub status: Option<String>,
     /// The HTTP method used in the load test.
     pub method: HttpMethod,
 }
@@ -68,27 +124,22 @@ impl ApiMonitor for LoadTest {
     /// according to the `LoadTestConfig` settings, and records the results.
     ///
     /// # Parameters
-    /// - `client`: The HTTP client used to make requests.
+    /// - `_client`: Unused; requests go through the load test's own `client` instead.
     ///
     /// # Returns
     /// A `Result` indicating the success or failure of the load test execution.
-    async fn execute(&self, client: &Client, workflow_name: &str) -> Result<(), String> {
-        let mut attempt = 0;
-        let max_attempts = self.load_test_config.retry_count.unwrap_or(0) as usize; // Provide a default value if `retry_count` is None and cast to usize for comparison
-
-        while attempt <= max_attempts {
-            match self.run_load_test(client, workflow_name).await {
-                Ok(_) => return Ok(()),
-                Err(e) if attempt < max_attempts => { // Correct comparison with unwrapped and converted retry_count
-                    log::warn!("Load test attempt {} failed: {}. Retrying...", attempt + 1, e);
-                    attempt += 1;
-                    tokio::time::sleep(Duration::from_secs(5)).await; // Backoff before retry
-                },
-                Err(e) => return Err(format!("Load test failed after {} attempts: {}", attempt + 1, e)),
-            }
-        }
+    async fn execute(&self, _client: &Client, workflow_name: &str) -> Result<(), MonitorError> {
+        let client = &self.client;
+        let max_attempts = self.load_test_config.retry_count.unwrap_or(0); // Provide a default value if `retry_count` is None
+        // The deadline is measured from the first attempt and bounds every retry and backoff after it.
+        let deadline = self.load_test_config.overall_deadline_secs
+            .map(|secs| Instant::now() + Duration::from_secs(secs as u64));
 
-        Err("Load test failed: Maximum retry attempts reached".to_string())
+        let stopped = monitoring_stopped(&self.app_state).await;
+
+        let (results, outcome) = run_with_retries(max_attempts, RETRY_BACKOFF, deadline, stopped, || self.run_load_test(client, workflow_name)).await;
+        record_attempt_results(&self.app_state, workflow_name, results).await;
+        outcome
     }
 
     /// Provides a descriptive name for the load test, incorporating the API endpoint's name
@@ -105,7 +156,7 @@ impl ApiMonitor for LoadTest {
     /// # Returns
     /// An optional `u64` representing the response time threshold in milliseconds, if applicable.
     fn response_time_threshold(&self) -> Option<u64> {
-        None
+        self.load_test_config.response_time_threshold_ms
     }
 
     /// Retrieves the order in which the load test should be executed relative to other tasks.
@@ -115,181 +166,915 @@ impl ApiMonitor for LoadTest {
     fn get_task_order(&self) -> usize {
         self.api_config.task_order.unwrap_or(usize::MAX)
     }
+
+    fn api_configs(&self) -> Vec<Arc<ApiConfig>> {
+        vec![self.api_config.clone()]
+    }
+
+    fn is_load_test(&self) -> bool {
+        true
+    }
 }
 
 impl LoadTest {
 
      /// Asynchronously executes the load test against the configured API endpoint.
     ///
-    /// This method simulates concurrent users by spawning asynchronous tasks that
-    /// send requests to the API endpoint, respecting the configuration parameters
-    /// such as initial load, maximum load, spawn rate, and maximum duration of the test.
+    /// Virtual users are ramped up by `ramp_up_users`, each sending one request to the
+    /// API endpoint, and the aggregated metrics are returned for `execute` to record.
     ///
     /// # Parameters
     /// - `client`: The HTTP client used to send requests to the API.
     ///
     /// # Returns
-    /// A `Result<(), String>` indicating the success or failure of the load test.
-    /// On success, it returns `Ok(())`. On failure, it returns an `Err` with an error message.
-    async fn run_load_test(&self, client: &Client, workflow_name: &str) -> Result<(), String> {
-        // Records the start time of the load test to calculate the total duration later.
-        let start_time = Instant::now();
-
-        // Initializes a vector to store results of each load test step.
-        let mut all_results = Vec::new();
-
-        // Sets a sensible default for max_duration if not specified, here assumed as 1 second for simplicity.
-        let sensible_max_duration_secs: u64 = 1;
-        // Retrieves max_duration from the test configuration, using the sensible default if not specified.
-        let max_duration_secs = self.load_test_config.max_duration_secs
-                                    .map(|secs| secs as u64)
-                                    .unwrap_or(sensible_max_duration_secs);
-        // Converts the duration from seconds to a Duration object for easier comparison.
-        let max_duration = Duration::from_secs(max_duration_secs);
-
-        // Initializes the current load based on the test configuration or defaults to 0.
-        let mut current_load = self.load_test_config.initial_load.unwrap_or_default();
-        // Retrieves the maximum load from the configuration or uses the maximum usize value if not specified.
-        let max_load = self.load_test_config.max_load.unwrap_or(usize::MAX);
-        // Retrieves the spawn rate (users per second) from the configuration, defaulting to 1 if not specified.
-        let spawn_rate = self.load_test_config.spawn_rate.unwrap_or(1);
-
-        // Sets up a repeating interval of 1 second to control the spawn rate.
-        let mut interval = tokio::time::interval(Duration::from_secs(1));
-
-        // Continues to execute the load test until the current load reaches the max load or the max duration is exceeded.
-        while current_load < max_load && start_time.elapsed() < max_duration {
-            // Waits for the next tick of the interval, effectively pausing for 1 second.
-            interval.tick().await;
-
-            // Calculates the number of new users to spawn this tick, without exceeding the max load.
-            // let new_users = std::cmp::min(spawn_rate, max_load - current_load);
-            let new_users = if current_load >= max_load {
-                0
-            } else {
-                std::cmp::min(spawn_rate, max_load - current_load)
-            };
+    /// The aggregated metrics, along with a `Result<(), MonitorError>` indicating the success
+    /// or failure of the load test.
+    async fn run_load_test(&self, client: &Client, workflow_name: &str) -> (AttemptResults, Result<(), MonitorError>) {
+        let mut span = MonitorSpan::start("load_test", workflow_name, &self.api_config.name, &self.api_config.url, &self.api_config.method);
 
-            // Updates the current load by adding the new users.
-            current_load += new_users;
-
-            // Logs the number of new users being spawned and the total current load.
-            log::info!("Spawning {} new users, total users: {}", new_users, current_load);
-
-            // Creates a semaphore with a number of permits equal to the current load, controlling concurrent access.
-            let semaphore = Arc::new(Semaphore::new(current_load));
-
-            // Maps each new user to a spawned task, creating a vector of these tasks.
-            let tasks = (0..new_users).map(|_| {
-                // Clones the client and API configuration for use within the async task.
-                let client_clone = client.clone();
-                let api_config_clone = self.api_config.clone();
-                let semaphore_clone = semaphore.clone();
-
-                // Spawns an asynchronous task for each user.
-                tokio::spawn(async move {
-                    // Acquires a permit from the semaphore before proceeding, ensuring concurrency control.
-                    let _permit = semaphore_clone.acquire_owned().await.expect("Failed to acquire semaphore permit");
-                    // Records the start time of the request for duration calculation.
-                    let start = Instant::now();
-
-                    // Attempts to create a request builder using the client and API configuration.
-                    let request_result = create_request_builder(&client_clone, &api_config_clone);
-                    match request_result {
-                        // If successful, sends the request and awaits the response.
-                        Ok(request_builder) => {
-                            let response = request_builder.send().await;
-                            match response {
-                                // On successful response, extracts the status code, response body, and calculates the duration.
-                                Ok(resp) => {
-                                    let status = resp.status();
-                                    let body = resp.text().await.unwrap_or_default();
-                                    let bytes = body.len();
-                                    let duration = start.elapsed();
-                                    // Returns the status code, duration, and response size.
-                                    Ok((status, duration, bytes))
-                                },
-                                // Logs any errors encountered while sending the request.
-                                Err(e) => {
-                                    log::error!("Request error: {}", e);
-                                    Err(e.to_string())
-                                },
-                            }
-                        },
-                        // Logs any errors encountered while creating the request builder.
-                        Err(e) => {
-                            log::error!("Request creation error: {}", e);
-                            Err(e)
-                        },
-                    }
-                })
-            }).collect::<Vec<_>>();
-
-
-            let join_results = join_all(tasks).await;
-            let step_results = join_results.into_iter().map(|join_result| {
-                join_result.unwrap_or_else(|join_error| {
-                    log::error!("Task panicked: {:?}", join_error);
-                    Err("Task panicked".to_string())
-                })
-            }).collect::<Vec<_>>();
-
-            all_results.extend(step_results);
-
-            if start_time.elapsed() >= max_duration {
-                log::info!("Max duration reached, ending load test early.");
-                break;
+        // Requests cycle through the target URLs in turn, so replicas get an even share.
+        let urls = self.api_config.target_urls();
+        let next_url = AtomicUsize::new(0);
+        // Body variants rotate on their own counter, so they don't pair up with particular URLs.
+        let body_variants = self.api_config.body_variant_configs();
+        let next_body = AtomicUsize::new(0);
+        let abandon_after = self.load_test_config.abandon_after_ms.map(Duration::from_millis);
+        let in_flight = Arc::new(InFlight::default());
+        let (all_results, achieved_rps) = run_users(&self.load_test_config, |deadline| {
+            let index = next_url.fetch_add(1, Ordering::Relaxed) % urls.len();
+            let api_config = body_variants[next_body.fetch_add(1, Ordering::Relaxed) % body_variants.len()].clone();
+            let request = send_load_test_request(client.clone(), api_config, urls[index].clone(), self.settings.clone(), self.rate_limiter.clone(), abandon_after, deadline);
+            let in_flight = in_flight.clone();
+            async move { (index, in_flight.track(request).await) }
+        }).await;
+
+        // With several URLs, the totals are complemented by a breakdown per URL.
+        let url_breakdown = (urls.len() > 1).then(|| {
+            let mut results_by_url: Vec<Vec<RequestOutcome>> = vec![Vec::new(); urls.len()];
+            for (index, outcome) in &all_results {
+                results_by_url[*index].push(outcome.clone());
             }
+            urls.iter().cloned().zip(results_by_url)
+                .map(|(url, results)| (url, analyze_results(&responses(results), self.load_test_config.latency_buckets(), self.api_config.expected_status_codes.as_deref(), self.load_test_config.report_percentiles())))
+                .collect()
+        });
+
+        let mut load_test_data = build_load_test_data(&self.api_config, &self.load_test_config, all_results.into_iter().map(|(_, outcome)| outcome).collect());
+        load_test_data.url_breakdown = url_breakdown;
+        load_test_data.peak_concurrency = in_flight.peak();
+        load_test_data.achieved_rps = achieved_rps;
+
+        let results = vec![(self.api_config.name.clone(), load_test_data)];
+        let outcome = attempt_outcome(&results);
+        span.record_status(if outcome.is_ok() { "OK" } else { "ERROR" }, None);
+
+        (results, outcome)
+    }
+}
+
+
+/// Drives a weighted mix of endpoints as a single load test.
+///
+/// Each virtual user picks one of the workflow's load-test-enabled APIs at random, in
+/// proportion to its `weight`, which models realistic traffic better than testing each
+/// endpoint in isolation. Results are recorded per endpoint under each API's name.
+#[derive(Debug, Clone)]
+pub struct WeightedLoadTest {
+    /// The endpoints taking part in the mix.
+    pub api_configs: Vec<Arc<ApiConfig>>,
+    /// A reference to the shared application state where test results are recorded.
+    pub app_state: Arc<Mutex<AppState>>,
+    /// Configuration specifying the parameters of the load test as a whole.
+    pub load_test_config: LoadTestConfig,
+    /// Global settings that influence how requests are built.
+    pub settings: Arc<Settings>,
+    /// The workflow's request rate limiter, waited on before every request.
+    pub rate_limiter: Option<SharedRateLimiter>,
+    /// The client requests go through, built once by `load_test_client` and reused by every run.
+    pub client: Client,
+}
+
+#[async_trait]
+impl ApiMonitor for WeightedLoadTest {
+
+    /// Executes the weighted load test, retrying according to the `LoadTestConfig` settings.
+    async fn execute(&self, _client: &Client, workflow_name: &str) -> Result<(), MonitorError> {
+        let client = &self.client;
+        let max_attempts = self.load_test_config.retry_count.unwrap_or(0);
+        let deadline = self.load_test_config.overall_deadline_secs
+            .map(|secs| Instant::now() + Duration::from_secs(secs as u64));
+
+        let stopped = monitoring_stopped(&self.app_state).await;
+
+        let (results, outcome) = run_with_retries(max_attempts, RETRY_BACKOFF, deadline, stopped, || self.run_weighted_load_test(client)).await;
+        record_attempt_results(&self.app_state, workflow_name, results).await;
+        outcome
+    }
+
+    fn describe(&self) -> String {
+        let names: Vec<&str> = self.api_configs.iter().map(|api| api.name.as_str()).collect();
+        format!("WeightedLoadTest for {}", names.join(", "))
+    }
+
+    fn response_time_threshold(&self) -> Option<u64> {
+        self.load_test_config.response_time_threshold_ms
+    }
+
+    /// Runs with the earliest order of the endpoints in the mix.
+    fn get_task_order(&self) -> usize {
+        self.api_configs.iter()
+            .map(|api| api.task_order.unwrap_or(usize::MAX))
+            .min()
+            .unwrap_or(usize::MAX)
+    }
+
+    fn api_configs(&self) -> Vec<Arc<ApiConfig>> {
+        self.api_configs.clone()
+    }
+
+    fn is_load_test(&self) -> bool {
+        true
+    }
+}
+
+impl WeightedLoadTest {
+
+    /// Ramps up virtual users that each hit a weighted-randomly chosen endpoint, then
+    /// returns the metrics of every endpoint separately.
+    async fn run_weighted_load_test(&self, client: &Client) -> (AttemptResults, Result<(), MonitorError>) {
+        let weights: Vec<u32> = self.api_configs.iter().map(|api| api.weight.unwrap_or(1)).collect();
+        let picker = match WeightedPicker::new(&weights) {
+            Ok(picker) => picker,
+            Err(message) => return (Vec::new(), Err(MonitorError::Config(message))),
+        };
+
+        let body_variants: Vec<Vec<Arc<ApiConfig>>> = self.api_configs.iter().map(ApiConfig::body_variant_configs).collect();
+        let next_body: Vec<AtomicUsize> = self.api_configs.iter().map(|_| AtomicUsize::new(0)).collect();
+        let abandon_after = self.load_test_config.abandon_after_ms.map(Duration::from_millis);
+        let in_flight = Arc::new(InFlight::default());
+        let (all_results, achieved_rps) = run_users(&self.load_test_config, |deadline| {
+            let index = picker.pick();
+            let variants = &body_variants[index];
+            let api_config = variants[next_body[index].fetch_add(1, Ordering::Relaxed) % variants.len()].clone();
+            let request = send_load_test_request(client.clone(), api_config.clone(), api_config.url.clone(), self.settings.clone(), self.rate_limiter.clone(), abandon_after, deadline);
+            let in_flight = in_flight.clone();
+            async move { (index, in_flight.track(request).await) }
+        }).await;
+
+        let mut results_by_endpoint: Vec<Vec<RequestOutcome>> = vec![Vec::new(); self.api_configs.len()];
+        for (index, outcome) in all_results {
+            results_by_endpoint[index].push(outcome);
         }
 
-        // Once the load test loop is complete, calculate the total duration
-        let total_duration = start_time.elapsed();
-        log::info!("Load test completed. Total duration: {:?}", total_duration);
+        let results = self.api_configs.iter().zip(results_by_endpoint).map(|(api_config, results)| {
+            let mut load_test_data = build_load_test_data(api_config, &self.load_test_config, results);
+            load_test_data.peak_concurrency = in_flight.peak();
+            load_test_data.achieved_rps = achieved_rps;
+            (api_config.name.clone(), load_test_data)
+        }).collect();
+        let outcome = attempt_outcome(&results);
+
+        (results, outcome)
+    }
+}
+
+
+/// Chooses indices at random in proportion to a list of weights.
+pub struct WeightedPicker {
+    distribution: WeightedIndex<u32>,
+}
 
-        // Filter the results to only include successful requests and calculate statistics.
-        let filtered_results: Vec<(StatusCode, Duration, usize)> = all_results.into_iter()
-            .filter_map(|result| match result {
-                Ok((status, duration, bytes)) => Some((status, duration, bytes)),
-                Err(_) => None,
+impl WeightedPicker {
+    /// Fails when there are no weights or they are all zero.
+    pub fn new(weights: &[u32]) -> Result<Self, String> {
+        WeightedIndex::new(weights)
+            .map(|distribution| WeightedPicker { distribution })
+            .map_err(|e| format!("Invalid endpoint weights {:?}: {}", weights, e))
+    }
+
+    pub fn pick(&self) -> usize {
+        self.distribution.sample(&mut rand::thread_rng())
+    }
+}
+
+
+/// Counts the load test requests in flight and remembers the highest count seen.
+#[derive(Debug, Default)]
+struct InFlight {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl InFlight {
+    /// Runs `request`, counting it as in flight until it completes.
+    async fn track<T>(self: &Arc<Self>, request: impl std::future::Future<Output = T>) -> T {
+        let _entry = self.enter();
+        request.await
+    }
+
+    /// Counts one more request as in flight until the returned guard is dropped, which also
+    /// happens when the task holding it panics or is cancelled.
+    fn enter(self: &Arc<Self>) -> InFlightEntry {
+        let now = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(now, Ordering::SeqCst);
+        InFlightEntry(self.clone())
+    }
+
+    fn current(&self) -> usize {
+        self.current.load(Ordering::SeqCst)
+    }
+
+    fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+/// A request counted by `InFlight::enter`, for as long as it is held.
+struct InFlightEntry(Arc<InFlight>);
+
+impl Drop for InFlightEntry {
+    fn drop(&mut self) {
+        self.0.current.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The outcome of a single load test request: status code, duration, decoded size in bytes,
+/// on-the-wire size in bytes and whether the body was cut off at `max_response_bytes`, or why
+/// no response was received.
+type RequestOutcome = Result<(StatusCode, Duration, usize, usize, bool), RequestFailure>;
+
+/// Why a load test request produced no response to measure.
+#[derive(Debug, Clone, PartialEq)]
+enum RequestFailure {
+    /// The request could not be built or failed in transit, with the error message.
+    Error(String),
+    /// The request outlasted the `abandon_after_ms` budget and was dropped unfinished.
+    Slow(Duration),
+    /// The request was still in flight when the load test reached its deadline.
+    CutOff,
+}
+
+impl std::fmt::Display for RequestFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestFailure::Error(message) => f.write_str(message),
+            RequestFailure::Slow(budget) => write!(f, "slow: abandoned after {:?}", budget),
+            RequestFailure::CutOff => f.write_str("cut off at the load test deadline"),
+        }
+    }
+}
+
+/// Runs the load test's users, paced to `target_rps` when it is set and ramped up otherwise.
+/// Each user is given the test's deadline, past which it should give up on its request.
+/// Returns their outputs and, for a paced test, the achieved request rate.
+async fn run_users<T, F, Fut>(load_test_config: &LoadTestConfig, spawn_user: F) -> (Vec<T>, Option<f64>)
+where
+    F: FnMut(Instant) -> Fut,
+    Fut: std::future::Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    match load_test_config.target_rps {
+        Some(target_rps) => {
+            let (results, achieved_rps) = pace_users(load_test_config, target_rps, spawn_user).await;
+            (results, Some(achieved_rps))
+        },
+        None => (ramp_up_users(load_test_config, spawn_user).await, None),
+    }
+}
+
+/// Starts one user every `1 / target_rps` seconds for the test's maximum duration, an open
+/// model where the request rate is fixed and concurrency follows from the target's latency.
+///
+/// Start times are scheduled from the beginning of the test rather than from the previous
+/// start, so a late start does not push every later one back. A start that would take the
+/// outstanding users past `max_in_flight` is skipped. Returns the users' outputs and the
+/// rate at which they were actually started.
+async fn pace_users<T, F, Fut>(load_test_config: &LoadTestConfig, target_rps: f64, mut spawn_user: F) -> (Vec<T>, f64)
+where
+    F: FnMut(Instant) -> Fut,
+    Fut: std::future::Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let start_time = Instant::now();
+    let max_duration = Duration::from_secs(load_test_config.max_duration_secs.map_or(1, |secs| secs as u64));
+    let mut tasks: Vec<tokio::task::JoinHandle<T>> = Vec::new();
+    // Users still running, kept up to date by the users themselves so checking it stays cheap.
+    let outstanding = Arc::new(InFlight::default());
+    let mut skipped = 0;
+
+    for slot in 0u64.. {
+        let due = Duration::from_secs_f64(slot as f64 / target_rps);
+        if due >= max_duration {
+            break;
+        }
+        tokio::time::sleep_until(start_time + due).await;
+
+        if let Some(max_in_flight) = load_test_config.max_in_flight {
+            if outstanding.current() >= max_in_flight {
+                skipped += 1;
+                continue;
+            }
+        }
+        let user = spawn_user(start_time + max_duration);
+        let entry = outstanding.enter();
+        tasks.push(tokio::spawn(async move {
+            let _entry = entry;
+            user.await
+        }));
+    }
+
+    // The rate is taken over the whole window, or longer if the generator fell behind schedule.
+    let window = start_time.elapsed().max(max_duration);
+    let achieved_rps = tasks.len() as f64 / window.as_secs_f64();
+    if skipped > 0 {
+        log::warn!("Backpressure: skipped {} requests at max_in_flight {}; achieved {:.1} of {} requests per second",
+            skipped, load_test_config.max_in_flight.unwrap_or_default(), achieved_rps, target_rps);
+    }
+    log::info!("Started {} requests at {:.1} per second (target {})", tasks.len(), achieved_rps, target_rps);
+
+    let join_results = join_all(tasks).await;
+    let all_results = join_results.into_iter().filter_map(|join_result| {
+        join_result.map_err(|join_error| log::error!("Task panicked: {:?}", join_error)).ok()
+    }).collect();
+
+    (all_results, achieved_rps)
+}
+
+/// Ramps virtual users up according to `load_test_config`, running the future produced by
+/// `spawn_user` once per user, and collects their outputs.
+///
+/// This simulates concurrent users by spawning asynchronous tasks, respecting the
+/// configuration parameters such as initial load, maximum load, spawn rate, and maximum
+/// duration of the test, or the load schedule when one is configured. Users started on
+/// earlier ticks keep running while later ticks add more. Under a schedule, each tick tops
+/// the users still running up to the scheduled target, and spawns none while they already
+/// meet or exceed it, so concurrency follows the schedule down as well as up. Either way, a
+/// single semaphore sized to the maximum load holds a permit for every running user, so no
+/// more than the maximum load are in flight at once, across ticks. With `max_in_flight`, a
+/// tick tops the outstanding users up to the cap at most, and spawns none while the cap is
+/// reached. Users whose task panics are logged and left out of the results.
+async fn ramp_up_users<T, F, Fut>(load_test_config: &LoadTestConfig, mut spawn_user: F) -> Vec<T>
+where
+    F: FnMut(Instant) -> Fut,
+    Fut: std::future::Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    // Records the start time of the load test to calculate the total duration later.
+    let start_time = Instant::now();
+
+    // Initializes a vector to store the task of every spawned user.
+    let mut tasks: Vec<tokio::task::JoinHandle<T>> = Vec::new();
+    // Users still running, kept up to date by the users themselves so checking it stays cheap.
+    let outstanding = Arc::new(InFlight::default());
+
+    // A schedule replaces the linear ramp: each tick tops the running users up to the scheduled target.
+    let schedule = load_test_config.load_schedule.clone().map(|mut schedule| {
+        schedule.sort_unstable();
+        schedule
+    });
+
+    // Sets a sensible default for max_duration if not specified, here assumed as 1 second for simplicity.
+    // A schedule runs until its last point instead.
+    let sensible_max_duration_secs: u64 = schedule.as_ref()
+        .and_then(|schedule| schedule.last())
+        .map_or(1, |(secs, _)| *secs as u64 + 1);
+    // Retrieves max_duration from the test configuration, using the sensible default if not specified.
+    let max_duration_secs = load_test_config.max_duration_secs
+                                .map(|secs| secs as u64)
+                                .unwrap_or(sensible_max_duration_secs);
+    // Converts the duration from seconds to a Duration object for easier comparison.
+    let max_duration = Duration::from_secs(max_duration_secs);
+
+    // Initializes the current load based on the test configuration or defaults to 0.
+    let mut current_load = if schedule.is_some() { 0 } else { load_test_config.initial_load.unwrap_or_default() };
+    // Retrieves the maximum load from the configuration or uses the maximum usize value if not specified.
+    let max_load = match &schedule {
+        Some(schedule) => schedule.iter().map(|(_, users)| *users).max().unwrap_or_default(),
+        None => load_test_config.max_load.unwrap_or(usize::MAX),
+    };
+    // Retrieves the spawn rate (users per second) from the configuration, defaulting to 1 if not specified.
+    let spawn_rate = load_test_config.spawn_rate.unwrap_or(1);
+
+    // One semaphore for the whole test: every user holds a permit until it finishes, so users
+    // still in flight from earlier ticks count against the cap.
+    let semaphore = Arc::new(Semaphore::new(max_load.min(Semaphore::MAX_PERMITS)));
+
+    // Sets up a repeating interval of 1 second to control the spawn rate.
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+    // Continues to execute the load test until the current load reaches the max load or the max duration is exceeded.
+    // A schedule runs for its whole duration, since its target can rise again after users finish.
+    while (schedule.is_some() || current_load < max_load) && start_time.elapsed() < max_duration {
+        // Waits for the next tick of the interval, effectively pausing for 1 second.
+        interval.tick().await;
+        // A schedule's tick at its very end would only restart users right as it stops.
+        if schedule.is_some() && start_time.elapsed() >= max_duration {
+            break;
+        }
+
+        // Calculates the number of new users to spawn this tick, without exceeding the max load.
+        let new_users = if let Some(schedule) = &schedule {
+            scheduled_load(schedule, start_time.elapsed()).saturating_sub(outstanding.current())
+        } else if current_load >= max_load {
+            0
+        } else {
+            std::cmp::min(spawn_rate, max_load - current_load)
+        };
+
+        // Holds back users that would take the outstanding tasks past `max_in_flight`.
+        let new_users = match load_test_config.max_in_flight {
+            Some(max_in_flight) => {
+                let outstanding = outstanding.current();
+                let allowed = max_in_flight.saturating_sub(outstanding);
+                if allowed < new_users {
+                    log::warn!("Backpressure: {} users still in flight (max_in_flight {}); spawning {} of {} new users",
+                        outstanding, max_in_flight, allowed, new_users);
+                }
+                new_users.min(allowed)
+            },
+            None => new_users,
+        };
+        // Only this loop takes permits, so the ones available now are still there to take below.
+        let new_users = new_users.min(semaphore.available_permits());
+
+        // Updates the current load by adding the new users.
+        current_load += new_users;
+
+        // Logs the number of new users being spawned and the total current load.
+        log::info!("Spawning {} new users, total users: {}", new_users, current_load);
+
+        // Maps each new user to a spawned task; the next tick does not wait for them to finish.
+        tasks.extend((0..new_users).map(|_| {
+            let user = spawn_user(start_time + max_duration);
+            let entry = outstanding.enter();
+            let permit = semaphore.clone().try_acquire_owned().expect("permits were counted before spawning");
+
+            // Spawns an asynchronous task for each user, which releases its permit when it finishes.
+            tokio::spawn(async move {
+                let _entry = entry;
+                let _permit = permit;
+                user.await
             })
-            .collect();
+        }));
 
-        // Analyze the filtered results to compute summary statistics.
-        let (success_count,
-            failure_count,
-            median_response_time_ms,
-            average_response_time_ms,
-            min_response_time_ms,
-            max_response_time_ms,
-            status_code_distribution,
-            percentile_95th_response_time_ms,
-            requests_per_second,
-            average_bytes_per_response) = analyze_results(&filtered_results);
-
-        // Construct LoadTestMonitoringData
-        let load_test_data = LoadTestMonitoringData {
-            api_url: self.api_config.url.clone(),
-            total_requests: filtered_results.len(),
-            success_count,
-            failure_count,
-            median_response_time_ms,
-            average_response_time_ms,
-            min_response_time_ms,
-            max_response_time_ms,
-            status_code_distribution,
-            percentile_95th_response_time_ms,
-            requests_per_second,
-            average_bytes_per_response,
-            method: self.api_config.method.clone(),
+        if start_time.elapsed() >= max_duration {
+            log::info!("Max duration reached, ending load test early.");
+            break;
+        }
+    }
+
+    let join_results = join_all(tasks).await;
+    let all_results: Vec<T> = join_results.into_iter().filter_map(|join_result| {
+        join_result.map_err(|join_error| log::error!("Task panicked: {:?}", join_error)).ok()
+    }).collect();
+
+    // Once the load test loop is complete, calculate the total duration
+    let total_duration = start_time.elapsed();
+    log::info!("Load test completed. Total duration: {:?}", total_duration);
+
+    all_results
+}
+
+/// The target number of users `elapsed` into a load schedule sorted by time: linearly
+/// interpolated between points, and held at the first and last point outside them.
+fn scheduled_load(schedule: &[(usize, usize)], elapsed: Duration) -> usize {
+    let elapsed_secs = elapsed.as_secs_f64();
+    let Some(next) = schedule.iter().position(|(secs, _)| *secs as f64 > elapsed_secs) else {
+        return schedule.last().map_or(0, |(_, users)| *users);
+    };
+    if next == 0 {
+        return schedule[0].1;
+    }
+
+    let (start_secs, start_users) = schedule[next - 1];
+    let (end_secs, end_users) = schedule[next];
+    let progress = (elapsed_secs - start_secs as f64) / (end_secs - start_secs) as f64;
+    (start_users as f64 + (end_users as f64 - start_users as f64) * progress).floor() as usize
+}
+
+/// Builds the client load tests send requests through: the one `settings` describe, except
+/// that bodies are handed over undecoded, so both their on-the-wire and decoded sizes are known.
+pub(crate) fn load_test_client(settings: &Settings) -> Result<Client, ClientError> {
+    get_client(Some(HttpClientConfig { decompress: Some(false), ..settings.http_client_config() }))
+}
+
+/// Sends one load test request to `url` and measures it. With an `abandon_after` budget, a
+/// request still waiting for its response or body when the budget runs out is dropped and
+/// recorded as a slow failure, so the generator doesn't wait on it. A request still running
+/// at `deadline`, the end of the load test, is dropped and recorded as cut off, so slow
+/// requests can't stretch the test far past `max_duration_secs`. With a `rate_limiter`, the
+/// request first waits its turn, and is cut off if that turn doesn't come before `deadline`.
+async fn send_load_test_request(client: Client, api_config: Arc<ApiConfig>, url: String, settings: Arc<Settings>, rate_limiter: Option<SharedRateLimiter>, abandon_after: Option<Duration>, deadline: Instant) -> RequestOutcome {
+    if let Some(limiter) = &rate_limiter {
+        if tokio::time::timeout_at(deadline, limiter.until_ready()).await.is_err() {
+            return Err(RequestFailure::CutOff);
+        }
+    }
+
+    // Records the start time of the request for duration calculation, after any wait for the limiter.
+    let start = Instant::now();
+
+    // Attempts to create a request builder using the client and API configuration.
+    // Every spawned request builds its own request, so each gets a distinct request ID.
+    let request_result = create_request_builder_for_url(&client, &api_config, &url, &settings);
+    match request_result {
+        // If successful, sends the request and awaits the response.
+        Ok(prepared) => {
+            let exchange = async {
+                let response = prepared.builder.send().await;
+                match response {
+                    // On successful response, extracts the status code, response body, and calculates the duration.
+                    Ok(resp) => {
+                        let status = resp.status();
+                        // Content-Encoding has to be read before the body is consumed.
+                        let content_encoding = resp.headers()
+                            .get(CONTENT_ENCODING)
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string);
+                        let (body, truncated) = read_body_limited(resp, settings.max_response_bytes).await.unwrap_or_default();
+                        // The body arrives undecoded (see `load_test_client`), so its length is the wire size.
+                        let wire_bytes = body.len();
+                        let bytes = decoded_len(content_encoding.as_deref(), &body);
+                        let duration = start.elapsed();
+                        // Returns the status code, duration, decoded and on-the-wire response sizes.
+                        Ok((status, duration, bytes, wire_bytes, truncated))
+                    },
+                    // Logs any errors encountered while sending the request.
+                    Err(e) => {
+                        let message = error_chain(&e);
+                        log::error!("Request error: {}", message);
+                        Err(RequestFailure::Error(message))
+                    },
+                }
+            };
+            let exchange = async {
+                match abandon_after {
+                    Some(budget) => tokio::time::timeout(budget, exchange).await.unwrap_or_else(|_| {
+                        log::warn!("Abandoned request to {} after {:?}", url, budget);
+                        Err(RequestFailure::Slow(budget))
+                    }),
+                    None => exchange.await,
+                }
+            };
+            tokio::time::timeout_at(deadline, exchange).await.unwrap_or_else(|_| {
+                log::warn!("Cut off request to {} at the load test deadline", url);
+                Err(RequestFailure::CutOff)
+            })
+        },
+        // Logs any errors encountered while creating the request builder.
+        Err(e) => {
+            log::error!("Request creation error: {}", e);
+            Err(RequestFailure::Error(e))
+        },
+    }
+}
+
+/// Summarizes the outcomes of a load test against one endpoint.
+fn build_load_test_data(api_config: &ApiConfig, load_test_config: &LoadTestConfig, all_results: Vec<RequestOutcome>) -> LoadTestMonitoringData {
+    let truncated_responses = all_results.iter().filter(|outcome| matches!(outcome, Ok((.., true)))).count();
+    if truncated_responses > 0 {
+        log::warn!("'{}' had {} response bodies truncated at max_response_bytes", api_config.name, truncated_responses);
+    }
+
+    // Keep a few error messages, and count the errors, before the failed requests are filtered out.
+    let sample_errors = sample_errors(&all_results);
+    let network_errors = all_results.iter().filter(|outcome| outcome.is_err()).count();
+    let slow_failures = all_results.iter().filter(|outcome| matches!(outcome, Err(RequestFailure::Slow(_)))).count();
+    if slow_failures > 0 {
+        log::warn!("'{}' had {} requests abandoned past abandon_after_ms", api_config.name, slow_failures);
+    }
+    let cut_off_requests = all_results.iter().filter(|outcome| matches!(outcome, Err(RequestFailure::CutOff))).count();
+    if cut_off_requests > 0 {
+        log::warn!("'{}' had {} requests cut off at the end of the load test", api_config.name, cut_off_requests);
+    }
+
+    // Filter the results to only include successful requests and calculate statistics.
+    let filtered_results = responses(all_results);
+
+    // Analyze the filtered results to compute summary statistics.
+    let mut stats = analyze_results(&filtered_results, load_test_config.latency_buckets(), api_config.expected_status_codes.as_deref(), load_test_config.report_percentiles());
+    if network_errors > 0 {
+        stats.status_code_distribution.insert(0, network_errors);
+    }
+    let threshold_breached = load_test_config.response_time_threshold_ms
+        .is_some_and(|threshold_ms| stats.percentile_95th_response_time_ms > u128::from(threshold_ms));
+    if threshold_breached {
+        log::warn!("'{}' p95 response time of {}ms exceeds the {}ms threshold",
+            api_config.name, stats.percentile_95th_response_time_ms, load_test_config.response_time_threshold_ms.unwrap_or_default());
+    }
+
+    let min_samples = load_test_config.min_samples.unwrap_or(DEFAULT_MIN_SAMPLES);
+    let reliable = filtered_results.len() >= min_samples;
+    if !reliable {
+        log::warn!("'{}' got only {} responses (fewer than {}); its percentiles are unreliable",
+            api_config.name, filtered_results.len(), min_samples);
+    }
+
+    LoadTestMonitoringData {
+        api_url: api_config.url.clone(),
+        total_requests: filtered_results.len(),
+        success_count: stats.success_count,
+        failure_count: stats.failure_count,
+        median_response_time_ms: stats.median_response_time_ms,
+        average_response_time_ms: stats.average_response_time_ms,
+        median_response_time_micros: stats.median_response_time_micros,
+        average_response_time_micros: stats.average_response_time_micros,
+        min_response_time_ms: stats.min_response_time_ms,
+        max_response_time_ms: stats.max_response_time_ms,
+        status_code_distribution: stats.status_code_distribution,
+        percentile_95th_response_time_ms: stats.percentile_95th_response_time_ms,
+        percentiles: stats.percentiles,
+        requests_per_second: stats.requests_per_second,
+        average_bytes_per_response: stats.average_bytes_per_response,
+        average_wire_bytes: stats.average_wire_bytes,
+        latency_histogram: stats.latency_histogram,
+        latency_by_status: stats.latency_by_status,
+        url_breakdown: None,
+        response_time_threshold_ms: load_test_config.response_time_threshold_ms,
+        threshold_breached,
+        truncated_responses,
+        sample_errors,
+        slow_failures,
+        cut_off_requests,
+        reliable,
+        peak_concurrency: 0, // Filled in by the caller, which tracked the requests
+        target_rps: load_test_config.target_rps,
+        achieved_rps: None, // Filled in by the caller, which paced the requests
+        run_label: load_test_config.run_label.clone(),
+        status: None,
+        method: api_config.method.clone(),
+    }
+}
+
+/// Writes a status code distribution as a map with string keys in ascending code order, so
+/// output diffs cleanly, adding an empty `"0"` (no response) bucket when there were no errors.
+fn serialize_status_code_distribution<S: Serializer>(distribution: &HashMap<u16, usize>, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut sorted: BTreeMap<u16, usize> = distribution.iter().map(|(code, count)| (*code, *count)).collect();
+    sorted.entry(0).or_insert(0);
+    serializer.collect_map(sorted.into_iter().map(|(code, count)| (code.to_string(), count)))
+}
+
+/// Formats an error with its sources, since reqwest's own message rarely says what went wrong.
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+/// Number of distinct request error messages kept per load test.
+const MAX_SAMPLE_ERRORS: usize = 10;
+
+/// The first `MAX_SAMPLE_ERRORS` distinct error messages among the outcomes, in the order seen.
+fn sample_errors(outcomes: &[RequestOutcome]) -> Vec<String> {
+    let mut samples: Vec<String> = Vec::new();
+    for error in outcomes.iter().filter_map(|outcome| outcome.as_ref().err()) {
+        if samples.len() == MAX_SAMPLE_ERRORS {
+            break;
+        }
+        let error = error.to_string();
+        if !samples.contains(&error) {
+            samples.push(error);
+        }
+    }
+    samples
+}
+
+/// Keeps the outcomes that produced a response, dropping requests that failed outright.
+fn responses(outcomes: Vec<RequestOutcome>) -> Vec<(StatusCode, Duration, usize, usize)> {
+    outcomes.into_iter()
+        .filter_map(Result::ok)
+        .map(|(status, duration, bytes, wire_bytes, _)| (status, duration, bytes, wire_bytes))
+        .collect()
+}
+
+
+/// Pause between failed load test attempts.
+const RETRY_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Returns a future that resolves once the current monitoring run is stopped, i.e. its
+/// `stop_token` is cancelled by a drain. Without one (as in `--run-once`), it never resolves.
+async fn monitoring_stopped(app_state: &Arc<Mutex<AppState>>) -> impl std::future::Future<Output = ()> {
+    app_state.lock().await.stop_token.clone().cancelled_owned()
+}
+
+/// The metrics of one load test attempt, keyed by task name, before they are recorded.
+type AttemptResults = Vec<(String, LoadTestMonitoringData)>;
+
+/// Runs `attempt` until it succeeds, retrying up to `max_attempts` more times with `backoff` in between.
+///
+/// When a `deadline` is given, no retry is started that could not begin before it. When `stopped`
+/// resolves during a backoff, the backoff is cut short and no further attempt is made. Returns the
+/// results to record with the outcome: those of the successful attempt, or once retrying gives
+/// up, the best results gathered so far (see `fewer_failures`). The returned error keeps the kind
+/// of the last attempt's failure.
+async fn run_with_retries<F, Fut, S>(max_attempts: usize, backoff: Duration, deadline: Option<Instant>, stopped: S, mut attempt: F) -> (AttemptResults, Result<(), MonitorError>)
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = (AttemptResults, Result<(), MonitorError>)>,
+    S: std::future::Future<Output = ()>,
+{
+    let mut attempt_number = 0;
+    let mut best = AttemptResults::new();
+    tokio::pin!(stopped);
+
+    while attempt_number <= max_attempts {
+        let (results, outcome) = attempt().await;
+        let e = match outcome {
+            Ok(_) => return (results, Ok(())),
+            Err(e) => e,
         };
+        if best.is_empty() || fewer_failures(&results, &best) {
+            best = results;
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() + backoff >= deadline) {
+            return (best, Err(e.context(format!("Load test failed after {} attempts; overall deadline reached", attempt_number + 1))));
+        }
+        if attempt_number == max_attempts {
+            return (best, Err(e.context(format!("Load test failed after {} attempts", attempt_number + 1))));
+        }
+
+        log::warn!("Load test attempt {} failed: {}. Retrying...", attempt_number + 1, e);
+        attempt_number += 1;
+        // Backoff before retry, unless monitoring is stopped in the meantime
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {},
+            _ = &mut stopped => {
+                return (best, Err(e.context(format!("Load test stopped after {} attempts", attempt_number))));
+            },
+        }
+    }
+
+    (best, Err(MonitorError::Request("Load test failed: Maximum retry attempts reached".to_string())))
+}
+
+/// Judges the results of one load test attempt: it fails when any endpoint had failed requests
+/// or breached its response time threshold, or when not a single request succeeded.
+fn attempt_outcome(results: &AttemptResults) -> Result<(), MonitorError> {
+    if let Some((task_name, data)) = results.iter().find(|(_, data)| data.failure_count > 0) {
+        return Err(MonitorError::Assertion(format!("'{}' had {} failed requests out of {}", task_name, data.failure_count, data.total_requests)));
+    }
+    if let Some((task_name, data)) = results.iter().find(|(_, data)| data.threshold_breached) {
+        return Err(MonitorError::Assertion(format!("'{}' had a p95 of {}ms, over its {}ms threshold",
+            task_name, data.percentile_95th_response_time_ms, data.response_time_threshold_ms.unwrap_or_default())));
+    }
+    if results.iter().all(|(_, data)| data.success_count == 0) {
+        return Err(MonitorError::Request("No load test request succeeded".to_string()));
+    }
+    Ok(())
+}
+
+/// Whether `candidate` is a better set of results than `best`: it has results, and fewer of
+/// their requests failed.
+fn fewer_failures(candidate: &AttemptResults, best: &AttemptResults) -> bool {
+    let failures = |results: &AttemptResults| results.iter().map(|(_, data)| data.failure_count).sum::<usize>();
+    !candidate.is_empty() && failures(candidate) < failures(best)
+}
+
+/// Records the results of a load test attempt in the application state.
+async fn record_attempt_results(app_state: &Arc<Mutex<AppState>>, workflow_name: &str, results: AttemptResults) {
+    for (task_name, load_test_data) in results {
+        update_load_test_app_state(app_state, workflow_name, &task_name, load_test_data).await;
+    }
+}
+
+/// Aggregates over every stored load test, computed by `rollup`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadTestRollup {
+    /// The number of load tests folded into the rollup.
+    pub load_test_count: usize,
+    /// The requests sent by all load tests together.
+    pub total_requests: usize,
+    /// The requests that failed across all load tests.
+    pub failure_count: usize,
+    /// The share of all requests that failed, from 0 to 1; 0 when nothing was sent.
+    pub error_rate: f64,
+    /// The lowest 95th percentile response time among the load tests, in milliseconds.
+    pub min_p95_ms: u128,
+    /// The median of the load tests' 95th percentile response times, in milliseconds.
+    pub median_p95_ms: u128,
+    /// The highest 95th percentile response time among the load tests, in milliseconds.
+    pub max_p95_ms: u128,
+}
 
-        // Update application state with load test data
-        update_load_test_app_state(&self.app_state, &workflow_name, &self.api_config.name, load_test_data).await;
+/// Folds the load test results of every workflow into one set of aggregates.
+pub fn rollup(load_tests: &HashMap<String, HashMap<String, LoadTestMonitoringData>>) -> LoadTestRollup {
+    let all: Vec<&LoadTestMonitoringData> = load_tests.values().flat_map(HashMap::values).collect();
+    let total_requests: usize = all.iter().map(|data| data.total_requests).sum();
+    let failure_count: usize = all.iter().map(|data| data.failure_count).sum();
+    let mut p95s: Vec<u128> = all.iter().map(|data| data.percentile_95th_response_time_ms).collect();
+    p95s.sort_unstable();
 
-        Ok(())
+    LoadTestRollup {
+        load_test_count: all.len(),
+        total_requests,
+        failure_count,
+        error_rate: if total_requests == 0 { 0.0 } else { failure_count as f64 / total_requests as f64 },
+        min_p95_ms: p95s.first().copied().unwrap_or(0),
+        median_p95_ms: median(&p95s),
+        max_p95_ms: p95s.last().copied().unwrap_or(0),
     }
 }
 
+/// Summary statistics computed by `analyze_results`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoadTestStats {
+    /// The total number of successful requests.
+    pub success_count: usize,
+    /// The total number of failed requests.
+    pub failure_count: usize,
+    /// The median response time in milliseconds.
+    pub median_response_time_ms: u128,
+    /// The average response time in milliseconds.
+    pub average_response_time_ms: u128,
+    /// The median response time in microseconds.
+    pub median_response_time_micros: u128,
+    /// The average response time in microseconds.
+    pub average_response_time_micros: u128,
+    /// The minimum response time in milliseconds observed in the test.
+    pub min_response_time_ms: u128,
+    /// The maximum response time in milliseconds observed in the test.
+    pub max_response_time_ms: u128,
+    /// A distribution of HTTP status codes received during the test.
+    pub status_code_distribution: HashMap<u16, usize>,
+    /// The 95th percentile response time in milliseconds.
+    pub percentile_95th_response_time_ms: u128,
+    /// Response times in milliseconds for each requested percentile, keyed by label (e.g. "p99.9").
+    pub percentiles: HashMap<String, u128>,
+    /// The rate of requests per second calculated from the test duration and total requests.
+    pub requests_per_second: f64,
+    /// The average size in bytes of the responses received, after any content decoding.
+    pub average_bytes_per_response: u128,
+    /// The average on-the-wire size in bytes of the responses received.
+    pub average_wire_bytes: u128,
+    /// The number of responses per latency bucket, plus an overflow bucket.
+    pub latency_histogram: Vec<(u128, usize)>,
+    /// Latency statistics for the responses of each status code.
+    pub latency_by_status: HashMap<u16, LatencyStats>,
+}
+
+/// Latency statistics for a subset of load test responses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// The median response time in milliseconds.
+    pub median_ms: u128,
+    /// The average response time in milliseconds.
+    pub average_ms: u128,
+    /// The 95th percentile response time in milliseconds.
+    pub percentile_95th_ms: u128,
+}
+
+impl LatencyStats {
+    /// Computes the statistics from response times sorted in ascending order.
+    fn from_sorted(times_ms: &[u128]) -> Self {
+        LatencyStats {
+            median_ms: median(times_ms),
+            average_ms: if times_ms.is_empty() { 0 } else { times_ms.iter().sum::<u128>() / times_ms.len() as u128 },
+            percentile_95th_ms: percentile_95th(times_ms),
+        }
+    }
+}
+
+/// The median of response times sorted in ascending order, or 0 when there are none.
+fn median(sorted_times_ms: &[u128]) -> u128 {
+    if sorted_times_ms.is_empty() {
+        0
+    } else if sorted_times_ms.len().is_multiple_of(2) {
+        let mid_right = sorted_times_ms.len() / 2;
+        let mid_left = mid_right - 1;
+        (sorted_times_ms[mid_left] + sorted_times_ms[mid_right]) / 2
+    } else {
+        sorted_times_ms[sorted_times_ms.len() / 2]
+    }
+}
+
+/// The 95th percentile (nearest rank) of response times sorted in ascending order, or 0 when there are none.
+pub(crate) fn percentile_95th(sorted_times_ms: &[u128]) -> u128 {
+    percentile(sorted_times_ms, 95.0)
+}
+
+/// The given percentile (nearest rank, clamped to 0–100) of response times sorted in ascending
+/// order, or 0 when there are none.
+fn percentile(sorted_times_ms: &[u128], percentile: f64) -> u128 {
+    let index = if sorted_times_ms.is_empty() {
+        0
+    } else {
+        let fraction = (percentile / 100.0).clamp(0.0, 1.0);
+        // The tolerance keeps float error (0.999 * 1000 = 999.0000000000001) from skipping a rank.
+        ((fraction * (sorted_times_ms.len() as f64) - 1e-9).ceil() as usize).saturating_sub(1)
+    };
+    *sorted_times_ms.get(index).unwrap_or(&0)
+}
+
+/// The label a percentile is reported under, e.g. "p95" or "p99.9".
+fn percentile_label(percentile: f64) -> String {
+    format!("p{}", percentile)
+}
 
 /// Analyzes the results of a load test to calculate various performance metrics.
 ///
@@ -298,36 +1083,30 @@ impl LoadTest {
 /// status code distributions, requests per second, and average bytes per response.
 ///
 /// # Parameters
-/// - `results`: A slice of tuples containing the status code, duration, and size in bytes
-///   of each request made during the load test.
+/// - `results`: A slice of tuples containing the status code, duration, decoded size in bytes
+///   and on-the-wire size in bytes of each request made during the load test.
+/// - `latency_buckets_ms`: The upper bounds, in milliseconds, of the latency histogram buckets.
+/// - `expected_status_codes`: The status codes counted as successes; any 2xx when `None`.
+/// - `report_percentiles`: The response time percentiles to compute, e.g. `[50.0, 99.9]`.
 ///
 /// # Returns
-/// A tuple containing the following aggregated metrics:
-/// - `usize`: The total number of successful requests.
-/// - `usize`: The total number of failed requests.
-/// - `u128`: The median response time in milliseconds.
-/// - `u128`: The average response time in milliseconds.
-/// - `u128`: The minimum response time in milliseconds observed in the test.
-/// - `u128`: The maximum response time in milliseconds observed in the test.
-/// - `HashMap<u16, usize>`: A distribution of HTTP status codes received during the test.
-/// - `u128`: The 95th percentile response time in milliseconds.
-/// - `f64`: The rate of requests per second calculated from the test duration and total requests.
-/// - `u128`: The average size in bytes of the responses received.
-///
-/// The function ensures that all metrics are calculated accurately to provide a comprehensive
-/// overview of the load test's performance.
-fn analyze_results(results: &[(StatusCode, Duration, usize)]) -> (usize, usize, u128, u128, u128, u128, HashMap<u16, usize>, u128, f64, u128) {
+/// A `LoadTestStats` holding the aggregated metrics.
+pub fn analyze_results(results: &[(StatusCode, Duration, usize, usize)], latency_buckets_ms: &[u128], expected_status_codes: Option<&[u16]>, report_percentiles: &[f64]) -> LoadTestStats {
     let mut success_count = 0;
     let mut failure_count = 0;
     let mut total_duration = 0u128;
+    let mut total_duration_micros = 0u128;
     let mut total_bytes = 0u128; // Accumulator for total bytes
+    let mut total_wire_bytes = 0u128; // Accumulator for total on-the-wire bytes
     let mut response_times_ms = Vec::new(); // Collect all response times for percentile calculation
+    let mut response_times_micros = Vec::new();
     let mut min_response_time_ms = u128::MAX;
     let mut max_response_time_ms = u128::MIN;
     let mut status_code_distribution = HashMap::new();
+    let mut times_by_status: HashMap<u16, Vec<u128>> = HashMap::new();
 
-    for (status, duration, bytes) in results {
-        if status.is_success() {
+    for (status, duration, bytes, wire_bytes) in results {
+        if is_expected_status(expected_status_codes, status.as_u16()) {
             success_count += 1;
         } else {
             failure_count += 1;
@@ -336,11 +1115,15 @@ fn analyze_results(results: &[(StatusCode, Duration, usize)]) -> (usize, usize,
         let duration_ms = duration.as_millis();
         response_times_ms.push(duration_ms);
         total_duration += duration_ms;
+        total_duration_micros += duration.as_micros();
+        response_times_micros.push(duration.as_micros());
         total_bytes += *bytes as u128; // Add the response size to the total
+        total_wire_bytes += *wire_bytes as u128;
         min_response_time_ms = min_response_time_ms.min(duration_ms);
         max_response_time_ms = max_response_time_ms.max(duration_ms);
 
         *status_code_distribution.entry(status.as_u16()).or_insert(0) += 1;
+        times_by_status.entry(status.as_u16()).or_default().push(duration_ms);
     }
 
     let average_response_time_ms = if !results.is_empty() {
@@ -348,29 +1131,32 @@ fn analyze_results(results: &[(StatusCode, Duration, usize)]) -> (usize, usize,
     } else {
         0
     };
-
-    // Calculate the 95th percentile
-    response_times_ms.sort_unstable();
-    let percentile_95th_index = if response_times_ms.is_empty() {
-        0
+    let average_response_time_micros = if !results.is_empty() {
+        total_duration_micros / results.len() as u128
     } else {
-        ((0.95 * (response_times_ms.len() as f64)).ceil() as usize).saturating_sub(1)
-    };
-    let percentile_95th_response_time_ms = *response_times_ms.get(percentile_95th_index).unwrap_or(&0);
-
-    // Calculate Median
-    let median_response_time_ms = if response_times_ms.is_empty() {
         0
-    } else if response_times_ms.len() % 2 == 0 {
-        let mid_right = response_times_ms.len() / 2;
-        let mid_left = mid_right - 1;
-        (response_times_ms[mid_left] + response_times_ms[mid_right]) / 2
-    } else {
-        response_times_ms[response_times_ms.len() / 2]
     };
 
-    // Calculate Requests per Second (RPS)
-    let total_test_duration_secs = total_duration as f64 / 1000.0; // Convert milliseconds to seconds
+    // Calculate the 95th percentile and the median
+    response_times_ms.sort_unstable();
+    let percentile_95th_response_time_ms = percentile_95th(&response_times_ms);
+    let median_response_time_ms = median(&response_times_ms);
+    response_times_micros.sort_unstable();
+    let median_response_time_micros = median(&response_times_micros);
+    let percentiles = report_percentiles.iter()
+        .map(|&p| (percentile_label(p), percentile(&response_times_ms, p)))
+        .collect();
+
+    // Break latency down per status code, so fast errors don't mask slow successes.
+    let latency_by_status = times_by_status.into_iter()
+        .map(|(status, mut times)| {
+            times.sort_unstable();
+            (status, LatencyStats::from_sorted(&times))
+        })
+        .collect();
+
+    // Calculate Requests per Second (RPS), from microseconds so sub-millisecond requests still count
+    let total_test_duration_secs = total_duration_micros as f64 / 1_000_000.0;
     let requests_per_second = if total_test_duration_secs > 0.0 {
         results.len() as f64 / total_test_duration_secs
     } else {
@@ -383,22 +1169,64 @@ fn analyze_results(results: &[(StatusCode, Duration, usize)]) -> (usize, usize,
     } else {
         0
     };
+    let average_wire_bytes = if !results.is_empty() {
+        total_wire_bytes / results.len() as u128
+    } else {
+        0
+    };
+
+    let latency_histogram = latency_histogram(&response_times_ms, latency_buckets_ms);
 
-    (
+    LoadTestStats {
         success_count,
         failure_count,
         median_response_time_ms,
         average_response_time_ms,
+        median_response_time_micros,
+        average_response_time_micros,
         min_response_time_ms,
         max_response_time_ms,
         status_code_distribution,
         percentile_95th_response_time_ms,
+        percentiles,
         requests_per_second,
-        average_bytes_per_response
-    )
+        average_bytes_per_response,
+        average_wire_bytes,
+        latency_histogram,
+        latency_by_status,
+    }
+}
+
+/// Counts response times per bucket, with a trailing overflow bucket bounded by `u128::MAX`.
+///
+/// Bounds are inclusive upper edges and may be given in any order.
+fn latency_histogram(response_times_ms: &[u128], bucket_bounds_ms: &[u128]) -> Vec<(u128, usize)> {
+    let mut bounds = bucket_bounds_ms.to_vec();
+    bounds.sort_unstable();
+    bounds.dedup();
+    if bounds.last() != Some(&u128::MAX) {
+        bounds.push(u128::MAX);
+    }
+
+    let mut histogram: Vec<(u128, usize)> = bounds.into_iter().map(|bound| (bound, 0)).collect();
+    for response_time in response_times_ms {
+        // The overflow bucket guarantees a match.
+        let bucket = histogram.partition_point(|(bound, _)| bound < response_time);
+        histogram[bucket].1 += 1;
+    }
+    histogram
 }
 
 
+/// Records a result carrying only a status (e.g. "TIMEOUT") for a load test that produced none,
+/// with the other load test results rather than the task results.
+pub(crate) async fn record_load_test_status(app_state: &Arc<Mutex<AppState>>, workflow_name: &str, api_config: &ApiConfig, status: &str) {
+    let load_test_config = api_config.load_test_config.clone().unwrap_or_default();
+    let mut load_test_data = build_load_test_data(api_config, &load_test_config, Vec::new());
+    load_test_data.status = Some(status.to_string());
+    update_load_test_app_state(app_state, workflow_name, &api_config.name, load_test_data).await;
+}
+
 /// Updates the shared application state with the results of a load test.
 ///
 /// # Parameters
@@ -423,9 +1251,872 @@ async fn update_load_test_app_state(
         .entry(workflow_name.to_string()) // Use workflow_name to access the correct entry
         .or_insert_with(HashMap::new);
 
+    statsd::record_load_test(workflow_name, task_name, &load_test_data);
+
     // Update the monitoring data for the specific API URL within the workflow
     workflow_data.insert(task_name.to_string(), load_test_data);
 
+    // Evict the stalest results once there are more than `max_results_entries`.
+    let evicted = state.load_test_results_order.lock().await.touch(workflow_name, task_name, state.max_results_entries);
+    evict_results(load_test_monitoring_data, &evicted);
+
     // Log the update for debugging or informational purposes
     log::info!("Updated load test data for {} in workflow {}", task_name, workflow_name);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_LATENCY_BUCKETS_MS;
+
+    /// An API named `name` at `url`, with the fields every load test fixture shares.
+    fn api_config(name: &str, url: &str) -> ApiConfig {
+        let yaml = format!(
+            r#"
+name: "{}"
+url: "{}"
+method: GET
+headers: {{}}
+expected_field: "id"
+response_time_threshold: 2000
+load_test: true
+"#,
+            name, url
+        );
+        serde_yaml::from_str(&yaml).expect("Failed to parse YAML")
+    }
+
+    /// A load test of `api_config` with its own application state and default settings.
+    fn load_test_for(api_config: ApiConfig, load_test_config: LoadTestConfig) -> LoadTest {
+        let settings = Arc::new(Settings::default());
+        LoadTest {
+            api_config: Arc::new(api_config),
+            app_state: Arc::new(Mutex::new(AppState::default())),
+            load_test_config,
+            client: load_test_client(&settings).unwrap(),
+            settings,
+            rate_limiter: None,
+        }
+    }
+
+    /// Runs one attempt of `load_test` and records its results, as `execute` does.
+    async fn run_once(load_test: &LoadTest, client: &Client) -> Result<(), MonitorError> {
+        let (results, outcome) = load_test.run_load_test(client, "Sample Workflow").await;
+        record_attempt_results(&load_test.app_state, "Sample Workflow", results).await;
+        outcome
+    }
+
+    #[tokio::test]
+    async fn test_load_test_round_robins_across_urls() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let replica_a = MockServer::start().await;
+        let replica_b = MockServer::start().await;
+        for server in [&replica_a, &replica_b] {
+            Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(server).await;
+        }
+
+        let api_config = ApiConfig {
+            urls: Some(vec![replica_a.uri(), replica_b.uri()]),
+            ..api_config("Replicated Todos", "")
+        };
+        let load_test = load_test_for(api_config, LoadTestConfig { initial_load: Some(0), max_load: Some(6), spawn_rate: Some(6), ..LoadTestConfig::default() });
+        let app_state = load_test.app_state.clone();
+
+        run_once(&load_test, &Client::new()).await.unwrap();
+
+        assert_eq!(replica_a.received_requests().await.unwrap().len(), 3);
+        assert_eq!(replica_b.received_requests().await.unwrap().len(), 3);
+
+        let state = app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Replicated Todos"].clone();
+        assert_eq!(data.total_requests, 6);
+        let breakdown = data.url_breakdown.expect("per-URL breakdown should be recorded");
+        assert_eq!(breakdown[&replica_a.uri()].success_count, 3);
+        assert_eq!(breakdown[&replica_b.uri()].success_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_load_test_cycles_through_body_variants() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let api_config = ApiConfig {
+            method: HttpMethod::POST,
+            body: Some(r#"{"title": "ignored"}"#.to_string()),
+            body_variants: Some(vec![r#"{"title": "a"}"#.to_string(), r#"{"title": "b"}"#.to_string(), r#"{"title": "c"}"#.to_string()]),
+            ..api_config("Create Todo", &server.uri())
+        };
+        let load_test = load_test_for(api_config, LoadTestConfig { initial_load: Some(0), max_load: Some(9), spawn_rate: Some(9), ..LoadTestConfig::default() });
+
+        run_once(&load_test, &Client::new()).await.unwrap();
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for request in server.received_requests().await.unwrap() {
+            *counts.entry(String::from_utf8(request.body).unwrap()).or_default() += 1;
+        }
+        assert_eq!(counts, HashMap::from([
+            (r#"{"title": "a"}"#.to_string(), 3),
+            (r#"{"title": "b"}"#.to_string(), 3),
+            (r#"{"title": "c"}"#.to_string(), 3),
+        ]));
+    }
+
+    #[tokio::test]
+    async fn test_failed_requests_contribute_error_samples() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        // Responses slower than the client timeout fail without a status code.
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+            .mount(&server)
+            .await;
+
+        let load_test = load_test_for(api_config("Hanging Endpoint", &server.uri()), LoadTestConfig { initial_load: Some(0), max_load: Some(3), spawn_rate: Some(3), ..LoadTestConfig::default() });
+        let app_state = load_test.app_state.clone();
+        let client = Client::builder().timeout(Duration::from_millis(100)).build().unwrap();
+
+        let result = run_once(&load_test, &client).await;
+
+        let state = app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Hanging Endpoint"].clone();
+        assert_eq!(data.sample_errors.len(), 1, "identical errors should be sampled once: {:?}", data.sample_errors);
+        assert!(data.sample_errors[0].contains("timed out"), "{}", data.sample_errors[0]);
+        // Without a single response, the attempt fails.
+        assert_eq!(result, Err(MonitorError::Request("No load test request succeeded".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_requests_past_abandon_budget_are_slow_failures() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let load_test = load_test_for(api_config("Slow Endpoint", &server.uri()), LoadTestConfig {
+            initial_load: Some(0),
+            max_load: Some(3),
+            spawn_rate: Some(3),
+            abandon_after_ms: Some(200),
+            ..LoadTestConfig::default()
+        });
+        let app_state = load_test.app_state.clone();
+
+        let started = Instant::now();
+        let result = run_once(&load_test, &Client::new()).await;
+
+        // The requests are dropped at the budget instead of waiting out the 5s delay.
+        assert!(started.elapsed() < Duration::from_secs(3), "took {:?}", started.elapsed());
+        let state = app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Slow Endpoint"].clone();
+        assert_eq!(data.slow_failures, 3);
+        assert_eq!(data.status_code_distribution[&0], 3);
+        assert_eq!(data.sample_errors, vec!["slow: abandoned after 200ms"]);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_requests_in_flight_at_max_duration_are_cut_off() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(10)))
+            .mount(&server)
+            .await;
+
+        let load_test = load_test_for(api_config("Slow Endpoint", &server.uri()), LoadTestConfig {
+            initial_load: Some(0),
+            max_load: Some(3),
+            spawn_rate: Some(3),
+            max_duration_secs: Some(1),
+            ..LoadTestConfig::default()
+        });
+        let app_state = load_test.app_state.clone();
+
+        let started = Instant::now();
+        let result = run_once(&load_test, &Client::new()).await;
+
+        // The test ends at max_duration instead of waiting out the 10s delay.
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(1) && elapsed < Duration::from_millis(1500), "took {:?}", elapsed);
+        let state = app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Slow Endpoint"].clone();
+        assert_eq!(data.cut_off_requests, 3);
+        assert_eq!(data.slow_failures, 0);
+        assert_eq!(data.status_code_distribution[&0], 3);
+        assert_eq!(data.sample_errors, vec!["cut off at the load test deadline"]);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_peak_concurrency_counts_overlapping_requests() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        // Each request outlives one tick but not two.
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(1500)))
+            .mount(&server)
+            .await;
+
+        let load_test = load_test_for(api_config("Slow Todos", &server.uri()), LoadTestConfig { initial_load: Some(0), max_load: Some(6), spawn_rate: Some(2), max_duration_secs: Some(5), ..LoadTestConfig::default() });
+        let app_state = load_test.app_state.clone();
+
+        run_once(&load_test, &Client::new()).await.unwrap();
+
+        // Ticks at 0s, 1s and 2s each start 2 requests, which overlap only with the previous tick's.
+        let state = app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Slow Todos"].clone();
+        assert_eq!(data.total_requests, 6);
+        assert_eq!(data.peak_concurrency, 4);
+    }
+
+    #[tokio::test]
+    async fn test_run_label_is_recorded_on_results() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let load_test = load_test_for(api_config("Fetch Todos", &server.uri()), LoadTestConfig {
+            initial_load: Some(0),
+            max_load: Some(2),
+            spawn_rate: Some(2),
+            run_label: Some("after-index-fix".to_string()),
+            ..LoadTestConfig::default()
+        });
+        let app_state = load_test.app_state.clone();
+
+        run_once(&load_test, &Client::new()).await.unwrap();
+
+        let state = app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Fetch Todos"].clone();
+        assert_eq!(data.run_label.as_deref(), Some("after-index-fix"));
+    }
+
+    #[test]
+    fn test_sample_errors_are_distinct_and_bounded() {
+        let outcomes: Vec<RequestOutcome> = (0..30)
+            .map(|i| Err(RequestFailure::Error(format!("error {}", i % 15))))
+            .chain([Ok((StatusCode::OK, Duration::from_millis(5), 0, 0, false))])
+            .collect();
+
+        let samples = sample_errors(&outcomes);
+
+        assert_eq!(samples, (0..10).map(|i| format!("error {}", i)).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight_holds_back_spawning_against_slow_target() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let config = LoadTestConfig {
+            initial_load: Some(0),
+            spawn_rate: Some(6),
+            max_load: Some(6),
+            max_duration_secs: Some(10),
+            max_in_flight: Some(2),
+            ..LoadTestConfig::default()
+        };
+
+        let results = ramp_up_users(&config, |_| {
+            let (in_flight, max_in_flight) = (in_flight.clone(), max_in_flight.clone());
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                // A slow target: each request outlives most of a tick.
+                tokio::time::sleep(Duration::from_millis(600)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            }
+        }).await;
+
+        assert_eq!(results.len(), 6);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_paced_users_track_target_rps() {
+        let config = LoadTestConfig {
+            max_duration_secs: Some(2),
+            target_rps: Some(25.0),
+            ..LoadTestConfig::default()
+        };
+
+        let (results, achieved_rps) = pace_users(&config, 25.0, |_| async {
+            // Slower than the pacing interval, so requests overlap instead of holding the rate down.
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }).await;
+
+        assert_eq!(results.len(), 50);
+        assert!((achieved_rps - 25.0).abs() <= 2.5, "achieved {} requests per second", achieved_rps);
+    }
+
+    #[tokio::test]
+    async fn test_panicking_users_free_their_max_in_flight_slot() {
+        let config = LoadTestConfig {
+            max_duration_secs: Some(1),
+            max_in_flight: Some(1),
+            ..LoadTestConfig::default()
+        };
+
+        let (results, achieved_rps) = pace_users(&config, 10.0, |_| async {
+            panic!("user failed");
+        }).await;
+
+        // Every user panics, but none stays counted as in flight, so none is skipped.
+        assert!(results.is_empty());
+        assert_eq!(achieved_rps, 10.0);
+    }
+
+    #[test]
+    fn test_scheduled_load_follows_schedule_points() {
+        let schedule = [(0, 10), (30, 50), (60, 100)];
+        let at = |secs: f64| scheduled_load(&schedule, Duration::from_secs_f64(secs));
+
+        assert_eq!(at(0.0), 10);
+        assert_eq!(at(15.0), 30);
+        assert_eq!(at(30.0), 50);
+        assert_eq!(at(45.0), 75);
+        assert_eq!(at(60.0), 100);
+        assert_eq!(at(90.0), 100);
+        // Before a schedule's first point, its load is held.
+        assert_eq!(scheduled_load(&[(5, 20), (10, 40)], Duration::ZERO), 20);
+    }
+
+    #[tokio::test]
+    async fn test_ramp_up_spawns_users_per_schedule() {
+        let spawned_at = std::sync::Mutex::new(Vec::new());
+        let started = Instant::now();
+        let config = LoadTestConfig {
+            load_schedule: Some(vec![(2, 4), (0, 2)]),
+            max_duration_secs: None,
+            ..LoadTestConfig::default()
+        };
+
+        let results = ramp_up_users(&config, |_| {
+            spawned_at.lock().unwrap().push(started.elapsed().as_secs());
+            async {}
+        }).await;
+
+        // Ticks at 0s, 1s and 2s top the running users up to 2, 3 and 4. Every user finishes
+        // at once, so each tick starts its target afresh.
+        assert_eq!(results.len(), 9);
+        assert_eq!(*spawned_at.lock().unwrap(), vec![0, 0, 1, 1, 1, 2, 2, 2, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_ramp_up_follows_a_falling_schedule() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let started = Instant::now();
+        let spawned = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = LoadTestConfig {
+            load_schedule: Some(vec![(0, 4), (1, 4), (2, 1), (4, 1)]),
+            max_duration_secs: None,
+            ..LoadTestConfig::default()
+        };
+
+        let results = ramp_up_users(&config, |_| {
+            let (in_flight, spawned) = (in_flight.clone(), spawned.clone());
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                spawned.lock().unwrap().push((started.elapsed().as_secs(), now));
+                // Outlives a tick, so users of one tick are still running at the next.
+                tokio::time::sleep(Duration::from_millis(1500)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            }
+        }).await;
+
+        // 4 users at 0s are still running at 1s, so none are added. Once they finish, the
+        // schedule has dropped to 1, which one user at 2s and its replacement at 4s keep up.
+        assert_eq!(results.len(), 6);
+        assert_eq!(*spawned.lock().unwrap(), vec![(0, 1), (0, 2), (0, 3), (0, 4), (2, 1), (4, 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_users_from_earlier_ticks_keep_running() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let config = LoadTestConfig {
+            initial_load: Some(0),
+            spawn_rate: Some(2),
+            max_load: Some(4),
+            max_duration_secs: Some(5),
+            ..LoadTestConfig::default()
+        };
+
+        let results = ramp_up_users(&config, |_| {
+            let (in_flight, max_in_flight) = (in_flight.clone(), max_in_flight.clone());
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                // Outlives the tick, so the next tick's users overlap with these.
+                tokio::time::sleep(Duration::from_millis(1500)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            }
+        }).await;
+
+        // The second tick's users start while the first tick's are still running.
+        assert_eq!(results.len(), 4);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_users_never_exceed_max_load() {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        // The schedule rises past its earlier targets while the first users are still running.
+        let config = LoadTestConfig {
+            load_schedule: Some(vec![(0, 2), (1, 3), (2, 3)]),
+            max_duration_secs: Some(3),
+            ..LoadTestConfig::default()
+        };
+
+        let results = ramp_up_users(&config, |_| {
+            let (in_flight, max_in_flight) = (in_flight.clone(), max_in_flight.clone());
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now, Ordering::SeqCst);
+                // Outlives every tick of the test.
+                tokio::time::sleep(Duration::from_millis(2500)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+            }
+        }).await;
+
+        // 2 users at 0s and 1 more at 1s; after that the 3 running users fill the cap.
+        assert_eq!(results.len(), 3);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_weighted_picker_follows_weights() {
+        let picker = WeightedPicker::new(&[1, 3, 6]).unwrap();
+        let samples = 20_000;
+        let mut counts = [0usize; 3];
+        for _ in 0..samples {
+            counts[picker.pick()] += 1;
+        }
+
+        for (count, expected) in counts.iter().zip([0.1, 0.3, 0.6]) {
+            let observed = *count as f64 / samples as f64;
+            assert!((observed - expected).abs() < 0.02, "observed {} expected {}", observed, expected);
+        }
+    }
+
+    #[test]
+    fn test_weighted_picker_rejects_all_zero_weights() {
+        assert!(WeightedPicker::new(&[0, 0]).is_err());
+        assert!(WeightedPicker::new(&[]).is_err());
+    }
+
+    #[test]
+    fn test_latency_histogram_counts_per_bucket_with_overflow() {
+        let results: Vec<_> = [5u64, 10, 11, 50, 99, 100, 250, 1200]
+            .iter()
+            .map(|ms| (StatusCode::OK, Duration::from_millis(*ms), 0, 0))
+            .collect();
+
+        let stats = analyze_results(&results, &[100, 10, 500], None, &[]);
+
+        assert_eq!(stats.latency_histogram, vec![(10, 2), (100, 4), (500, 1), (u128::MAX, 1)]);
+    }
+
+    #[test]
+    fn test_latency_histogram_defaults_when_unconfigured() {
+        let buckets = LoadTestConfig::default().latency_buckets().to_vec();
+        assert_eq!(buckets, DEFAULT_LATENCY_BUCKETS_MS.to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_overall_deadline() {
+        let attempts = AtomicUsize::new(0);
+        let deadline = Instant::now() + Duration::from_millis(250);
+
+        let (_, result) = run_with_retries(100, Duration::from_millis(100), Some(deadline), std::future::pending(), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            (Vec::new(), Err(MonitorError::Request("boom".to_string())))
+        }).await;
+
+        let error = result.unwrap_err();
+        assert!(error.message().contains("overall deadline reached"), "{}", error);
+        // Attempts at ~0ms, ~100ms and ~200ms; another backoff would end past the 250ms deadline.
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert!(Instant::now() < deadline + Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_stopping_monitoring_during_backoff_ends_retries() {
+        let app_state = Arc::new(Mutex::new(AppState { monitoring_started: true, ..AppState::default() }));
+        let stop_token = app_state.lock().await.stop_token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            stop_token.cancel();
+        });
+        let attempts = AtomicUsize::new(0);
+        let started = Instant::now();
+
+        let stopped = monitoring_stopped(&app_state).await;
+        let (_, result) = run_with_retries(3, Duration::from_secs(5), None, stopped, || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            (Vec::new(), Err(MonitorError::Request("boom".to_string())))
+        }).await;
+
+        assert_eq!(result.unwrap_err(), MonitorError::Request("Load test stopped after 1 attempts: boom".to_string()));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_retries_exhaust_attempts_without_deadline() {
+        let attempts = AtomicUsize::new(0);
+
+        let (_, result) = run_with_retries(2, Duration::from_millis(1), None, std::future::pending(), || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            (Vec::new(), Err(MonitorError::Timeout("boom".to_string())))
+        }).await;
+
+        assert_eq!(result.unwrap_err(), MonitorError::Timeout("Load test failed after 3 attempts: boom".to_string()));
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_retries_return_the_best_attempt() {
+        let api_config = api_config("Flaky Todos", "http://localhost/todos");
+        let mut failures = [3, 1, 2].into_iter();
+
+        let (results, result) = run_with_retries(2, Duration::from_millis(1), None, std::future::pending(), || {
+            let mut load_test_data = build_load_test_data(&api_config, &LoadTestConfig::default(), Vec::new());
+            load_test_data.failure_count = failures.next().unwrap();
+            async move { (vec![("Flaky Todos".to_string(), load_test_data)], Err(MonitorError::Assertion("boom".to_string()))) }
+        }).await;
+
+        // The second of three failed attempts had the fewest failed requests.
+        assert!(result.is_err());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.failure_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_load_test_is_retried_until_an_attempt_passes() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        // The first attempt's two requests fail; everything after them succeeds.
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(500)).up_to_n_times(2).with_priority(1).mount(&server).await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        let load_test = load_test_for(api_config("Flaky Todos", &server.uri()), LoadTestConfig {
+            initial_load: Some(0),
+            max_load: Some(2),
+            spawn_rate: Some(2),
+            retry_count: Some(2),
+            ..LoadTestConfig::default()
+        });
+
+        load_test.execute(&Client::new(), "Sample Workflow").await.unwrap();
+
+        // The second attempt passed, so there was no third.
+        assert_eq!(server.received_requests().await.unwrap().len(), 4);
+        let state = load_test.app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Flaky Todos"].clone();
+        assert_eq!((data.success_count, data.failure_count), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_load_test_with_failed_requests_fails_without_retries() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(500)).up_to_n_times(1).with_priority(1).mount(&server).await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        let load_test = load_test_for(api_config("Flaky Todos", &server.uri()), LoadTestConfig {
+            initial_load: Some(0),
+            max_load: Some(2),
+            spawn_rate: Some(2),
+            ..LoadTestConfig::default()
+        });
+
+        let error = load_test.execute(&Client::new(), "Sample Workflow").await.unwrap_err();
+
+        assert_eq!(error, MonitorError::Assertion("Load test failed after 1 attempts: 'Flaky Todos' had 1 failed requests out of 2".to_string()));
+        let state = load_test.app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Flaky Todos"].clone();
+        assert_eq!((data.success_count, data.failure_count), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_load_test_requests_wait_for_the_workflow_rate_limiter() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        let load_test = LoadTest {
+            rate_limiter: crate::factory::rate_limiter(10.0).map(Arc::new),
+            ..load_test_for(api_config("Throttled Todos", &server.uri()), LoadTestConfig {
+                initial_load: Some(0),
+                max_load: Some(10),
+                spawn_rate: Some(10),
+                max_duration_secs: Some(5),
+                ..LoadTestConfig::default()
+            })
+        };
+
+        let started = Instant::now();
+        let running = async {
+            load_test.execute(&Client::new(), "Sample Workflow").await.unwrap();
+            started.elapsed()
+        };
+        let midway = async {
+            tokio::time::sleep(Duration::from_millis(450)).await;
+            server.received_requests().await.unwrap().len()
+        };
+        let (elapsed, sent_midway) = tokio::join!(running, midway);
+
+        // Ten users start at once, but their requests go out 100ms apart.
+        assert!((4..=6).contains(&sent_midway), "{} requests after 450ms", sent_midway);
+        assert_eq!(server.received_requests().await.unwrap().len(), 10);
+        assert!(elapsed >= Duration::from_millis(880), "took {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_draining_during_a_retry_backoff_stops_the_load_test() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(500)).mount(&server).await;
+        let load_test = load_test_for(api_config("Broken Todos", &server.uri()), LoadTestConfig {
+            initial_load: Some(0),
+            max_load: Some(2),
+            spawn_rate: Some(2),
+            retry_count: Some(3),
+            ..LoadTestConfig::default()
+        });
+        // Drain as `/api/v1/workflow/drain` does, while the first attempt's backoff is running.
+        let stop_token = load_test.app_state.lock().await.stop_token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            stop_token.cancel();
+        });
+        let started = Instant::now();
+
+        let error = load_test.execute(&Client::new(), "Sample Workflow").await.unwrap_err();
+
+        assert_eq!(error, MonitorError::Assertion("Load test stopped after 1 attempts: 'Broken Todos' had 2 failed requests out of 2".to_string()));
+        assert!(started.elapsed() < RETRY_BACKOFF, "took {:?}", started.elapsed());
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+        let state = load_test.app_state.lock().await;
+        assert_eq!(state.load_test_monitoring_data.lock().await["Sample Workflow"]["Broken Todos"].failure_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_breached_response_time_threshold_fails_the_load_test() {
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(300)))
+            .mount(&server)
+            .await;
+        let load_test = load_test_for(api_config("Slow Todos", &server.uri()), LoadTestConfig {
+            initial_load: Some(0),
+            max_load: Some(2),
+            spawn_rate: Some(2),
+            response_time_threshold_ms: Some(100),
+            ..LoadTestConfig::default()
+        });
+
+        let error = load_test.execute(&Client::new(), "Sample Workflow").await.unwrap_err();
+
+        assert!(matches!(error, MonitorError::Assertion(_)), "{:?}", error);
+        assert!(error.message().contains("over its 100ms threshold"), "{}", error);
+        let state = load_test.app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Slow Todos"].clone();
+        assert!(data.threshold_breached);
+        assert_eq!((data.success_count, data.failure_count), (2, 0));
+    }
+
+    #[tokio::test]
+    async fn test_load_test_reports_wire_and_decoded_sizes() {
+        use std::io::Write;
+        use wiremock::{matchers::{headers, method}, Mock, MockServer, ResponseTemplate};
+
+        let decoded = "a".repeat(4000);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(decoded.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(headers("accept-encoding", vec!["gzip", "br", "deflate"]))
+            .respond_with(ResponseTemplate::new(200).insert_header("content-encoding", "gzip").set_body_bytes(gzipped.clone()))
+            .mount(&server)
+            .await;
+
+        let load_test = load_test_for(api_config("Compressed", &server.uri()), LoadTestConfig { initial_load: Some(0), max_load: Some(2), spawn_rate: Some(2), ..LoadTestConfig::default() });
+        let app_state = load_test.app_state.clone();
+
+        load_test.execute(&Client::new(), "Sample Workflow").await.unwrap();
+
+        let state = app_state.lock().await;
+        let data = state.load_test_monitoring_data.lock().await["Sample Workflow"]["Compressed"].clone();
+        assert_eq!(data.success_count, 2);
+        assert_eq!(data.average_wire_bytes, gzipped.len() as u128);
+        assert_eq!(data.average_bytes_per_response, decoded.len() as u128);
+    }
+
+    #[test]
+    fn test_analyze_results_reports_custom_percentiles() {
+        let results: Vec<_> = (1..=1000)
+            .map(|ms| (StatusCode::OK, Duration::from_millis(ms), 0, 0))
+            .collect();
+
+        let stats = analyze_results(&results, &[], None, &[50.0, 90.0, 99.0, 99.9]);
+
+        assert_eq!(stats.percentiles, HashMap::from([
+            ("p50".to_string(), 500),
+            ("p90".to_string(), 900),
+            ("p99".to_string(), 990),
+            ("p99.9".to_string(), 999),
+        ]));
+        // The dedicated p95 field is still filled in, whether or not 95 is requested.
+        assert_eq!(stats.percentile_95th_response_time_ms, 950);
+    }
+
+    #[test]
+    fn test_analyze_results_computes_named_stats() {
+        let results = vec![
+            (StatusCode::OK, Duration::from_millis(10), 0, 0),
+            (StatusCode::OK, Duration::from_millis(20), 0, 0),
+            (StatusCode::INTERNAL_SERVER_ERROR, Duration::from_millis(30), 0, 0),
+            (StatusCode::OK, Duration::from_millis(40), 0, 0),
+        ];
+
+        let stats = analyze_results(&results, &[], None, &[]);
+
+        assert_eq!(stats.success_count, 3);
+        assert_eq!(stats.failure_count, 1);
+        assert_eq!(stats.median_response_time_ms, 25);
+        assert_eq!(stats.average_response_time_ms, 25);
+        assert_eq!(stats.min_response_time_ms, 10);
+        assert_eq!(stats.max_response_time_ms, 40);
+        assert_eq!(stats.percentile_95th_response_time_ms, 40);
+        assert_eq!(stats.status_code_distribution, HashMap::from([(200, 3), (500, 1)]));
+        // Four requests over 100ms of summed response time.
+        assert_eq!(stats.requests_per_second, 40.0);
+    }
+
+    #[test]
+    fn test_analyze_results_keeps_sub_millisecond_latency() {
+        let results = vec![
+            (StatusCode::OK, Duration::from_micros(200), 0, 0),
+            (StatusCode::OK, Duration::from_micros(300), 0, 0),
+            (StatusCode::OK, Duration::from_micros(700), 0, 0),
+        ];
+
+        let stats = analyze_results(&results, &[], None, &[]);
+
+        assert_eq!((stats.median_response_time_ms, stats.average_response_time_ms), (0, 0));
+        assert_eq!(stats.median_response_time_micros, 300);
+        assert_eq!(stats.average_response_time_micros, 400);
+        // Three requests over 1.2ms of summed response time.
+        assert!((stats.requests_per_second - 2500.0).abs() < 1e-6, "{}", stats.requests_per_second);
+    }
+
+    #[test]
+    fn test_analyze_results_honors_expected_status_codes() {
+        let results = vec![
+            (StatusCode::CREATED, Duration::from_millis(10), 0, 0),
+            (StatusCode::CREATED, Duration::from_millis(10), 0, 0),
+            (StatusCode::OK, Duration::from_millis(10), 0, 0),
+        ];
+
+        let stats = analyze_results(&results, &[], Some(&[201]), &[]);
+
+        assert_eq!(stats.success_count, 2);
+        assert_eq!(stats.failure_count, 1);
+    }
+
+    #[test]
+    fn test_latency_by_status_separates_fast_errors_from_slow_successes() {
+        let results: Vec<_> = [(200u16, 400u64), (200, 500), (200, 600), (500, 5), (500, 15)]
+            .iter()
+            .map(|(status, ms)| (StatusCode::from_u16(*status).unwrap(), Duration::from_millis(*ms), 0, 0))
+            .collect();
+
+        let stats = analyze_results(&results, &[], None, &[]);
+
+        assert_eq!(stats.latency_by_status[&200], LatencyStats { median_ms: 500, average_ms: 500, percentile_95th_ms: 600 });
+        assert_eq!(stats.latency_by_status[&500], LatencyStats { median_ms: 10, average_ms: 10, percentile_95th_ms: 15 });
+        assert_eq!(stats.latency_by_status.len(), 2);
+    }
+
+    #[test]
+    fn test_high_p95_breaches_response_time_threshold() {
+        let api_config = api_config("Slow Endpoint", "http://localhost/slow");
+        let outcomes = |slowest_ms: u64| -> Vec<RequestOutcome> {
+            (0..19).map(|_| 50).chain([slowest_ms])
+                .map(|ms| Ok((StatusCode::OK, Duration::from_millis(ms), 0, 0, false)))
+                .collect()
+        };
+        let config = LoadTestConfig { response_time_threshold_ms: Some(200), ..LoadTestConfig::default() };
+
+        // 20 samples: the 19th is the p95, so a single slow outlier doesn't count.
+        assert!(!build_load_test_data(&api_config, &config, outcomes(900)).threshold_breached);
+
+        let mut slow = outcomes(900);
+        slow[18] = Ok((StatusCode::OK, Duration::from_millis(800), 0, 0, false));
+        assert!(build_load_test_data(&api_config, &config, slow).threshold_breached);
+
+        let unset = LoadTestConfig::default();
+        assert!(!build_load_test_data(&api_config, &unset, outcomes(900)).threshold_breached);
+    }
+
+    #[test]
+    fn test_status_code_distribution_serializes_sorted_string_keys() {
+        let api_config = api_config("Mixed Endpoint", "http://localhost/mixed");
+        let response = |status: u16| Ok((StatusCode::from_u16(status).unwrap(), Duration::from_millis(10), 0, 0, false));
+        let config = LoadTestConfig::default();
+
+        let with_errors = build_load_test_data(&api_config, &config, vec![response(503), response(200), Err(RequestFailure::Error("connection refused".to_string())), response(200)]);
+        let json = serde_json::to_string(&with_errors).unwrap();
+        assert!(json.contains(r#""status_code_distribution":{"0":1,"200":2,"503":1}"#), "{}", json);
+
+        let without_errors = build_load_test_data(&api_config, &config, vec![response(404), response(200)]);
+        let json = serde_json::to_string(&without_errors).unwrap();
+        assert!(json.contains(r#""status_code_distribution":{"0":0,"200":1,"404":1}"#), "{}", json);
+
+        let round_trip: LoadTestMonitoringData = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_trip.status_code_distribution, HashMap::from([(0, 0), (200, 1), (404, 1)]));
+    }
+
+    #[test]
+    fn test_percentiles_are_unreliable_below_min_samples() {
+        let api_config = api_config("Sparse Endpoint", "http://localhost/sparse");
+        let outcomes = |count: usize| -> Vec<RequestOutcome> {
+            (0..count).map(|_| Ok((StatusCode::OK, Duration::from_millis(10), 0, 0, false))).collect()
+        };
+        let default_config = LoadTestConfig::default();
+
+        assert!(!build_load_test_data(&api_config, &default_config, outcomes(DEFAULT_MIN_SAMPLES - 1)).reliable);
+        assert!(build_load_test_data(&api_config, &default_config, outcomes(DEFAULT_MIN_SAMPLES)).reliable);
+
+        let strict = LoadTestConfig { min_samples: Some(100), ..LoadTestConfig::default() };
+        assert!(!build_load_test_data(&api_config, &strict, outcomes(99)).reliable);
+        assert!(build_load_test_data(&api_config, &strict, outcomes(100)).reliable);
+    }
+
+    #[test]
+    fn test_analyze_results_of_no_requests() {
+        let stats = analyze_results(&[], &[], None, &[]);
+
+        assert_eq!((stats.success_count, stats.failure_count), (0, 0));
+        assert_eq!(stats.average_response_time_ms, 0);
+        assert_eq!(stats.requests_per_second, 0.0);
+        assert_eq!(stats.latency_histogram, vec![(u128::MAX, 0)]);
+    }
+}
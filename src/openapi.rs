@@ -0,0 +1,369 @@
+use serde_json::{json, Value};
+
+/// Builds the OpenAPI 3 document describing the server's own HTTP API.
+///
+/// Written out by hand, so it has to be updated alongside `configure_routes` in `main.rs`.
+pub fn openapi_document() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "thunderhawk",
+            "description": "Triggers monitoring workflows and reports their latest results.",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": {
+            "/api/v1/workflow/trigger_all": {
+                "post": {
+                    "summary": "Start every loaded workflow",
+                    "security": bearer_security(),
+                    "responses": {
+                        "200": json_response("The workflows that were started", json!({
+                            "type": "object",
+                            "properties": {
+                                "message": { "type": "string" },
+                                "workflows": { "type": "array", "items": { "type": "string" } },
+                                "scheduled_start": { "type": "string", "nullable": true },
+                            },
+                        })),
+                        "401": unauthorized_response(),
+                        "409": json_response("Monitoring is already running", error_schema()),
+                    },
+                },
+            },
+            "/api/v1/workflow/trigger_selected": {
+                "post": {
+                    "summary": "Start the named workflows, optionally overriding load test settings for this run",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": webhook_payload_schema() } },
+                    },
+                    "security": bearer_security(),
+                    "responses": {
+                        "200": text_response("Monitoring was triggered"),
+                        "400": text_response("No matching workflows, or a malformed payload"),
+                        "401": unauthorized_response(),
+                        "409": json_response("Monitoring is already running", error_schema()),
+                    },
+                },
+            },
+            "/api/v1/workflow/trigger": {
+                "get": {
+                    "summary": "Start every loaded workflow, or those named in `workflows`",
+                    "deprecated": true,
+                    "parameters": [{
+                        "name": "workflows",
+                        "in": "query",
+                        "required": false,
+                        "description": "Comma-separated names of the workflows to start",
+                        "schema": { "type": "string" },
+                    }],
+                    "security": bearer_security(),
+                    "responses": {
+                        "200": json_response("The workflows that were started", json!({
+                            "type": "object",
+                            "properties": {
+                                "message": { "type": "string" },
+                                "workflows": { "type": "array", "items": { "type": "string" } },
+                                "scheduled_start": { "type": "string", "nullable": true },
+                            },
+                        })),
+                        "400": json_response("None of the named workflows exist", error_schema()),
+                        "401": unauthorized_response(),
+                        "409": json_response("Monitoring is already running", error_schema()),
+                    },
+                },
+                "post": {
+                    "summary": "Start the named workflows",
+                    "deprecated": true,
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": webhook_payload_schema() } },
+                    },
+                    "security": bearer_security(),
+                    "responses": {
+                        "200": text_response("Monitoring was triggered"),
+                        "400": text_response("No matching workflows, or a malformed payload"),
+                        "401": unauthorized_response(),
+                        "409": json_response("Monitoring is already running", error_schema()),
+                    },
+                },
+            },
+            "/api/v1/workflow/drain": {
+                "post": {
+                    "summary": "Let the running cycle finish its in-flight workflows, start no more, then stop monitoring",
+                    "security": bearer_security(),
+                    "responses": {
+                        "200": json_response("Draining, or stopped at once when no cycle was in flight", json!({
+                            "type": "object",
+                            "properties": { "message": { "type": "string" } },
+                        })),
+                        "401": unauthorized_response(),
+                        "409": json_response("Monitoring is not running", error_schema()),
+                    },
+                },
+            },
+            "/api/v1/workflow/run_task": {
+                "post": {
+                    "summary": "Run one HTTP task immediately, outside its workflow, and return its result",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": {
+                            "type": "object",
+                            "required": ["workflow", "task"],
+                            "properties": {
+                                "workflow": { "type": "string" },
+                                "task": { "type": "string" },
+                            },
+                        } } },
+                    },
+                    "security": bearer_security(),
+                    "responses": {
+                        "200": json_response("The task's monitoring data", json!({ "type": "object" })),
+                        "400": json_response("A malformed payload or a non-HTTP task", error_schema()),
+                        "401": unauthorized_response(),
+                        "404": json_response("No such workflow or task", error_schema()),
+                    },
+                },
+            },
+            "/api/v1/result/task": {
+                "get": {
+                    "summary": "Latest task results, by workflow and task name",
+                    "responses": { "200": json_response("Task results", results_schema()) },
+                },
+            },
+            "/api/v1/result/task.ndjson": {
+                "get": {
+                    "summary": "Latest task results as newline-delimited JSON, one task per line",
+                    "responses": {
+                        "200": {
+                            "description": "One JSON object per line, with `workflow` and `task` alongside the task's result fields",
+                            "content": { "application/x-ndjson": { "schema": { "type": "string" } } },
+                        },
+                    },
+                },
+            },
+            "/api/v1/result/load-test": {
+                "get": {
+                    "summary": "Latest load test results, by workflow and task name",
+                    "responses": {
+                        "200": {
+                            "description": "Load test results, as CSV when `Accept: text/csv` is sent",
+                            "content": {
+                                "application/json": { "schema": results_schema() },
+                                "text/csv": { "schema": { "type": "string" } },
+                            },
+                        },
+                    },
+                },
+            },
+            "/api/v1/result/ping": {
+                "get": {
+                    "summary": "Latest ping check latencies, by workflow and task name",
+                    "responses": { "200": json_response("Ping results", results_schema()) },
+                },
+            },
+            "/api/v1/result/stats": {
+                "get": {
+                    "summary": "Aggregates across every load test: request and error totals and the spread of p95 latencies",
+                    "responses": {
+                        "200": json_response("Load test rollup", json!({
+                            "type": "object",
+                            "properties": {
+                                "load_test_count": { "type": "integer" },
+                                "total_requests": { "type": "integer" },
+                                "failure_count": { "type": "integer" },
+                                "error_rate": { "type": "number" },
+                                "min_p95_ms": { "type": "integer" },
+                                "median_p95_ms": { "type": "integer" },
+                                "max_p95_ms": { "type": "integer" },
+                            },
+                        })),
+                    },
+                },
+            },
+            "/api/v1/result/summary": {
+                "get": {
+                    "summary": "A 0-100 health score per workflow, from its success rate and latencies against their thresholds",
+                    "responses": {
+                        "200": json_response("Health scores by workflow name", json!({
+                            "type": "object",
+                            "additionalProperties": {
+                                "type": "object",
+                                "properties": {
+                                    "health_score": { "type": "number" },
+                                    "success_rate": { "type": "number" },
+                                    "latency_score": { "type": "number" },
+                                    "task_count": { "type": "integer" },
+                                    "load_test_count": { "type": "integer" },
+                                },
+                            },
+                        })),
+                    },
+                },
+            },
+            "/api/v1/result/workflow": {
+                "get": {
+                    "summary": "PASSED, FAILED or SKIPPED for each workflow's latest run",
+                    "responses": {
+                        "200": json_response("Workflow verdicts", json!({
+                            "type": "object",
+                            "additionalProperties": { "type": "string", "enum": ["PASSED", "FAILED", "SKIPPED"] },
+                        })),
+                    },
+                },
+            },
+            "/api/v1/workflows": {
+                "get": {
+                    "summary": "The loaded workflows and their tasks, with secrets in task URLs redacted",
+                    "responses": {
+                        "200": json_response("Loaded workflows", json!({
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "name": { "type": "string" },
+                                    "tasks": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "name": { "type": "string" },
+                                                "method": { "type": "string" },
+                                                "urls": { "type": "array", "items": { "type": "string" } },
+                                                "load_test": { "type": "boolean" },
+                                            },
+                                        },
+                                    },
+                                },
+                            },
+                        })),
+                    },
+                },
+            },
+            "/api/v1/version": {
+                "get": {
+                    "summary": "The running version, git commit and build time",
+                    "responses": {
+                        "200": json_response("Build information", json!({
+                            "type": "object",
+                            "properties": {
+                                "version": { "type": "string" },
+                                "git_sha": { "type": "string" },
+                                "build_timestamp": { "type": "string" },
+                            },
+                        })),
+                    },
+                },
+            },
+            "/debug/state": {
+                "get": {
+                    "summary": "Internal flags and entry counts, for troubleshooting",
+                    "security": bearer_security(),
+                    "responses": {
+                        "200": json_response("The server's internal state", json!({
+                            "type": "object",
+                            "properties": {
+                                "monitoring_started": { "type": "boolean" },
+                                "draining": { "type": "boolean" },
+                                "cycles_completed": { "type": "integer" },
+                                "workflow_count": { "type": "integer" },
+                                "task_entries": { "type": "integer" },
+                                "load_test_entries": { "type": "integer" },
+                                "uptime_secs": { "type": "integer" },
+                            },
+                        })),
+                        "401": unauthorized_response(),
+                    },
+                },
+            },
+            "/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "responses": { "200": json_response("The OpenAPI document", json!({ "type": "object" })) },
+                },
+            },
+        },
+        "components": {
+            "securitySchemes": {
+                "bearerAuth": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "Required on the workflow routes when the server runs with `--api-token`",
+                },
+            },
+        },
+    })
+}
+
+/// The workflow routes take a bearer token, though only when `--api-token` is set.
+fn bearer_security() -> Value {
+    json!([{ "bearerAuth": [] }, {}])
+}
+
+fn unauthorized_response() -> Value {
+    json_response("Missing or invalid bearer token, when the server runs with `--api-token`", error_schema())
+}
+
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } },
+    })
+}
+
+fn text_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "text/plain": { "schema": { "type": "string" } } },
+    })
+}
+
+fn error_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": { "error": { "type": "string" } },
+    })
+}
+
+/// Results are nested as workflow name -> task name -> result object.
+fn results_schema() -> Value {
+    json!({
+        "type": "object",
+        "additionalProperties": {
+            "type": "object",
+            "additionalProperties": { "type": "object" },
+        },
+    })
+}
+
+fn webhook_payload_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["workflow_names"],
+        "properties": {
+            "workflow_names": { "type": "array", "items": { "type": "string" } },
+            "run_label": {
+                "type": "string",
+                "description": "Label stored on this run's load test results, to compare runs",
+            },
+            "max_concurrency": {
+                "type": "integer",
+                "description": "How many of the selected workflows may run at once, for this run only",
+            },
+            "overrides": {
+                "type": "object",
+                "description": "Load test settings for this run only, keyed by task name",
+                "additionalProperties": {
+                    "type": "object",
+                    "properties": {
+                        "initial_load": { "type": "integer" },
+                        "max_load": { "type": "integer" },
+                        "spawn_rate": { "type": "integer" },
+                        "retry_count": { "type": "integer" },
+                        "max_duration_secs": { "type": "integer" },
+                    },
+                },
+            },
+        },
+    })
+}
@@ -0,0 +1,196 @@
+use std::{collections::HashMap, sync::Arc, time::{Duration, Instant}};
+use log::{error, info};
+use reqwest::Client;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use crate::{appstate::AppState, config::{ApiConfig, Settings}, factory::{ApiMonitor, MonitorError}, telemetry::MonitorSpan};
+use crate::tcp_monitor::{connect, tcp_address};
+
+/// Number of connects made per run when `ping_count` is not configured.
+const DEFAULT_PING_COUNT: usize = 4;
+
+/// Aggregate connect latency from a ping check.
+#[derive(Debug, Clone, Serialize)]
+pub struct PingMonitoringData {
+    pub api_url: String,
+    /// "OK" when every connect succeeded, "ERROR" otherwise.
+    pub status: String,
+    /// The number of connects attempted.
+    pub sample_count: usize,
+    /// The number of connects that succeeded.
+    pub success_count: usize,
+    /// The number of connects that failed or timed out.
+    pub failure_count: usize,
+    /// The fastest successful connect in milliseconds.
+    pub min_connect_time_ms: u128,
+    /// The average successful connect time in milliseconds.
+    pub average_connect_time_ms: u128,
+    /// The slowest successful connect in milliseconds.
+    pub max_connect_time_ms: u128,
+}
+
+/// Measures reachability and latency by repeatedly opening and closing a TCP connection,
+/// a ping substitute that needs no raw sockets or extra privileges.
+pub struct PingMonitor {
+    /// Configuration whose `url` names the `host:port` to connect to (optionally prefixed with `tcp://`).
+    pub api_config: Arc<ApiConfig>,
+    /// A reference to the shared application state for recording monitoring data.
+    pub app_state: Arc<Mutex<AppState>>,
+    /// Global settings; the HTTP timeout doubles as the per-connect timeout.
+    pub settings: Arc<Settings>,
+}
+
+#[async_trait::async_trait]
+impl ApiMonitor for PingMonitor {
+    async fn execute(&self, _client: &Client, workflow_name: &str) -> Result<(), MonitorError> {
+        let mut span = MonitorSpan::start("ping", workflow_name, &self.api_config.name, &self.api_config.url, &self.api_config.method);
+        let address = tcp_address(&self.api_config.url);
+        let timeout = Duration::from_secs(self.settings.http_timeout_seconds);
+        let count = self.api_config.ping_count.unwrap_or(DEFAULT_PING_COUNT).max(1);
+
+        let mut samples = Vec::with_capacity(count);
+        for _ in 0..count {
+            let start = Instant::now();
+            samples.push(connect(address, timeout).await.map(|_| start.elapsed()).map_err(|e| e.to_string()));
+        }
+
+        let ping_data = analyze_samples(&self.api_config.url, &samples);
+        let status = ping_data.status.clone();
+        let failure_count = ping_data.failure_count;
+        update_ping_app_state(&self.app_state, workflow_name, &self.api_config.name, ping_data).await;
+        span.record_status(&status, None);
+
+        if failure_count == 0 {
+            info!("'{}' answered {} of {} pings", self.api_config.name, count, count);
+            Ok(())
+        } else {
+            let error_message = format!("'{}' lost {} of {} pings to {}", self.api_config.name, failure_count, count, address);
+            error!("{}", error_message);
+            Err(MonitorError::Request(error_message))
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("Ping check for {}", self.api_config.name)
+    }
+
+    fn response_time_threshold(&self) -> Option<u64> {
+        None
+    }
+
+    fn get_task_order(&self) -> usize {
+        self.api_config.task_order.unwrap_or(usize::MAX)
+    }
+
+    fn api_configs(&self) -> Vec<Arc<ApiConfig>> {
+        vec![self.api_config.clone()]
+    }
+}
+
+/// Summarizes connect samples into min/avg/max latency over the successful connects.
+fn analyze_samples(api_url: &str, samples: &[Result<Duration, String>]) -> PingMonitoringData {
+    let times: Vec<u128> = samples.iter().filter_map(|sample| sample.as_ref().ok()).map(|d| d.as_millis()).collect();
+    let failure_count = samples.len() - times.len();
+
+    PingMonitoringData {
+        api_url: api_url.to_string(),
+        status: if failure_count == 0 { "OK" } else { "ERROR" }.to_string(),
+        sample_count: samples.len(),
+        success_count: times.len(),
+        failure_count,
+        min_connect_time_ms: times.iter().copied().min().unwrap_or(0),
+        average_connect_time_ms: if times.is_empty() { 0 } else { times.iter().sum::<u128>() / times.len() as u128 },
+        max_connect_time_ms: times.iter().copied().max().unwrap_or(0),
+    }
+}
+
+async fn update_ping_app_state(app_state: &Arc<Mutex<AppState>>, workflow_name: &str, task_name: &str, ping_data: PingMonitoringData) {
+    let state = app_state.lock().await;
+    let mut ping_monitoring_data = state.ping_monitoring_data.lock().await;
+    ping_monitoring_data
+        .entry(workflow_name.to_string())
+        .or_insert_with(HashMap::new)
+        .insert(task_name.to_string(), ping_data);
+    log::info!("Updated ping data for {} in workflow {}", task_name, workflow_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn ping_config(url: &str, ping_count: usize) -> ApiConfig {
+        let yaml = format!(
+            r#"
+name: "Ping Gateway"
+url: "{}"
+method: GET
+headers: {{}}
+expected_field: ""
+response_time_threshold: 2000
+check_type: ping
+ping_count: {}
+"#,
+            url, ping_count
+        );
+        serde_yaml::from_str(&yaml).expect("Failed to parse YAML")
+    }
+
+    async fn run_check(url: &str, ping_count: usize) -> (Result<(), MonitorError>, PingMonitoringData) {
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let monitor = PingMonitor {
+            api_config: Arc::new(ping_config(url, ping_count)),
+            app_state: app_state.clone(),
+            settings: Arc::new(Settings { http_timeout_seconds: 2, ..Settings::default() }),
+        };
+        let result = monitor.execute(&Client::new(), "Sample Workflow").await;
+
+        let state = app_state.lock().await;
+        let ping_data = state.ping_monitoring_data.lock().await;
+        (result, ping_data["Sample Workflow"]["Ping Gateway"].clone())
+    }
+
+    #[tokio::test]
+    async fn test_ping_measures_every_sample_against_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let (result, data) = run_check(&address, 5).await;
+
+        assert!(result.is_ok());
+        assert_eq!(data.status, "OK");
+        assert_eq!(data.sample_count, 5);
+        assert_eq!(data.success_count, 5);
+        assert_eq!(data.failure_count, 0);
+        assert!(data.min_connect_time_ms <= data.average_connect_time_ms);
+        assert!(data.average_connect_time_ms <= data.max_connect_time_ms);
+    }
+
+    #[tokio::test]
+    async fn test_ping_to_closed_port_records_losses() {
+        let address = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().to_string();
+
+        let (result, data) = run_check(&address, 3).await;
+
+        assert!(result.is_err());
+        assert_eq!(data.status, "ERROR");
+        assert_eq!(data.failure_count, 3);
+        assert_eq!(data.success_count, 0);
+    }
+
+    #[test]
+    fn test_analyze_samples_ignores_failures_in_latency() {
+        let samples = vec![
+            Ok(Duration::from_millis(10)),
+            Err("connection refused".to_string()),
+            Ok(Duration::from_millis(30)),
+        ];
+
+        let data = analyze_samples("tcp://gateway:443", &samples);
+
+        assert_eq!((data.success_count, data.failure_count), (2, 1));
+        assert_eq!(data.min_connect_time_ms, 10);
+        assert_eq!(data.average_connect_time_ms, 20);
+        assert_eq!(data.max_connect_time_ms, 30);
+    }
+}
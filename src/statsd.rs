@@ -0,0 +1,163 @@
+//! Optional StatsD export of task and load test results.
+//!
+//! Metrics are only sent when a StatsD address was configured at startup with `--statsd-addr`.
+//! They go out over UDP with DogStatsD tags, one datagram per result; a send that fails is
+//! logged and dropped, so an unreachable agent never holds up monitoring.
+
+use std::{net::{SocketAddr, ToSocketAddrs, UdpSocket}, sync::OnceLock};
+use crate::{loadtest::LoadTestMonitoringData, tasks::MonitoringData};
+
+/// The client results are sent through, installed once by `init_statsd`.
+static CLIENT: OnceLock<StatsdClient> = OnceLock::new();
+
+/// Prefix of every metric name.
+const PREFIX: &str = "thunderhawk";
+
+/// Sets up metric export to the StatsD agent at `addr` (`host:port`).
+///
+/// Does nothing when no address is configured, and fails when the address can't be used.
+pub fn init_statsd(addr: Option<&str>) -> std::io::Result<()> {
+    let Some(addr) = addr else {
+        return Ok(());
+    };
+    let client = StatsdClient::connect(addr)?;
+    if CLIENT.set(client).is_err() {
+        log::warn!("StatsD export was already initialized; ignoring {}.", addr);
+    } else {
+        log::info!("Sending metrics to StatsD at {}", addr);
+    }
+    Ok(())
+}
+
+/// Sends a task result, when StatsD export is set up.
+pub(crate) fn record_task(workflow: &str, task: &str, data: &MonitoringData) {
+    if let Some(client) = CLIENT.get() {
+        client.record_task(workflow, task, data);
+    }
+}
+
+/// Sends a load test result, when StatsD export is set up.
+pub(crate) fn record_load_test(workflow: &str, task: &str, data: &LoadTestMonitoringData) {
+    if let Some(client) = CLIENT.get() {
+        client.record_load_test(workflow, task, data);
+    }
+}
+
+/// A non-blocking UDP socket connected to a StatsD agent.
+#[derive(Debug)]
+pub struct StatsdClient {
+    socket: UdpSocket,
+}
+
+impl StatsdClient {
+    /// Resolves `addr` and connects a non-blocking UDP socket to it.
+    pub fn connect(addr: &str) -> std::io::Result<Self> {
+        let target: SocketAddr = addr.to_socket_addrs()?.next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, format!("{} did not resolve", addr)))?;
+        let local: SocketAddr = if target.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(target)?;
+        socket.set_nonblocking(true)?;
+        Ok(StatsdClient { socket })
+    }
+
+    /// Sends `thunderhawk.response_time` and a `thunderhawk.success` or `thunderhawk.failure` count.
+    pub fn record_task(&self, workflow: &str, task: &str, data: &MonitoringData) {
+        let tags = tags(workflow, task, &[("status", &data.status)]);
+        let outcome = if matches!(data.status.as_str(), "OK" | "DEGRADED") { "success" } else { "failure" };
+        self.send(&[
+            format!("{}.response_time:{}|ms|#{}", PREFIX, data.response_time, tags),
+            format!("{}.{}:1|c|#{}", PREFIX, outcome, tags),
+        ]);
+    }
+
+    /// Sends a load test's latencies, request counts and rate under `thunderhawk.load_test.*`.
+    pub fn record_load_test(&self, workflow: &str, task: &str, data: &LoadTestMonitoringData) {
+        let tags = tags(workflow, task, &[]);
+        self.send(&[
+            format!("{}.load_test.median_response_time:{}|ms|#{}", PREFIX, data.median_response_time_ms, tags),
+            format!("{}.load_test.p95_response_time:{}|ms|#{}", PREFIX, data.percentile_95th_response_time_ms, tags),
+            format!("{}.load_test.success:{}|c|#{}", PREFIX, data.success_count, tags),
+            format!("{}.load_test.failure:{}|c|#{}", PREFIX, data.failure_count, tags),
+            format!("{}.load_test.requests_per_second:{}|g|#{}", PREFIX, data.requests_per_second, tags),
+        ]);
+    }
+
+    /// Sends the metrics as one newline-separated datagram.
+    fn send(&self, metrics: &[String]) {
+        if let Err(e) = self.socket.send(metrics.join("\n").as_bytes()) {
+            log::warn!("Failed to send metrics to StatsD: {}", e);
+        }
+    }
+}
+
+/// Formats DogStatsD tags, replacing the characters that delimit tags and metrics.
+fn tags(workflow: &str, task: &str, extra: &[(&str, &str)]) -> String {
+    [("workflow", workflow), ("task", task)].iter().chain(extra)
+        .map(|(key, value)| format!("{}:{}", key, value.replace([',', '|', '#', '\n'], "_")))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{sample_load_test_data, sample_task_data};
+    use std::time::Duration;
+
+    #[test]
+    fn test_task_result_is_sent_as_tagged_metrics() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let client = StatsdClient::connect(&agent.local_addr().unwrap().to_string()).unwrap();
+        let data = MonitoringData {
+            response_time: 42,
+            response_time_micros: 42_000,
+            status_code: Some(500),
+            ..sample_task_data("ERROR")
+        };
+
+        client.record_task("Sample Workflow", "Fetch, Todo", &data);
+
+        let mut buffer = [0; 1024];
+        let received = agent.recv(&mut buffer).unwrap();
+        assert_eq!(std::str::from_utf8(&buffer[..received]).unwrap(), [
+            "thunderhawk.response_time:42|ms|#workflow:Sample Workflow,task:Fetch_ Todo,status:ERROR",
+            "thunderhawk.failure:1|c|#workflow:Sample Workflow,task:Fetch_ Todo,status:ERROR",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_load_test_result_is_sent_as_tagged_metrics() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        agent.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        let client = StatsdClient::connect(&agent.local_addr().unwrap().to_string()).unwrap();
+        let data = LoadTestMonitoringData {
+            total_requests: 100,
+            success_count: 97,
+            failure_count: 3,
+            percentile_95th_response_time_ms: 40,
+            requests_per_second: 12.5,
+            ..sample_load_test_data()
+        };
+
+        client.record_load_test("Sample Workflow", "Fetch Todos", &data);
+
+        let mut buffer = [0; 1024];
+        let received = agent.recv(&mut buffer).unwrap();
+        let tags = "#workflow:Sample Workflow,task:Fetch Todos";
+        assert_eq!(std::str::from_utf8(&buffer[..received]).unwrap(), [
+            format!("thunderhawk.load_test.median_response_time:12|ms|{}", tags),
+            format!("thunderhawk.load_test.p95_response_time:40|ms|{}", tags),
+            format!("thunderhawk.load_test.success:97|c|{}", tags),
+            format!("thunderhawk.load_test.failure:3|c|{}", tags),
+            format!("thunderhawk.load_test.requests_per_second:12.5|g|{}", tags),
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_unusable_address_fails_to_initialize() {
+        assert!(init_statsd(Some("no port here")).is_err());
+        assert!(init_statsd(None).is_ok());
+    }
+}
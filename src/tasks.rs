@@ -1,28 +1,83 @@
 use std::{collections::HashMap, str::FromStr, sync::Arc};
-use log::{info,error};
+use log::{info,error,warn};
 use tokio::sync::Mutex;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use serde::Serialize;
-use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
-use crate::{appstate::AppState, config::{ApiConfig, HttpMethod}, factory::{create_request_builder, ApiMonitor}};
-use std::time::Instant;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AGE, CONTENT_TYPE, ETAG, IF_NONE_MATCH, RETRY_AFTER};
+use crate::{appstate::{evict_results, AppState}, config::{ApiConfig, HttpMethod, Settings, DEFAULT_LEARN_CYCLES, DEFAULT_THRESHOLD_FACTOR}, factory::{create_request_builder, ApiMonitor, MonitorError, SharedRateLimiter}, loadtest::percentile_95th, statsd, telemetry::MonitorSpan, utils::http_client::read_body_limited};
+use std::time::{Duration, Instant, SystemTime};
 
 
 /// Represents the data collected during the monitoring of an API call.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct MonitoringData {
     /// The name of the workflow this data is associated with.
     pub api_url: String,
-    /// The status of the monitoring operation, e.g., "OK" or "ERROR".
+    /// The status of the monitoring operation: "OK", "DEGRADED" when slower than the warn
+    /// threshold, or "ERROR".
     pub status: String,
     /// The response time measured for the API call, in milliseconds.
     pub response_time: u64,
+    /// The same response time in microseconds, which keeps sub-millisecond responses apart from 0.
+    pub response_time_micros: u64,
     /// The HTTP status code returned by the API call, if applicable.
     pub status_code: Option<u16>,
     /// The HTTP method used for the API call.
     pub method: HttpMethod,
+    /// A truncated snippet of the response body, captured only for failed calls
+    /// when `capture_body_on_error` is enabled.
+    pub error_body: Option<String>,
+    /// The request ID sent with the call, when request ID injection is enabled.
+    pub request_id: Option<String>,
+    /// The `Idempotency-Key` sent with the request, when `generate_idempotency_key` is enabled.
+    pub idempotency_key: Option<String>,
+    /// The addresses a DNS check resolved the host to.
+    pub resolved_ips: Option<Vec<String>>,
+    /// How many `429 Too Many Requests` responses were received before the recorded one.
+    pub rate_limited_count: usize,
+    /// With ETag tracking, whether the content changed since the previous run (`false` on a 304).
+    /// `None` when tracking is off or there was no previous ETag to compare against.
+    pub content_changed: Option<bool>,
+    /// Whether reading the response body stopped at `max_response_bytes`.
+    pub body_truncated: bool,
+    /// Whether a cache such as a CDN served the response, from its `X-Cache` header, or else
+    /// from a non-zero `Age`. `None` when the response carried neither.
+    pub cache_hit: Option<bool>,
+    /// How long the response had been cached, in seconds, from its `Age` header.
+    pub cache_age_secs: Option<u64>,
+    /// The response time, in milliseconds, above which the call counts as degraded.
+    pub response_time_warn_ms: Option<u64>,
+    /// The response time, in milliseconds, above which the call counts as failed.
+    pub response_time_fail_ms: Option<u64>,
 }
 
+/// A response time threshold learned from a task's first successful runs.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LearnedThreshold {
+    /// Response times, in milliseconds, of the warmup runs so far.
+    pub samples: Vec<u64>,
+    /// The p95 of the warmup runs, once all of them are in.
+    pub baseline_p95_ms: Option<u64>,
+    /// `baseline_p95_ms` scaled by `threshold_factor`; slower responses fail.
+    pub threshold_ms: Option<u64>,
+}
+
+/// A task's run of consecutive failures, for tasks with `circuit_break_after`.
+#[derive(Debug, Clone, Default)]
+pub struct CircuitBreaker {
+    /// Failed runs since the last successful one.
+    pub consecutive_failures: usize,
+    /// When the circuit last opened, or last let a probe through. Runs are skipped until the
+    /// cooldown has passed.
+    pub opened_at: Option<Instant>,
+}
+
+/// Delay before retrying a 429 response that carries no usable `Retry-After` header.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Default upper bound on the size of a captured error body snippet.
+const DEFAULT_ERROR_BODY_MAX_BYTES: usize = 1024;
+
 
 pub enum MonitoringDataType {
     /// Represents a simple task monitoring operation.
@@ -35,13 +90,52 @@ pub struct Task {
     pub api_config: Arc<ApiConfig>,
     /// A reference to the shared application state for recording monitoring data.
     pub app_state: Arc<Mutex<AppState>>, // Include a reference to AppState
+    /// Global settings that influence how requests are built.
+    pub settings: Arc<Settings>,
+    /// The workflow's request rate limiter, waited on before every request, retries included.
+    pub rate_limiter: Option<SharedRateLimiter>,
 }
 
 #[async_trait::async_trait]
 impl ApiMonitor for Task {
 
-    async fn execute(&self, client: &Client, workflow_name: &str) -> Result<(), String> {
-        let start = Instant::now();
+    async fn execute(&self, client: &Client, workflow_name: &str) -> Result<(), MonitorError> {
+        let Some(break_after) = self.api_config.circuit_break_after else {
+            return self.check(client, workflow_name).await;
+        };
+        if !claim_circuit(&self.app_state, workflow_name, &self.api_config).await {
+            record_task_status(&self.app_state, workflow_name, &self.api_config, "CIRCUIT_OPEN", Duration::ZERO).await;
+            return Err(MonitorError::Request(format!("'{}' skipped: its circuit is open after {} consecutive failures", self.api_config.name, break_after)));
+        }
+
+        let result = self.check(client, workflow_name).await;
+        record_circuit_outcome(&self.app_state, workflow_name, &self.api_config, break_after, result.is_ok()).await;
+        result
+    }
+
+    fn describe(&self) -> String {
+        format!("Task for {}", self.api_config.name)
+    }
+
+    fn response_time_threshold(&self) -> Option<u64> {
+        None // No specific threshold for HTTP status monitoring
+    }
+
+    fn get_task_order(&self) -> usize {
+        self.api_config.task_order.unwrap_or(usize::MAX)
+    }
+
+    fn api_configs(&self) -> Vec<Arc<ApiConfig>> {
+        vec![self.api_config.clone()]
+    }
+}
+
+
+impl Task {
+    /// Sends the request, retrying rate limited responses, checks the response and records the result.
+    async fn check(&self, client: &Client, workflow_name: &str) -> Result<(), MonitorError> {
+        let mut span = MonitorSpan::start("task", workflow_name, &self.api_config.name, &self.api_config.url, &self.api_config.method);
+        let mut start;
         let mut headers = HeaderMap::new();
 
         for (key, value) in &self.api_config.headers {
@@ -53,41 +147,173 @@ impl ApiMonitor for Task {
             }
         }
 
-        let request_builder = create_request_builder(client, &self.api_config)?;
+        // A 429 is retried after its `Retry-After` delay, as long as retries remain and the
+        // total wait stays within the HTTP timeout.
+        let max_retries = self.api_config.retry_count.unwrap_or(0);
+        let wait_budget = Duration::from_secs(self.settings.http_timeout_seconds);
+        let mut waited = Duration::ZERO;
+        let mut rate_limited_count = 0;
+
+        // With ETag tracking, the previous ETag is sent back so an unchanged resource answers 304.
+        let track_etag = self.api_config.track_etag.unwrap_or(false);
+        let previous_etag = if track_etag {
+            stored_etag(&self.app_state, workflow_name, &self.api_config.name).await
+        } else {
+            None
+        };
+
+        // Every attempt carries the first attempt's idempotency key, so the target can tell a
+        // retry from a new write.
+        let mut first_idempotency_key: Option<String> = None;
+        let (response, request_id, idempotency_key) = loop {
+            let mut prepared = create_request_builder(client, &self.api_config, &self.settings).map_err(MonitorError::Config)?;
+            match &first_idempotency_key {
+                Some(key) => prepared = prepared.with_idempotency_key(key),
+                None => first_idempotency_key = prepared.idempotency_key.clone(),
+            }
+            let builder = match &previous_etag {
+                Some(etag) => prepared.builder.header(IF_NONE_MATCH, etag),
+                None => prepared.builder,
+            };
+            // The wait for the rate limiter doesn't count towards the response time.
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.until_ready().await;
+            }
+            start = Instant::now();
+            let response = builder.send().await;
+
+            let delay = match &response {
+                Ok(resp) if resp.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    rate_limited_count += 1;
+                    retry_after(resp.headers()).unwrap_or(DEFAULT_RETRY_AFTER)
+                },
+                _ => break (response, prepared.request_id, prepared.idempotency_key),
+            };
+            if rate_limited_count > max_retries || waited + delay > wait_budget {
+                break (response, prepared.request_id, prepared.idempotency_key);
+            }
 
-        let response = request_builder.send().await;
+            info!("'{}' was rate limited; retrying in {:?}", self.api_config.name, delay);
+            tokio::time::sleep(delay).await;
+            waited += delay;
+        };
 
         let duration = start.elapsed();
+        let response_time = duration.as_millis() as u64;
+        let response_time_micros = duration.as_micros() as u64;
+        let response_time_warn_ms = self.api_config.response_time_warn_ms;
+        let response_time_fail_ms = self.api_config.response_time_fail_limit();
 
         // Create a MonitoringData instance based on the response
         match response {
             Ok(resp) => {
                 let status_code = resp.status().as_u16();
-                if resp.status().is_success() {
-                    // If the status is within the range of success codes
-                    let monitoring_data = MonitoringData {
-                        api_url: self.api_config.url.clone(),
-                        status: "OK".to_string(),
-                        response_time: duration.as_millis() as u64,
-                        status_code: Some(status_code), // Store the successful status code
-                        method: self.api_config.method.clone(), // Include the method in the monitoring data
-                    };
-                    update_app_state(&self.app_state, &workflow_name, &self.api_config.name, MonitoringDataType::Task, monitoring_data).await;
-                    info!("'{}' succeeded with status code {} in {:?}", self.api_config.name, status_code, duration);
-                    Ok(())
+                let (cache_hit, cache_age_secs) = cache_status(resp.headers());
+                let not_modified = previous_etag.is_some() && resp.status() == StatusCode::NOT_MODIFIED;
+                let content_changed = if track_etag {
+                    let etag = resp.headers().get(ETAG).and_then(|value| value.to_str().ok()).map(str::to_string);
+                    let changed = previous_etag.as_ref().map(|previous| !not_modified && etag.as_ref() != Some(previous));
+                    if let Some(etag) = etag {
+                        store_etag(&self.app_state, workflow_name, &self.api_config.name, etag).await;
+                    }
+                    changed
                 } else {
-                    // For non-successful HTTP status codes
-                    let error_message = format!("'{}' responded with HTTP status {}", self.api_config.name, status_code);
-                    error!("{}", error_message);
-                    let monitoring_data = MonitoringData {
-                        api_url: self.api_config.url.clone(),
-                        status: "ERROR".to_string(),
-                        response_time: duration.as_millis() as u64,
-                        status_code: Some(status_code), // Store the error status code
-                        method: self.api_config.method.clone(), // Include the method in the monitoring data
-                    };
-                    update_app_state(&self.app_state, &workflow_name, &self.api_config.name, MonitoringDataType::Task, monitoring_data).await;
-                    Err(error_message)
+                    None
+                };
+                let capture_body = self.api_config.capture_body_on_error.unwrap_or(false);
+                let mut body_truncated = false;
+                let (failure, body) = if not_modified {
+                    (None, None)
+                } else if self.api_config.is_expected_status(status_code) {
+                    let content_type = resp.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok()).map(str::to_string);
+                    if !self.api_config.has_expected_content_type(content_type.as_deref()) {
+                        let body = if capture_body { Some(self.read_body(resp, &mut body_truncated).await) } else { None };
+                        (Some(format!("'{}' responded with Content-Type {} instead of {}", self.api_config.name,
+                            content_type.as_deref().unwrap_or("(none)"), self.api_config.expected_content_type.as_deref().unwrap_or_default())), body)
+                    } else if self.checks_body() {
+                        let body = self.read_body(resp, &mut body_truncated).await;
+                        (self.body_failure(&body), Some(body))
+                    } else {
+                        (None, None)
+                    }
+                } else {
+                    let body = if capture_body { Some(self.read_body(resp, &mut body_truncated).await) } else { None };
+                    (Some(format!("'{}' responded with HTTP status {}", self.api_config.name, status_code)), body)
+                };
+                // A learned threshold only tightens the configured limit.
+                let learned_limit = if self.api_config.learn_threshold.unwrap_or(false) {
+                    learn_threshold(&self.app_state, workflow_name, &self.api_config, response_time, failure.is_none()).await
+                } else {
+                    None
+                };
+                let response_time_fail_ms = response_time_fail_ms.into_iter().chain(learned_limit).min();
+                let latency_status = self.api_config.response_time_status(response_time, response_time_fail_ms);
+                let failure = failure.or_else(|| (latency_status == "ERROR").then(|| {
+                    format!("'{}' took {}ms, over its {}ms limit", self.api_config.name, response_time, response_time_fail_ms.unwrap_or_default())
+                }));
+
+                match failure {
+                    None => {
+                        // If the status is one of the expected success codes
+                        let monitoring_data = MonitoringData {
+                            api_url: self.api_config.url.clone(),
+                            status: latency_status.to_string(),
+                            response_time,
+                            response_time_micros,
+                            status_code: Some(status_code), // Store the successful status code
+                            method: self.api_config.method.clone(), // Include the method in the monitoring data
+                            error_body: None, // Bodies are never captured on success
+                            request_id,
+                            idempotency_key,
+                            resolved_ips: None,
+                            rate_limited_count,
+                            content_changed,
+                            body_truncated,
+                            cache_hit,
+                            cache_age_secs,
+                            response_time_warn_ms,
+                            response_time_fail_ms,
+                        };
+                        update_app_state(&self.app_state, workflow_name, &self.api_config.name, MonitoringDataType::Task, monitoring_data).await;
+                        span.record_status(latency_status, Some(status_code));
+                        if latency_status == "DEGRADED" {
+                            warn!("'{}' succeeded with status code {} but took {:?}, over its {}ms warn threshold",
+                                self.api_config.name, status_code, duration, response_time_warn_ms.unwrap_or_default());
+                        } else {
+                            info!("'{}' succeeded with status code {} in {:?}", self.api_config.name, status_code, duration);
+                        }
+                        Ok(())
+                    },
+                    Some(error_message) => {
+                        // For non-successful HTTP status codes and GraphQL errors
+                        error!("{}", error_message);
+                        let error_body = body.filter(|_| capture_body).map(|body| {
+                            let max_bytes = self.api_config.error_body_max_bytes.unwrap_or(DEFAULT_ERROR_BODY_MAX_BYTES);
+                            truncate_body(&body, max_bytes)
+                        });
+                        let monitoring_data = MonitoringData {
+                            api_url: self.api_config.url.clone(),
+                            status: "ERROR".to_string(),
+                            response_time,
+                            response_time_micros,
+                            status_code: Some(status_code), // Store the error status code
+                            method: self.api_config.method.clone(), // Include the method in the monitoring data
+                            error_body,
+                            request_id,
+                            idempotency_key,
+                            resolved_ips: None,
+                            rate_limited_count,
+                            content_changed,
+                            body_truncated,
+                            cache_hit,
+                            cache_age_secs,
+                            response_time_warn_ms,
+                            response_time_fail_ms,
+                        };
+                        update_app_state(&self.app_state, workflow_name, &self.api_config.name, MonitoringDataType::Task, monitoring_data).await;
+                        span.record_status("ERROR", Some(status_code));
+                        Err(MonitorError::Assertion(error_message))
+                    },
                 }
             },
             Err(e) => {
@@ -97,31 +323,230 @@ impl ApiMonitor for Task {
                 let monitoring_data = MonitoringData {
                     api_url: self.api_config.url.clone(),
                     status: "ERROR".to_string(),
-                    response_time: duration.as_millis() as u64,
+                    response_time,
+                    response_time_micros,
                     status_code: None, // No status code available in case of a connection error
                     method: self.api_config.method.clone(), // Include the method in the monitoring data
+                    request_id,
+                    idempotency_key,
+                    rate_limited_count,
+                    response_time_warn_ms,
+                    response_time_fail_ms,
+                    ..Default::default()
                 };
-                update_app_state(&self.app_state, &workflow_name,  &self.api_config.name, MonitoringDataType::Task, monitoring_data).await;
-                Err(error_message)
+                update_app_state(&self.app_state, workflow_name,  &self.api_config.name, MonitoringDataType::Task, monitoring_data).await;
+                span.record_status("ERROR", None);
+                if e.is_timeout() {
+                    Err(MonitorError::Timeout(error_message))
+                } else {
+                    Err(MonitorError::Request(error_message))
+                }
             }
         }
     }
 
-    fn describe(&self) -> String {
-        format!("Task for {}", self.api_config.name)
+    /// Whether a successful response still has its body checked.
+    fn checks_body(&self) -> bool {
+        self.api_config.graphql_query.is_some()
+            || !self.api_config.expected_field.is_empty()
+            || self.api_config.fail_on_empty_body.unwrap_or(false)
     }
 
-    fn response_time_threshold(&self) -> Option<u64> {
-        None // No specific threshold for HTTP status monitoring
+    /// Why the body of a successful response counts as a failure, if it does.
+    fn body_failure(&self, body: &str) -> Option<String> {
+        let name = &self.api_config.name;
+        if self.api_config.fail_on_empty_body.unwrap_or(false) && body.trim().is_empty() {
+            return Some(format!("'{}' responded with an empty body", name));
+        }
+        if self.api_config.graphql_query.is_some() {
+            // GraphQL reports failures in the response body, usually alongside a 200.
+            return graphql_errors(body).map(|errors| format!("'{}' returned GraphQL errors: {}", name, errors));
+        }
+        if self.api_config.expected_field.is_empty() {
+            return None;
+        }
+        let found = serde_json::from_str(body).is_ok_and(|json| self.api_config.has_expected_field(&json));
+        (!found).then(|| format!("'{}' response has no field '{}'", name, self.api_config.expected_field))
     }
 
-    fn get_task_order(&self) -> usize {
-        self.api_config.task_order.unwrap_or(usize::MAX)
+    /// Reads the response body as text, up to `max_response_bytes`, noting whether it was cut short.
+    async fn read_body(&self, resp: reqwest::Response, truncated: &mut bool) -> String {
+        match read_body_limited(resp, self.settings.max_response_bytes).await {
+            Ok((body, was_truncated)) => {
+                if was_truncated {
+                    log::warn!("'{}' response body exceeded {:?} bytes; truncated", self.api_config.name, self.settings.max_response_bytes);
+                }
+                *truncated = was_truncated;
+                String::from_utf8_lossy(&body).into_owned()
+            },
+            Err(e) => {
+                log::warn!("Failed to read response body of '{}': {}", self.api_config.name, e);
+                String::new()
+            }
+        }
+    }
+}
+
+/// The ETag last seen for a task, if any.
+async fn stored_etag(app_state: &Arc<Mutex<AppState>>, workflow_name: &str, task_name: &str) -> Option<String> {
+    let state = app_state.lock().await;
+    let etags = state.etags.lock().await;
+    etags.get(workflow_name).and_then(|tasks| tasks.get(task_name)).cloned()
+}
+
+/// Remembers a task's latest ETag for the next run.
+async fn store_etag(app_state: &Arc<Mutex<AppState>>, workflow_name: &str, task_name: &str, etag: String) {
+    let state = app_state.lock().await;
+    state.etags.lock().await
+        .entry(workflow_name.to_string())
+        .or_default()
+        .insert(task_name.to_string(), etag);
+}
+
+/// Whether the task may run. A closed circuit lets every run through. An open one, which failed
+/// `circuit_break_after` times in a row, skips runs until its cooldown has passed and then lets
+/// a single probe run through, restarting the cooldown so concurrent runs keep being skipped.
+async fn claim_circuit(app_state: &Arc<Mutex<AppState>>, workflow_name: &str, api_config: &ApiConfig) -> bool {
+    let state = app_state.lock().await;
+    let mut circuit_breakers = state.circuit_breakers.lock().await;
+    let Some(opened_at) = circuit_breakers.get_mut(workflow_name)
+        .and_then(|tasks| tasks.get_mut(&api_config.name))
+        .and_then(|breaker| breaker.opened_at.as_mut())
+    else {
+        return true;
+    };
+    if opened_at.elapsed() < api_config.circuit_cooldown() {
+        return false;
     }
+    *opened_at = Instant::now();
+    true
 }
 
+/// Counts a failed run toward the task's consecutive failures, opening its circuit at
+/// `break_after`; a failed probe reopens it for another cooldown. A successful run closes it.
+async fn record_circuit_outcome(app_state: &Arc<Mutex<AppState>>, workflow_name: &str, api_config: &ApiConfig, break_after: usize, succeeded: bool) {
+    let state = app_state.lock().await;
+    let mut circuit_breakers = state.circuit_breakers.lock().await;
+    let breaker = circuit_breakers
+        .entry(workflow_name.to_string())
+        .or_default()
+        .entry(api_config.name.clone())
+        .or_default();
+
+    if succeeded {
+        if breaker.opened_at.is_some() {
+            info!("'{}' succeeded again; closing its circuit", api_config.name);
+        }
+        *breaker = CircuitBreaker::default();
+        return;
+    }
+    breaker.consecutive_failures += 1;
+    if breaker.consecutive_failures >= break_after {
+        warn!("'{}' failed {} times in a row; skipping it for {:?}", api_config.name, breaker.consecutive_failures, api_config.circuit_cooldown());
+        breaker.opened_at = Some(Instant::now());
+    }
+}
+
+/// Returns the task's learned threshold once its warmup is over. Until then, records
+/// `response_time` as a warmup sample when the run succeeded, and computes the baseline once
+/// `learn_cycles` samples are in.
+async fn learn_threshold(app_state: &Arc<Mutex<AppState>>, workflow_name: &str, api_config: &ApiConfig, response_time: u64, succeeded: bool) -> Option<u64> {
+    let state = app_state.lock().await;
+    let mut learned_thresholds = state.learned_thresholds.lock().await;
+    let learned = learned_thresholds
+        .entry(workflow_name.to_string())
+        .or_default()
+        .entry(api_config.name.clone())
+        .or_default();
 
-async fn update_app_state(
+    if learned.threshold_ms.is_some() || !succeeded {
+        return learned.threshold_ms;
+    }
+    learned.samples.push(response_time);
+    if learned.samples.len() >= api_config.learn_cycles.unwrap_or(DEFAULT_LEARN_CYCLES).max(1) {
+        let mut sorted: Vec<u128> = learned.samples.iter().map(|ms| u128::from(*ms)).collect();
+        sorted.sort_unstable();
+        let baseline = percentile_95th(&sorted) as u64;
+        let factor = api_config.threshold_factor.unwrap_or(DEFAULT_THRESHOLD_FACTOR);
+        // A 0ms baseline would otherwise fail every later run that takes a millisecond.
+        let threshold = ((baseline as f64 * factor).ceil() as u64).max(1);
+        info!("'{}' learned a baseline p95 of {}ms; responses over {}ms now fail", api_config.name, baseline, threshold);
+        learned.baseline_p95_ms = Some(baseline);
+        learned.threshold_ms = Some(threshold);
+    }
+    None
+}
+
+/// Reads a `Retry-After` header given either as delay seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    // A date in the past means "retry now".
+    Some(date.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+}
+
+/// Returns the messages from a non-empty top-level GraphQL `errors` array, joined by "; ".
+fn graphql_errors(body: &str) -> Option<String> {
+    let response: serde_json::Value = serde_json::from_str(body).ok()?;
+    let errors = response.get("errors")?.as_array().filter(|errors| !errors.is_empty())?;
+    let messages: Vec<String> = errors.iter()
+        .map(|error| error.get("message").and_then(|m| m.as_str()).map(str::to_string).unwrap_or_else(|| error.to_string()))
+        .collect();
+    Some(messages.join("; "))
+}
+
+/// Truncates a response body to at most `max_bytes`, backing off to the nearest
+/// character boundary so the snippet stays valid UTF-8.
+fn truncate_body(body: &str, max_bytes: usize) -> String {
+    if body.len() <= max_bytes {
+        return body.to_string();
+    }
+    let mut end = max_bytes;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    body[..end].to_string()
+}
+
+/// Reads cache behaviour from the response headers: `X-Cache` (e.g. `HIT`, `Hit from cloudfront`,
+/// `TCP_MISS`) decides whether the response was a cache hit, falling back to a non-zero `Age`.
+fn cache_status(headers: &HeaderMap) -> (Option<bool>, Option<u64>) {
+    let age_secs = headers.get(AGE).and_then(|value| value.to_str().ok()).and_then(|value| value.trim().parse::<u64>().ok());
+    let x_cache = headers.get("x-cache").and_then(|value| value.to_str().ok()).map(str::to_ascii_uppercase);
+    let hit = match x_cache {
+        Some(x_cache) if x_cache.contains("HIT") => Some(true),
+        Some(x_cache) if x_cache.contains("MISS") => Some(false),
+        _ => age_secs.filter(|age| *age > 0).map(|_| true),
+    };
+    (hit, age_secs)
+}
+
+/// Records a result carrying only a status (e.g. "TIMEOUT") for an API that produced no response.
+pub(crate) async fn record_task_status(
+    app_state: &Arc<Mutex<AppState>>,
+    workflow_name: &str,
+    api_config: &ApiConfig,
+    status: &str,
+    elapsed: Duration,
+) {
+    let monitoring_data = MonitoringData {
+        api_url: api_config.url.clone(),
+        status: status.to_string(),
+        response_time: elapsed.as_millis() as u64,
+        response_time_micros: elapsed.as_micros() as u64,
+        status_code: None,
+        method: api_config.method.clone(),
+        response_time_warn_ms: api_config.response_time_warn_ms,
+        response_time_fail_ms: api_config.response_time_fail_limit(),
+        ..Default::default()
+    };
+    update_app_state(app_state, workflow_name, &api_config.name, MonitoringDataType::Task, monitoring_data).await;
+}
+
+pub(crate) async fn update_app_state(
     app_state: &Arc<Mutex<AppState>>,
     workflow_name: &str,
     task_name: &str,
@@ -142,11 +567,678 @@ async fn update_app_state(
                 .entry(workflow_name.to_string()) // Now correctly using entry on the HashMap
                 .or_insert_with(HashMap::new);
 
+            statsd::record_task(workflow_name, task_name, &monitoring_data);
+
             // Update the monitoring data for the specific API URL within the workflow
             workflow_data.insert(task_name.to_string(), monitoring_data);
 
+            // Dynamically named tasks would otherwise grow the map without bound.
+            let evicted = state.task_results_order.lock().await.touch(workflow_name, task_name, state.max_results_entries);
+            evict_results(task_monitoring_data, &evicted);
+
             log::info!("Updated task data for {} in workflow {}", task_name, workflow_name);
         },
    
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn api_config(url: &str, extra: &str) -> ApiConfig {
+        let yaml = format!(
+            r#"
+name: "Fetch Todo"
+url: "{}"
+method: GET
+headers: {{}}
+expected_field: "id"
+response_time_threshold: 2000
+{}
+"#,
+            url, extra
+        );
+        serde_yaml::from_str(&yaml).expect("Failed to parse YAML")
+    }
+
+    async fn run_task(api_config: ApiConfig) -> (Result<(), MonitorError>, MonitoringData) {
+        run_task_with_settings(api_config, Settings::default()).await
+    }
+
+    async fn run_task_with_settings(api_config: ApiConfig, settings: Settings) -> (Result<(), MonitorError>, MonitoringData) {
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let task = Task { api_config: Arc::new(api_config), app_state: app_state.clone(), settings: Arc::new(settings), rate_limiter: None };
+        let result = task.execute(&Client::new(), "Sample Workflow").await;
+
+        let state = app_state.lock().await;
+        let task_data = state.task_monitoring_data.lock().await;
+        (result, task_data["Sample Workflow"]["Fetch Todo"].clone())
+    }
+
+    #[test]
+    fn test_truncate_body_respects_char_boundaries() {
+        assert_eq!(truncate_body("short", 10), "short");
+        assert_eq!(truncate_body("abcdef", 3), "abc");
+        // 'é' is two bytes; cutting through it backs off to the previous boundary.
+        assert_eq!(truncate_body("aé", 2), "a");
+    }
+
+    #[tokio::test]
+    async fn test_error_response_captures_body_snippet() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("upstream database unavailable"))
+            .mount(&server)
+            .await;
+
+        let config = api_config(&server.uri(), "capture_body_on_error: true\nerror_body_max_bytes: 8");
+        let (result, data) = run_task(config).await;
+
+        assert!(matches!(result, Err(MonitorError::Assertion(_))), "{:?}", result);
+        assert_eq!(data.status, "ERROR");
+        assert_eq!(data.error_body.as_deref(), Some("upstream"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_response_fails_as_timeout() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+            .mount(&server)
+            .await;
+
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let task = Task { api_config: Arc::new(api_config(&server.uri(), "")), app_state, settings: Arc::new(Settings::default()), rate_limiter: None };
+        let client = Client::builder().timeout(Duration::from_millis(200)).build().unwrap();
+
+        let result = task.execute(&client, "Sample Workflow").await;
+
+        assert!(matches!(result, Err(MonitorError::Timeout(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_body_file_fails_as_config_error() {
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let config = api_config("http://127.0.0.1:9", "body_file: \"/nonexistent/thunderhawk-body.json\"");
+        let task = Task { api_config: Arc::new(config), app_state, settings: Arc::new(Settings::default()), rate_limiter: None };
+
+        let result = task.execute(&Client::new(), "Sample Workflow").await;
+
+        assert!(matches!(result, Err(MonitorError::Config(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_success_response_does_not_capture_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{\"id\": 1}"))
+            .mount(&server)
+            .await;
+
+        let config = api_config(&server.uri(), "capture_body_on_error: true");
+        let (result, data) = run_task(config).await;
+
+        assert!(result.is_ok());
+        assert_eq!(data.status, "OK");
+        assert_eq!(data.error_body, None);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_errors_array_marks_error_despite_200() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"data": null, "errors": [{"message": "Cannot query field \"todo\""}]}"#))
+            .mount(&server)
+            .await;
+
+        let config = api_config(&server.uri(), "graphql_query: \"{ todo { id } }\"");
+        let (result, data) = run_task(config).await;
+
+        let error = result.unwrap_err();
+        assert!(matches!(error, MonitorError::Assertion(_)), "{:?}", error);
+        assert!(error.message().contains("Cannot query field"));
+        assert_eq!(data.status, "ERROR");
+        assert_eq!(data.status_code, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_graphql_response_without_errors_is_ok() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"data": {"todo": {"id": 1}}, "errors": []}"#))
+            .mount(&server)
+            .await;
+
+        let config = api_config(&server.uri(), "graphql_query: \"{ todo { id } }\"");
+        let (result, data) = run_task(config).await;
+
+        assert!(result.is_ok());
+        assert_eq!(data.status, "OK");
+    }
+
+    async fn mount_rate_limit_then_ok(server: &MockServer, retry_after: &str) {
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", retry_after))
+            .up_to_n_times(1)
+            .mount(server)
+            .await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#)).mount(server).await;
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_retry_reuses_idempotency_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST")).respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#)).mount(&server).await;
+
+        let mut config = api_config(&server.uri(), "retry_count: 1\ngenerate_idempotency_key: true");
+        config.method = HttpMethod::POST;
+        let (result, data) = run_task(config).await;
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(data.rate_limited_count, 1);
+        let received = server.received_requests().await.unwrap();
+        let keys: Vec<Vec<&str>> = received.iter()
+            .map(|request| request.headers.get_all("Idempotency-Key").iter().map(|value| value.to_str().unwrap()).collect())
+            .collect();
+        let recorded = data.idempotency_key.expect("the key should be recorded");
+        assert_eq!(keys, [[recorded.as_str()], [recorded.as_str()]]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_retries_wait_for_the_workflow_rate_limiter() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#)).mount(&server).await;
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let task = Task {
+            api_config: Arc::new(api_config(&server.uri(), "retry_count: 2")),
+            app_state: app_state.clone(),
+            settings: Arc::new(Settings::default()),
+            rate_limiter: crate::factory::rate_limiter(5.0).map(Arc::new),
+        };
+
+        let started = Instant::now();
+        task.execute(&Client::new(), "Sample Workflow").await.unwrap();
+
+        // Three requests at 5/s: the retries go out 200ms apart although `Retry-After` is 0.
+        assert!(started.elapsed() >= Duration::from_millis(380), "took {:?}", started.elapsed());
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+        let state = app_state.lock().await;
+        let data = state.task_monitoring_data.lock().await["Sample Workflow"]["Fetch Todo"].clone();
+        assert_eq!(data.rate_limited_count, 2);
+        // The wait for the limiter isn't part of the response time.
+        assert!(data.response_time < 150, "{}ms", data.response_time);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_task_retries_after_seconds() {
+        let server = MockServer::start().await;
+        mount_rate_limit_then_ok(&server, "1").await;
+
+        let started = Instant::now();
+        let (result, data) = run_task(api_config(&server.uri(), "retry_count: 2")).await;
+
+        assert!(result.is_ok());
+        assert_eq!(data.status, "OK");
+        assert_eq!(data.rate_limited_count, 1);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_task_retries_after_http_date() {
+        let server = MockServer::start().await;
+        let retry_at = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(2));
+        mount_rate_limit_then_ok(&server, &retry_at).await;
+
+        let (result, data) = run_task(api_config(&server.uri(), "retry_count: 1")).await;
+
+        assert!(result.is_ok());
+        assert_eq!(data.rate_limited_count, 1);
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_wait_beyond_timeout_is_not_retried() {
+        let server = MockServer::start().await;
+        mount_rate_limit_then_ok(&server, "120").await;
+
+        let settings = Settings { http_timeout_seconds: 5, ..Settings::default() };
+        let (result, data) = run_task_with_settings(api_config(&server.uri(), "retry_count: 3"), settings).await;
+
+        assert!(result.is_err());
+        assert_eq!(data.status_code, Some(429));
+        assert_eq!(data.rate_limited_count, 1);
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_retry_after_parses_seconds_and_dates() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_expected_status_codes_override_2xx_default() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(404).set_body_string(r#"{"id": 1}"#)).mount(&server).await;
+
+        let (result, data) = run_task(api_config(&server.uri(), "expected_status_codes: [404]")).await;
+
+        assert!(result.is_ok());
+        assert_eq!(data.status, "OK");
+    }
+
+    #[tokio::test]
+    async fn test_etag_is_revalidated_on_next_run() {
+        use wiremock::matchers::header;
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304).insert_header("ETag", "\"v1\""))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"v1\"").set_body_string("{\"id\": 1}"))
+            .mount(&server)
+            .await;
+
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let task = Task {
+            api_config: Arc::new(api_config(&server.uri(), "track_etag: true")),
+            app_state: app_state.clone(),
+            settings: Arc::new(Settings::default()),
+            rate_limiter: None,
+        };
+        let latest = || async {
+            let state = app_state.lock().await;
+            let task_data = state.task_monitoring_data.lock().await;
+            task_data["Sample Workflow"]["Fetch Todo"].clone()
+        };
+
+        assert!(task.execute(&Client::new(), "Sample Workflow").await.is_ok());
+        let first = latest().await;
+        assert_eq!((first.status_code, first.content_changed), (Some(200), None));
+
+        assert!(task.execute(&Client::new(), "Sample Workflow").await.is_ok());
+        let second = latest().await;
+        assert_eq!(second.status, "OK");
+        assert_eq!((second.status_code, second.content_changed), (Some(304), Some(false)));
+
+        let received = server.received_requests().await.unwrap();
+        assert!(received[0].headers.get("If-None-Match").is_none());
+        assert_eq!(received[1].headers.get("If-None-Match").unwrap(), "\"v1\"");
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_consecutive_failures_and_half_opens_after_cooldown() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(503)).mount(&server).await;
+
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let task = Task {
+            api_config: Arc::new(api_config(&server.uri(), "circuit_break_after: 2\ncircuit_cooldown_secs: 1")),
+            app_state: app_state.clone(),
+            settings: Arc::new(Settings::default()),
+            rate_limiter: None,
+        };
+        let run = || async {
+            let result = task.execute(&Client::new(), "Sample Workflow").await;
+            let state = app_state.lock().await;
+            let status = state.task_monitoring_data.lock().await["Sample Workflow"]["Fetch Todo"].status.clone();
+            (result.is_ok(), status, server.received_requests().await.unwrap().len())
+        };
+
+        assert_eq!(run().await, (false, "ERROR".to_string(), 1));
+        assert_eq!(run().await, (false, "ERROR".to_string(), 2));
+        // Open: skipped without a request.
+        assert_eq!(run().await, (false, "CIRCUIT_OPEN".to_string(), 2));
+
+        // Half-open after the cooldown: one probe goes out, and its failure reopens the circuit.
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(run().await, (false, "ERROR".to_string(), 3));
+        assert_eq!(run().await, (false, "CIRCUIT_OPEN".to_string(), 3));
+
+        // A successful probe closes it again.
+        server.reset().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#)).mount(&server).await;
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(run().await, (true, "OK".to_string(), 1));
+        assert_eq!(run().await, (true, "OK".to_string(), 2));
+    }
+
+    #[tokio::test]
+    async fn test_half_open_circuit_lets_a_single_concurrent_probe_through() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).set_delay(Duration::from_millis(300)))
+            .mount(&server)
+            .await;
+
+        let task = Task {
+            api_config: Arc::new(api_config(&server.uri(), "circuit_break_after: 1\ncircuit_cooldown_secs: 1")),
+            app_state: Arc::new(Mutex::new(AppState::default())),
+            settings: Arc::new(Settings::default()),
+            rate_limiter: None,
+        };
+        let client = Client::new();
+        assert!(task.execute(&client, "Sample Workflow").await.is_err());
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let runs = (0..5).map(|_| task.execute(&client, "Sample Workflow"));
+        futures::future::join_all(runs).await;
+
+        // The first run plus one probe; the other four were skipped while the probe was out.
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cache_headers_are_recorded() {
+        let server = MockServer::start().await;
+        Mock::given(path("/hit"))
+            .respond_with(ResponseTemplate::new(200).insert_header("Age", "120").insert_header("X-Cache", "Hit from cloudfront").set_body_string(r#"{"id": 1}"#))
+            .mount(&server)
+            .await;
+        Mock::given(path("/miss"))
+            .respond_with(ResponseTemplate::new(200).insert_header("X-Cache", "MISS").set_body_string(r#"{"id": 1}"#))
+            .mount(&server)
+            .await;
+        Mock::given(path("/aged"))
+            .respond_with(ResponseTemplate::new(200).insert_header("Age", "30").set_body_string(r#"{"id": 1}"#))
+            .mount(&server)
+            .await;
+        Mock::given(path("/origin"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#))
+            .mount(&server)
+            .await;
+
+        let cache = |route: &'static str| {
+            let uri = format!("{}{}", server.uri(), route);
+            async move {
+                let (_, data) = run_task(api_config(&uri, "")).await;
+                (data.cache_hit, data.cache_age_secs)
+            }
+        };
+
+        assert_eq!(cache("/hit").await, (Some(true), Some(120)));
+        assert_eq!(cache("/miss").await, (Some(false), None));
+        assert_eq!(cache("/aged").await, (Some(true), Some(30)));
+        assert_eq!(cache("/origin").await, (None, None));
+    }
+
+    #[tokio::test]
+    async fn test_large_error_body_is_truncated_at_response_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500).set_body_string("e".repeat(64 * 1024)))
+            .mount(&server)
+            .await;
+
+        let settings = Settings { max_response_bytes: Some(100), ..Settings::default() };
+        let config = api_config(&server.uri(), "capture_body_on_error: true\nerror_body_max_bytes: 4096");
+        let (result, data) = run_task_with_settings(config, settings).await;
+
+        assert!(result.is_err());
+        assert!(data.body_truncated);
+        assert_eq!(data.error_body.map(|body| body.len()), Some(100));
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_sent_and_recorded() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header_exists("X-Request-Id"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"id": 1}"#))
+            .mount(&server)
+            .await;
+
+        let settings = Settings { inject_request_id: Some(true), ..Settings::default() };
+        let (result, data) = run_task_with_settings(api_config(&server.uri(), ""), settings).await;
+
+        assert!(result.is_ok());
+        let received = server.received_requests().await.unwrap();
+        let sent_id = received[0].headers.get("X-Request-Id").unwrap().to_str().unwrap();
+        assert_eq!(data.request_id.as_deref(), Some(sent_id));
+    }
+
+    #[tokio::test]
+    async fn test_stored_results_stay_within_max_entries() {
+        let app_state = Arc::new(Mutex::new(AppState { max_results_entries: Some(3), ..AppState::default() }));
+        let config = api_config("http://localhost/todos", "");
+
+        for i in 0..10 {
+            let workflow_name = format!("Workflow {}", i % 2);
+            let task_config = ApiConfig { name: format!("Task {}", i), ..config.clone() };
+            record_task_status(&app_state, &workflow_name, &task_config, "OK", Duration::from_millis(10)).await;
+        }
+
+        let state = app_state.lock().await;
+        let task_data = state.task_monitoring_data.lock().await;
+        let mut stored: Vec<&str> = task_data.values().flat_map(|tasks| tasks.keys()).map(String::as_str).collect();
+        stored.sort();
+        assert_eq!(stored, ["Task 7", "Task 8", "Task 9"]);
+    }
+
+    async fn run_expected_field_check(expected_field: &str, body: &str) -> (Result<(), MonitorError>, MonitoringData) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_string(body)).mount(&server).await;
+
+        let mut config = api_config(&server.uri(), "");
+        config.expected_field = expected_field.to_string();
+        run_task(config).await
+    }
+
+    #[tokio::test]
+    async fn test_expected_field_pointer_finds_nested_field() {
+        let (result, data) = run_expected_field_check("/data/0/id", r#"{"data": [{"id": 7}]}"#).await;
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(data.status, "OK");
+    }
+
+    #[tokio::test]
+    async fn test_expected_field_pointer_miss_is_an_error() {
+        let (result, data) = run_expected_field_check("/data/1/id", r#"{"data": [{"id": 7}]}"#).await;
+
+        assert!(matches!(result, Err(MonitorError::Assertion(_))), "{:?}", result);
+        assert_eq!(data.status, "ERROR");
+    }
+
+    #[tokio::test]
+    async fn test_expected_field_without_slash_is_a_top_level_key() {
+        let (top_level, _) = run_expected_field_check("id", r#"{"id": 7}"#).await;
+        let (nested, _) = run_expected_field_check("id", r#"{"data": {"id": 7}}"#).await;
+
+        assert!(top_level.is_ok(), "{:?}", top_level);
+        assert!(matches!(nested, Err(MonitorError::Assertion(_))), "{:?}", nested);
+    }
+
+    async fn run_empty_body_check(body: &str) -> (Result<(), MonitorError>, MonitoringData) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200).set_body_string(body)).mount(&server).await;
+
+        let mut config = api_config(&server.uri(), "fail_on_empty_body: true");
+        config.expected_field = String::new();
+        run_task(config).await
+    }
+
+    #[tokio::test]
+    async fn test_blank_body_fails_under_fail_on_empty_body() {
+        let (result, data) = run_empty_body_check(" \n").await;
+
+        assert!(matches!(result, Err(MonitorError::Assertion(_))), "{:?}", result);
+        assert_eq!(data.status, "ERROR");
+        assert_eq!(data.status_code, Some(200));
+    }
+
+    async fn run_latency_check(delay_ms: u64, extra: &str) -> (Result<(), MonitorError>, MonitoringData) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": 1 })).set_delay(Duration::from_millis(delay_ms)))
+            .mount(&server)
+            .await;
+
+        run_task(api_config(&server.uri(), extra)).await
+    }
+
+    #[tokio::test]
+    async fn test_response_time_bands() {
+        let thresholds = "response_time_warn_ms: 150\nresponse_time_fail_ms: 450";
+
+        let (result, data) = run_latency_check(0, thresholds).await;
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(data.status, "OK");
+        assert_eq!((data.response_time_warn_ms, data.response_time_fail_ms), (Some(150), Some(450)));
+
+        let (result, data) = run_latency_check(250, thresholds).await;
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(data.status, "DEGRADED");
+
+        let (result, data) = run_latency_check(600, thresholds).await;
+        assert!(matches!(result, Err(MonitorError::Assertion(_))), "{:?}", result);
+        assert_eq!(data.status, "ERROR");
+        assert_eq!(data.status_code, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_sub_millisecond_response_time_is_kept_in_micros() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "id": 1 })).set_delay(Duration::from_micros(300)))
+            .mount(&server)
+            .await;
+
+        let (result, data) = run_task(api_config(&server.uri(), "")).await;
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert!(data.response_time_micros >= 300, "recorded {}µs", data.response_time_micros);
+        assert_eq!(data.response_time, data.response_time_micros / 1000);
+    }
+
+    #[tokio::test]
+    async fn test_response_time_threshold_is_the_fail_level_by_default() {
+        let mut config = api_config("http://localhost", "");
+        config.response_time_threshold = 200;
+        assert_eq!(config.response_time_fail_limit(), Some(200));
+        config.response_time_threshold = 0;
+        assert_eq!(config.response_time_fail_limit(), None);
+
+        let (result, data) = run_latency_check(300, "response_time_warn_ms: 100").await;
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(data.status, "DEGRADED");
+        assert_eq!(data.response_time_fail_ms, Some(2000));
+    }
+
+    async fn run_content_type_check(content_type: &str) -> (Result<(), MonitorError>, MonitoringData) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(r#"{"id": 1}"#, content_type))
+            .mount(&server)
+            .await;
+
+        run_task(api_config(&server.uri(), "expected_content_type: application/json")).await
+    }
+
+    #[tokio::test]
+    async fn test_matching_content_type_passes() {
+        let (result, data) = run_content_type_check("application/json").await;
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(data.status, "OK");
+    }
+
+    #[tokio::test]
+    async fn test_content_type_with_charset_passes() {
+        let (result, data) = run_content_type_check("Application/JSON; charset=utf-8").await;
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(data.status, "OK");
+    }
+
+    #[tokio::test]
+    async fn test_html_error_page_fails_content_type_check() {
+        let (result, data) = run_content_type_check("text/html; charset=utf-8").await;
+
+        assert!(matches!(&result, Err(MonitorError::Assertion(message)) if message.contains("text/html")), "{:?}", result);
+        assert_eq!(data.status, "ERROR");
+        assert_eq!(data.status_code, Some(200));
+    }
+
+    #[tokio::test]
+    async fn test_learned_threshold_flags_later_regressions() {
+        let server = MockServer::start().await;
+        let body = serde_json::json!({ "id": 1 });
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body).set_delay(Duration::from_millis(50)))
+            .up_to_n_times(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body).set_delay(Duration::from_millis(600)))
+            .mount(&server)
+            .await;
+
+        let config = api_config(&server.uri(), "learn_threshold: true\nlearn_cycles: 3\nthreshold_factor: 2.0");
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let task = Task { api_config: Arc::new(config), app_state: app_state.clone(), settings: Arc::new(Settings::default()), rate_limiter: None };
+        let client = Client::new();
+
+        for _ in 0..3 {
+            assert!(task.execute(&client, "Sample Workflow").await.is_ok());
+        }
+        let learned = app_state.lock().await.learned_thresholds.lock().await["Sample Workflow"]["Fetch Todo"].clone();
+        let baseline = learned.baseline_p95_ms.expect("baseline should be learned after the warmup");
+        assert!((50..600).contains(&baseline), "baseline {}", baseline);
+        assert_eq!(learned.threshold_ms, Some(baseline * 2));
+
+        // Within the configured 2000ms, but well over twice the baseline.
+        let result = task.execute(&client, "Sample Workflow").await;
+        assert!(matches!(result, Err(MonitorError::Assertion(_))), "{:?}", result);
+        let state = app_state.lock().await;
+        let data = state.task_monitoring_data.lock().await["Sample Workflow"]["Fetch Todo"].clone();
+        assert_eq!(data.status, "ERROR");
+        assert_eq!(data.response_time_fail_ms, Some(baseline * 2));
+    }
+
+    #[tokio::test]
+    async fn test_zero_ms_baseline_learns_a_threshold_of_at_least_one_ms() {
+        let config = api_config("http://localhost", "learn_threshold: true\nlearn_cycles: 2");
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+
+        for _ in 0..2 {
+            assert_eq!(learn_threshold(&app_state, "Sample Workflow", &config, 0, true).await, None);
+        }
+
+        let learned = app_state.lock().await.learned_thresholds.lock().await["Sample Workflow"]["Fetch Todo"].clone();
+        assert_eq!(learned.baseline_p95_ms, Some(0));
+        assert_eq!(learned.threshold_ms, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_non_empty_body_passes_under_fail_on_empty_body() {
+        let (result, data) = run_empty_body_check("pong").await;
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(data.status, "OK");
+    }
+}
@@ -0,0 +1,149 @@
+use std::{sync::Arc, time::{Duration, Instant}};
+use log::{error, info};
+use reqwest::Client;
+use tokio::{net::TcpStream, sync::Mutex};
+use crate::{appstate::AppState, config::{ApiConfig, Settings}, factory::{ApiMonitor, MonitorError}, telemetry::MonitorSpan};
+use crate::tasks::{update_app_state, MonitoringData, MonitoringDataType};
+
+/// Checks that a TCP port accepts connections, recording the connect latency.
+pub struct TcpMonitor {
+    /// Configuration whose `url` names the `host:port` to connect to (optionally prefixed with `tcp://`).
+    pub api_config: Arc<ApiConfig>,
+    /// A reference to the shared application state for recording monitoring data.
+    pub app_state: Arc<Mutex<AppState>>,
+    /// Global settings; the HTTP timeout doubles as the connect timeout.
+    pub settings: Arc<Settings>,
+}
+
+#[async_trait::async_trait]
+impl ApiMonitor for TcpMonitor {
+    async fn execute(&self, _client: &Client, workflow_name: &str) -> Result<(), MonitorError> {
+        let mut span = MonitorSpan::start("tcp", workflow_name, &self.api_config.name, &self.api_config.url, &self.api_config.method);
+        let address = tcp_address(&self.api_config.url);
+        let timeout = Duration::from_secs(self.settings.http_timeout_seconds);
+
+        let start = Instant::now();
+        let result = connect(address, timeout).await;
+        let duration = start.elapsed();
+
+        let status = if result.is_ok() { "OK" } else { "ERROR" };
+        let monitoring_data = MonitoringData {
+            api_url: self.api_config.url.clone(),
+            status: status.to_string(),
+            response_time: duration.as_millis() as u64,
+            response_time_micros: duration.as_micros() as u64,
+            status_code: None, // TCP checks have no status code
+            method: self.api_config.method.clone(),
+            ..Default::default()
+        };
+        update_app_state(&self.app_state, workflow_name, &self.api_config.name, MonitoringDataType::Task, monitoring_data).await;
+        span.record_status(status, None);
+
+        match result {
+            Ok(()) => {
+                info!("'{}' accepted a TCP connection in {:?}", self.api_config.name, duration);
+                Ok(())
+            },
+            Err(e) => {
+                let error = e.context(format!("'{}' is not accepting TCP connections on {}", self.api_config.name, address));
+                error!("{}", error);
+                Err(error)
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("TCP check for {}", self.api_config.name)
+    }
+
+    fn response_time_threshold(&self) -> Option<u64> {
+        None
+    }
+
+    fn get_task_order(&self) -> usize {
+        self.api_config.task_order.unwrap_or(usize::MAX)
+    }
+
+    fn api_configs(&self) -> Vec<Arc<ApiConfig>> {
+        vec![self.api_config.clone()]
+    }
+}
+
+/// Strips an optional `tcp://` scheme, leaving the `host:port` to connect to.
+pub(crate) fn tcp_address(url: &str) -> &str {
+    url.strip_prefix("tcp://").unwrap_or(url)
+}
+
+/// Opens a TCP connection to `address` and closes it straight away.
+pub(crate) async fn connect(address: &str, timeout: Duration) -> Result<(), MonitorError> {
+    match tokio::time::timeout(timeout, TcpStream::connect(address)).await {
+        Ok(Ok(_stream)) => Ok(()),
+        Ok(Err(e)) => Err(MonitorError::Request(e.to_string())),
+        Err(_) => Err(MonitorError::Timeout(format!("timed out after {:?}", timeout))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn tcp_config(url: &str) -> ApiConfig {
+        let yaml = format!(
+            r#"
+name: "Database Port"
+url: "{}"
+method: GET
+headers: {{}}
+expected_field: ""
+response_time_threshold: 2000
+check_type: tcp
+"#,
+            url
+        );
+        serde_yaml::from_str(&yaml).expect("Failed to parse YAML")
+    }
+
+    async fn run_check(url: &str) -> (Result<(), MonitorError>, MonitoringData) {
+        let app_state = Arc::new(Mutex::new(AppState::default()));
+        let monitor = TcpMonitor {
+            api_config: Arc::new(tcp_config(url)),
+            app_state: app_state.clone(),
+            settings: Arc::new(Settings { http_timeout_seconds: 2, ..Settings::default() }),
+        };
+        let result = monitor.execute(&Client::new(), "Sample Workflow").await;
+
+        let state = app_state.lock().await;
+        let task_data = state.task_monitoring_data.lock().await;
+        (result, task_data["Sample Workflow"]["Database Port"].clone())
+    }
+
+    #[test]
+    fn test_tcp_address_strips_scheme() {
+        assert_eq!(tcp_address("tcp://db.internal:5432"), "db.internal:5432");
+        assert_eq!(tcp_address("db.internal:5432"), "db.internal:5432");
+    }
+
+    #[tokio::test]
+    async fn test_open_port_is_recorded_as_up() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("tcp://{}", listener.local_addr().unwrap());
+
+        let (result, data) = run_check(&url).await;
+
+        assert!(result.is_ok());
+        assert_eq!(data.status, "OK");
+        assert_eq!(data.status_code, None);
+    }
+
+    #[tokio::test]
+    async fn test_closed_port_is_recorded_as_down() {
+        // Bind and drop a listener to get a port that is very likely closed.
+        let address = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+
+        let (result, data) = run_check(&address.to_string()).await;
+
+        assert!(matches!(result, Err(MonitorError::Request(_))), "{:?}", result);
+        assert_eq!(data.status, "ERROR");
+    }
+}
@@ -0,0 +1,164 @@
+//! Optional OpenTelemetry export of monitoring spans.
+//!
+//! Spans are only produced when thunderhawk is built with the `otel` feature *and* an
+//! OTLP endpoint was configured at startup. In every other case `MonitorSpan` is an
+//! empty value and recording on it compiles down to nothing.
+
+use crate::config::HttpMethod;
+
+#[cfg(feature = "otel")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "otel")]
+use opentelemetry::{trace::{Span, Tracer, TracerProvider}, KeyValue};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider};
+
+/// The tracer used for monitoring spans, installed once by `init_tracing`.
+#[cfg(feature = "otel")]
+static TRACER: OnceLock<SdkTracer> = OnceLock::new();
+
+/// Keeps the tracer provider alive and flushes pending spans when dropped.
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: SdkTracerProvider,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Err(e) = self.provider.shutdown() {
+            log::warn!("Failed to flush OpenTelemetry spans: {}", e);
+        }
+    }
+}
+
+/// Sets up span export to the given OTLP/HTTP endpoint.
+///
+/// Returns `None` when no endpoint is configured, or when the binary was built without
+/// the `otel` feature (in which case a warning is logged).
+pub fn init_tracing(otlp_endpoint: Option<&str>) -> Option<TelemetryGuard> {
+    let endpoint = otlp_endpoint?;
+
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry_otlp::WithExportConfig;
+
+        let exporter = match opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()
+        {
+            Ok(exporter) => exporter,
+            Err(e) => {
+                log::error!("Failed to create OTLP exporter for {}: {}", endpoint, e);
+                return None;
+            }
+        };
+
+        let provider = SdkTracerProvider::builder().with_batch_exporter(exporter).build();
+        install(provider.clone());
+        log::info!("Exporting monitoring spans to {}", endpoint);
+        Some(TelemetryGuard { provider })
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        log::warn!("--otlp-endpoint {} ignored: thunderhawk was built without the `otel` feature.", endpoint);
+        None
+    }
+}
+
+#[cfg(feature = "otel")]
+fn install(provider: SdkTracerProvider) {
+    if TRACER.set(provider.tracer("thunderhawk")).is_err() {
+        log::warn!("OpenTelemetry tracing was already initialized; ignoring new provider.");
+    }
+}
+
+/// A span covering a single task or load test run.
+///
+/// The span ends when the value is dropped.
+pub struct MonitorSpan {
+    #[cfg(feature = "otel")]
+    inner: Option<opentelemetry_sdk::trace::Span>,
+}
+
+impl MonitorSpan {
+    /// Starts a span named `name`, tagged with the workflow, task, URL and method.
+    #[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+    pub fn start(name: &'static str, workflow: &str, task: &str, url: &str, method: &HttpMethod) -> Self {
+        #[cfg(feature = "otel")]
+        {
+            let inner = TRACER.get().map(|tracer| {
+                let mut span = tracer.start(name);
+                span.set_attributes([
+                    KeyValue::new("workflow", workflow.to_string()),
+                    KeyValue::new("task", task.to_string()),
+                    KeyValue::new("url", url.to_string()),
+                    KeyValue::new("method", format!("{:?}", method)),
+                ]);
+                span
+            });
+            MonitorSpan { inner }
+        }
+
+        #[cfg(not(feature = "otel"))]
+        MonitorSpan {}
+    }
+
+    /// Records the outcome of the monitored call on the span.
+    #[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+    pub fn record_status(&mut self, status: &str, status_code: Option<u16>) {
+        #[cfg(feature = "otel")]
+        if let Some(span) = self.inner.as_mut() {
+            span.set_attribute(KeyValue::new("status", status.to_string()));
+            if let Some(code) = status_code {
+                span.set_attribute(KeyValue::new("status_code", i64::from(code)));
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use super::*;
+    use opentelemetry_sdk::error::OTelSdkResult;
+    use opentelemetry_sdk::trace::{SpanData, SpanExporter};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Clone, Default)]
+    struct CollectingExporter {
+        spans: Arc<Mutex<Vec<SpanData>>>,
+    }
+
+    impl SpanExporter for CollectingExporter {
+        async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+            self.spans.lock().unwrap().extend(batch);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_monitor_span_is_exported_with_attributes() {
+        let exporter = CollectingExporter::default();
+        let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        install(provider.clone());
+
+        {
+            let mut span = MonitorSpan::start("task", "Sample Workflow", "Fetch Todo", "http://localhost/todos", &HttpMethod::GET);
+            span.record_status("OK", Some(200));
+        }
+        provider.force_flush().unwrap();
+
+        let spans = exporter.spans.lock().unwrap();
+        assert_eq!(spans.len(), 1);
+        assert_eq!(spans[0].name, "task");
+        let attribute = |key: &str| spans[0].attributes.iter().find(|kv| kv.key.as_str() == key).map(|kv| kv.value.to_string());
+        assert_eq!(attribute("workflow").as_deref(), Some("Sample Workflow"));
+        assert_eq!(attribute("task").as_deref(), Some("Fetch Todo"));
+        assert_eq!(attribute("method").as_deref(), Some("GET"));
+        assert_eq!(attribute("status").as_deref(), Some("OK"));
+        assert_eq!(attribute("status_code").as_deref(), Some("200"));
+    }
+}
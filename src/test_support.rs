@@ -0,0 +1,38 @@
+//! Sample results shared by the unit tests.
+
+use std::collections::HashMap;
+use crate::{loadtest::LoadTestMonitoringData, tasks::MonitoringData};
+
+/// A 12ms task result with a 200 response and the given `status`.
+pub(crate) fn sample_task_data(status: &str) -> MonitoringData {
+    MonitoringData {
+        api_url: "http://localhost/todos".to_string(),
+        status: status.to_string(),
+        response_time: 12,
+        response_time_micros: 12_000,
+        status_code: Some(200),
+        ..Default::default()
+    }
+}
+
+/// A load test result of four requests, one of which failed with a 500.
+pub(crate) fn sample_load_test_data() -> LoadTestMonitoringData {
+    LoadTestMonitoringData {
+        api_url: "http://localhost/todos".to_string(),
+        total_requests: 4,
+        success_count: 3,
+        failure_count: 1,
+        median_response_time_ms: 12,
+        average_response_time_ms: 15,
+        median_response_time_micros: 12_000,
+        average_response_time_micros: 15_000,
+        min_response_time_ms: 5,
+        max_response_time_ms: 30,
+        status_code_distribution: HashMap::from([(200, 3), (500, 1)]),
+        percentile_95th_response_time_ms: 30,
+        requests_per_second: 2.5,
+        reliable: true,
+        peak_concurrency: 2,
+        ..Default::default()
+    }
+}
@@ -1,37 +1,156 @@
-use reqwest::{Client, Error, header::HeaderMap, header::HeaderName, header::HeaderValue};
-use std::collections::HashMap;
+use reqwest::{Client, ClientBuilder, Error, Response, header::HeaderMap, header::HeaderName, header::HeaderValue, header::ACCEPT_ENCODING, header::COOKIE, header::SET_COOKIE, redirect::Policy, tls};
+use crate::config::host_allowed;
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr};
 use std::time::Duration;
 use std::str::FromStr;
 
 pub struct HttpClientConfig {
+    /// Bounds each request as a whole, from connecting until the response body is read.
     pub timeout_seconds: u64,
+    /// Bounds establishing the connection alone, so a slow DNS lookup or connect fails separately
+    /// from a slow response. Unset by default, leaving it to `timeout_seconds`.
+    pub connect_timeout_seconds: Option<u64>,
     pub proxy_url: Option<String>,
-    pub default_headers: HashMap<String, String>,
+    /// Headers sent with every request. Repeated keys are all sent, folded into one header
+    /// (see `combine_repeated_headers`).
+    pub default_headers: Vec<(String, String)>,
+    /// The `User-Agent` sent with every request. Defaults to `thunderhawk/<version>`.
+    pub user_agent: Option<String>,
+    /// Whether idle connections are kept for reuse. Defaults to true; false opens a fresh
+    /// connection per request, so load tests measure cold-connection latency.
+    pub reuse_connections: Option<bool>,
+    /// DNS overrides as `(host, address)` pairs, like entries in `/etc/hosts`. The address is an IP,
+    /// which keeps the URL's port, or an `ip:port`. Requests keep the original `Host` header.
+    pub resolve: Option<Vec<(String, String)>>,
+    /// Oldest TLS version accepted, e.g. "1.2". Unset by default, leaving the floor to reqwest.
+    pub min_tls_version: Option<String>,
+    /// Whether gzip, brotli and deflate responses are requested and decompressed. Defaults to
+    /// true; false leaves bodies as sent, so response sizes are the compressed sizes.
+    pub accept_compression: Option<bool>,
+    /// Whether compressed responses are decoded by the client. Defaults to true; false still asks
+    /// for compression (unless `accept_compression` is false) but hands bodies over as sent, so
+    /// callers can measure both sizes with `decoded_len`.
+    pub decompress: Option<bool>,
+    /// Hostnames, IP addresses and CIDR ranges redirects may lead to; a redirect anywhere else
+    /// fails the request. Unset by default, following redirects to any host.
+    pub allowed_hosts: Option<Vec<String>>,
 }
 
+/// How many redirects a request follows, as with reqwest's default policy.
+const MAX_REDIRECTS: usize = 10;
+
+/// The `User-Agent` used when none is configured.
+pub const DEFAULT_USER_AGENT: &str = concat!("thunderhawk/", env!("CARGO_PKG_VERSION"));
+
 impl Default for HttpClientConfig {
     fn default() -> Self {
         Self {
             timeout_seconds: 30, // Default timeout of 30 seconds
+            connect_timeout_seconds: None, // Connecting is bounded by the overall timeout
             proxy_url: None, // No proxy by default
-            default_headers: HashMap::new(), // No default headers
+            default_headers: Vec::new(), // No default headers
+            user_agent: None, // Falls back to DEFAULT_USER_AGENT
+            reuse_connections: None, // Connections are pooled
+            resolve: None, // Hosts are resolved through DNS
+            min_tls_version: None, // reqwest's default floor
+            accept_compression: None, // Compressed responses are decompressed
+            decompress: None, // Decoding is left to `accept_compression`
+            allowed_hosts: None, // Redirects may lead anywhere
+        }
+    }
+}
+
+/// Why an HTTP client couldn't be built from an `HttpClientConfig`.
+#[derive(Debug)]
+pub enum ClientError {
+    /// reqwest rejected the configuration, e.g. a malformed proxy URL.
+    Build(Error),
+    /// A configured value is invalid, e.g. an unknown TLS version.
+    Config(String),
+}
+
+impl std::fmt::Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Build(e) => e.fmt(f),
+            ClientError::Config(message) => f.write_str(message),
         }
     }
 }
 
-pub fn get_client(config: Option<HttpClientConfig>) -> Result<Client, Error> {
-    let config = config.unwrap_or_default();
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Build(e) => Some(e),
+            ClientError::Config(_) => None,
+        }
+    }
+}
+
+impl From<Error> for ClientError {
+    fn from(e: Error) -> Self {
+        ClientError::Build(e)
+    }
+}
+
+pub fn get_client(config: Option<HttpClientConfig>) -> Result<Client, ClientError> {
+    Ok(client_builder(config.unwrap_or_default())?.build()?)
+}
 
+/// Configures a client builder from `config`, without building the client yet.
+fn client_builder(config: HttpClientConfig) -> Result<ClientBuilder, ClientError> {
     let mut client_builder = Client::builder()
-        .timeout(Duration::from_secs(config.timeout_seconds));
+        .timeout(Duration::from_secs(config.timeout_seconds))
+        .user_agent(config.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
 
-    // Configure proxy if specified
+    if let Some(secs) = config.connect_timeout_seconds {
+        client_builder = client_builder.connect_timeout(Duration::from_secs(secs));
+    }
+
+    // Configure proxy if specified; a malformed proxy URL is an error rather than being ignored,
+    // since every request would otherwise bypass the intended proxy or fail opaquely.
     if let Some(proxy_url) = config.proxy_url {
-        if let Ok(proxy) = reqwest::Proxy::all(&proxy_url) {
-            client_builder = client_builder.proxy(proxy);
-        } else {
-            eprintln!("Invalid proxy URL: {}", proxy_url);
-        }
+        client_builder = client_builder.proxy(reqwest::Proxy::all(&proxy_url)?);
+    }
+
+    // Without idle connections in the pool, every request has to connect (and handshake) again.
+    if !config.reuse_connections.unwrap_or(true) {
+        client_builder = client_builder.pool_max_idle_per_host(0);
+    }
+
+    // A dropped override would silently send the host's requests wherever DNS points.
+    for (host, address) in config.resolve.iter().flatten() {
+        let address = parse_resolve_address(address)
+            .ok_or_else(|| ClientError::Config(format!("Invalid address for {}: {}", host, address)))?;
+        client_builder = client_builder.resolve(host, address);
+    }
+
+    // Connecting without the intended TLS floor would be worse than not connecting at all.
+    if let Some(version) = &config.min_tls_version {
+        client_builder = client_builder.min_tls_version(parse_tls_version(version).map_err(ClientError::Config)?);
+    }
+
+    // Checking task URLs alone would let a redirect take a request to any host.
+    if let Some(allowed_hosts) = config.allowed_hosts {
+        client_builder = client_builder.redirect(Policy::custom(move |attempt| {
+            if !host_allowed(&allowed_hosts, attempt.url().as_str()) {
+                let message = format!("redirect to {} leaves the allowed hosts", attempt.url());
+                attempt.error(message)
+            } else if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else {
+                attempt.follow()
+            }
+        }));
+    }
+
+    if let Some(enabled) = config.accept_compression {
+        client_builder = client_builder.gzip(enabled).brotli(enabled).deflate(enabled);
+    }
+    let raw_bodies = config.decompress == Some(false);
+    if raw_bodies {
+        client_builder = client_builder.gzip(false).brotli(false).deflate(false);
     }
 
     // Initialize an empty HeaderMap
@@ -41,13 +160,389 @@ pub fn get_client(config: Option<HttpClientConfig>) -> Result<Client, Error> {
     for (key, value) in config.default_headers.iter() {
         // Convert each key and value to HeaderName and HeaderValue
         if let (Ok(h_key), Ok(h_value)) = (HeaderName::from_str(key), HeaderValue::from_str(value)) {
-            headers.insert(h_key, h_value);
+            headers.append(h_key, h_value); // `append` keeps earlier values for the same key
         } else {
             eprintln!("Invalid header: {}: {}", key, value);
         }
     }
 
-    client_builder = client_builder.default_headers(headers);
+    // Without decoding, reqwest no longer asks for compression itself.
+    if raw_bodies && config.accept_compression != Some(false) && !headers.contains_key(ACCEPT_ENCODING) {
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("gzip, br, deflate"));
+    }
+
+    client_builder = client_builder.default_headers(combine_repeated_headers(&headers));
+
+    Ok(client_builder)
+}
+
+/// Parses a TLS version given as "1.0", "1.1", "1.2" or "1.3".
+pub fn parse_tls_version(version: &str) -> Result<tls::Version, String> {
+    match version.trim() {
+        "1.0" => Ok(tls::Version::TLS_1_0),
+        "1.1" => Ok(tls::Version::TLS_1_1),
+        "1.2" => Ok(tls::Version::TLS_1_2),
+        "1.3" => Ok(tls::Version::TLS_1_3),
+        other => Err(format!("Invalid TLS version '{}' (expected 1.0, 1.1, 1.2 or 1.3)", other)),
+    }
+}
+
+/// Parses a DNS override address, either `ip:port` or a bare IP. A bare IP gets port 0,
+/// which makes reqwest keep the port of the URL being requested.
+pub fn parse_resolve_address(address: &str) -> Option<SocketAddr> {
+    SocketAddr::from_str(address).ok()
+        .or_else(|| IpAddr::from_str(address).ok().map(|ip| SocketAddr::new(ip, 0)))
+}
+
+/// Folds repeated header values into a single value.
+///
+/// reqwest keeps only one default value per header name, so repeated keys would otherwise
+/// silently lose all but the last value. Joining them with commas is equivalent for
+/// list-based fields such as `Accept` (RFC 9110, section 5.3). `Cookie` pairs are joined
+/// with `; ` instead (RFC 6265, section 5.4). `Set-Cookie` can't be combined at all
+/// (RFC 9110, section 5.3), so only its last value is kept.
+fn combine_repeated_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut combined = HeaderMap::new();
+    for name in headers.keys() {
+        let values: Vec<&[u8]> = headers.get_all(name).iter().map(|value| value.as_bytes()).collect();
+        let separator: &[u8] = match *name {
+            COOKIE => b"; ",
+            SET_COOKIE => {
+                if values.len() > 1 {
+                    log::warn!("Set-Cookie can't be combined; sending only its last default value.");
+                }
+                combined.insert(name.clone(), headers.get_all(name).iter().next_back().cloned().expect("every key has a value"));
+                continue;
+            },
+            _ => b", ",
+        };
+        let value = HeaderValue::from_bytes(&values.join(separator))
+            .expect("joining valid header values yields a valid header value");
+        combined.insert(name.clone(), value);
+    }
+    combined
+}
+
+/// Checks that a TCP connection to the proxy's host and port can be opened within `timeout`.
+pub async fn probe_proxy(proxy_url: &str, timeout: Duration) -> Result<(), String> {
+    let url = reqwest::Url::parse(proxy_url).map_err(|e| format!("Invalid proxy URL '{}': {}", proxy_url, e))?;
+    let host = url.host_str().ok_or_else(|| format!("Proxy URL '{}' has no host", proxy_url))?;
+    let port = url.port_or_known_default().ok_or_else(|| format!("Proxy URL '{}' has no port", proxy_url))?;
+
+    match tokio::time::timeout(timeout, tokio::net::TcpStream::connect((host, port))).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(format!("Proxy {}:{} is unreachable: {}", host, port, e)),
+        Err(_) => Err(format!("Proxy {}:{} did not accept a connection within {:?}", host, port, timeout)),
+    }
+}
+
+/// The size of `body` once decoded according to its `Content-Encoding`.
+///
+/// Handles gzip, deflate and br; bodies with no or another encoding count as sent. A body that
+/// fails to decode, e.g. one cut short at `max_response_bytes`, counts up to the point of failure.
+pub fn decoded_len(content_encoding: Option<&str>, body: &[u8]) -> usize {
+    let mut decoder: Box<dyn Read + '_> = match content_encoding.map(|encoding| encoding.trim().to_ascii_lowercase()).as_deref() {
+        Some("gzip") | Some("x-gzip") => Box::new(flate2::read::GzDecoder::new(body)),
+        Some("deflate") => Box::new(flate2::read::ZlibDecoder::new(body)),
+        Some("br") => Box::new(brotli_decompressor::Decompressor::new(body, 4096)),
+        _ => return body.len(),
+    };
+
+    let mut buffer = [0u8; 8192];
+    let mut total = 0;
+    while let Ok(read @ 1..) = decoder.read(&mut buffer) {
+        total += read;
+    }
+    total
+}
+
+/// Reads a response body, stopping once `max_bytes` have been read.
+///
+/// Returns the body read so far and whether it was cut short. Without a limit the whole body is read.
+pub async fn read_body_limited(mut response: Response, max_bytes: Option<usize>) -> Result<(Vec<u8>, bool), Error> {
+    let Some(max_bytes) = max_bytes else {
+        return Ok((response.bytes().await?.to_vec(), false));
+    };
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        let remaining = max_bytes - body.len();
+        if chunk.len() > remaining {
+            body.extend_from_slice(&chunk[..remaining]);
+            return Ok((body, true));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok((body, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_invalid_proxy_url_is_an_error() {
+        let result = get_client(Some(HttpClientConfig {
+            proxy_url: Some("http://not a proxy".to_string()),
+            ..HttpClientConfig::default()
+        }));
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout_is_set_apart_from_request_timeout() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(1500)))
+            .mount(&server)
+            .await;
+        let client = |timeout_seconds, connect_timeout_seconds| get_client(Some(HttpClientConfig {
+            timeout_seconds,
+            connect_timeout_seconds,
+            ..HttpClientConfig::default()
+        })).unwrap();
 
-    client_builder.build()
+        // The connection opens at once, so a slow response outlasts the connect timeout...
+        let response = client(30, Some(1)).get(server.uri()).send().await;
+        assert!(response.unwrap().status().is_success());
+
+        // ...but not the request timeout.
+        let error = client(1, None).get(server.uri()).send().await.unwrap_err();
+        assert!(error.is_timeout(), "{}", error);
+    }
+
+    #[test]
+    fn test_invalid_min_tls_version_fails_to_build() {
+        let client = |version: &str| get_client(Some(HttpClientConfig { min_tls_version: Some(version.to_string()), ..HttpClientConfig::default() }));
+
+        assert!(client("1.2").is_ok());
+        assert!(matches!(client("1.4"), Err(ClientError::Config(message)) if message.contains("1.4")));
+    }
+
+    #[tokio::test]
+    async fn test_compression_follows_accept_compression() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        for accept_compression in [None, Some(false)] {
+            let client = get_client(Some(HttpClientConfig { accept_compression, ..HttpClientConfig::default() })).unwrap();
+            client.get(server.uri()).send().await.unwrap();
+        }
+
+        let received = server.received_requests().await.unwrap();
+        let accept_encoding = received[0].headers.get("Accept-Encoding").unwrap().to_str().unwrap();
+        for encoding in ["gzip", "br", "deflate"] {
+            assert!(accept_encoding.contains(encoding), "{}", accept_encoding);
+        }
+        assert!(received[1].headers.get("Accept-Encoding").is_none());
+    }
+
+    #[test]
+    fn test_invalid_tls_version_is_rejected() {
+        assert_eq!(parse_tls_version(" 1.3 "), Ok(tls::Version::TLS_1_3));
+        assert!(parse_tls_version("1.4").is_err());
+        assert!(parse_tls_version("TLSv1.2").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_override_routes_host_to_address() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        let port = server.address().port();
+
+        let client = get_client(Some(HttpClientConfig {
+            resolve: Some(vec![("backend.thunderhawk.invalid".to_string(), "127.0.0.1".to_string())]),
+            ..HttpClientConfig::default()
+        })).unwrap();
+        let response = client.get(format!("http://backend.thunderhawk.invalid:{}/", port)).send().await.unwrap();
+
+        assert!(response.status().is_success());
+        let received = server.received_requests().await.unwrap();
+        assert_eq!(received[0].headers.get("Host").unwrap().to_str().unwrap(), format!("backend.thunderhawk.invalid:{}", port));
+    }
+
+    #[test]
+    fn test_invalid_resolve_address_fails_to_build() {
+        let result = get_client(Some(HttpClientConfig {
+            resolve: Some(vec![("backend.thunderhawk.invalid".to_string(), "backend.internal".to_string())]),
+            ..HttpClientConfig::default()
+        }));
+
+        assert!(matches!(result, Err(ClientError::Config(message)) if message.contains("backend.internal")));
+    }
+
+    #[test]
+    fn test_resolve_address_accepts_ip_with_or_without_port() {
+        assert_eq!(parse_resolve_address("10.0.0.5"), Some("10.0.0.5:0".parse().unwrap()));
+        assert_eq!(parse_resolve_address("10.0.0.5:8443"), Some("10.0.0.5:8443".parse().unwrap()));
+        assert_eq!(parse_resolve_address("::1"), Some("[::1]:0".parse().unwrap()));
+        assert_eq!(parse_resolve_address("backend.internal"), None);
+    }
+
+    #[test]
+    fn test_valid_proxy_url_builds_client() {
+        let result = get_client(Some(HttpClientConfig {
+            proxy_url: Some("http://127.0.0.1:3128".to_string()),
+            ..HttpClientConfig::default()
+        }));
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_probe_proxy_reports_reachability() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open_url = format!("http://{}", listener.local_addr().unwrap());
+        assert!(probe_proxy(&open_url, Duration::from_secs(1)).await.is_ok());
+
+        drop(listener);
+        assert!(probe_proxy(&open_url, Duration::from_secs(1)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_user_agent_defaults_to_crate_version() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        get_client(None).unwrap().get(server.uri()).send().await.unwrap();
+
+        let received = server.received_requests().await.unwrap();
+        let expected = format!("thunderhawk/{}", env!("CARGO_PKG_VERSION"));
+        assert_eq!(received[0].headers.get("User-Agent").unwrap(), expected.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_custom_user_agent_is_sent() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let client = get_client(Some(HttpClientConfig {
+            user_agent: Some("acme-monitor/2.0".to_string()),
+            ..HttpClientConfig::default()
+        })).unwrap();
+        client.get(server.uri()).send().await.unwrap();
+
+        let received = server.received_requests().await.unwrap();
+        assert_eq!(received[0].headers.get("User-Agent").unwrap(), "acme-monitor/2.0");
+    }
+
+    #[tokio::test]
+    async fn test_repeated_default_headers_are_all_sent() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let client = get_client(Some(HttpClientConfig {
+            default_headers: vec![
+                ("Accept".to_string(), "application/json".to_string()),
+                ("Accept".to_string(), "text/plain".to_string()),
+                ("Cookie".to_string(), "session=abc".to_string()),
+                ("Cookie".to_string(), "theme=dark".to_string()),
+                ("Set-Cookie".to_string(), "first=1".to_string()),
+                ("Set-Cookie".to_string(), "second=2".to_string()),
+            ],
+            ..HttpClientConfig::default()
+        })).unwrap();
+        client.get(server.uri()).send().await.unwrap();
+
+        let received = server.received_requests().await.unwrap();
+        assert_eq!(received[0].headers.get("Accept").unwrap(), "application/json, text/plain");
+        assert_eq!(received[0].headers.get("Cookie").unwrap(), "session=abc; theme=dark");
+        assert_eq!(received[0].headers.get_all("Set-Cookie").iter().collect::<Vec<_>>(), ["second=2"]);
+    }
+
+    #[tokio::test]
+    async fn test_redirects_outside_allowed_hosts_fail() {
+        use wiremock::matchers::path;
+
+        let server = MockServer::start().await;
+        let port = server.address().port();
+        Mock::given(path("/inside"))
+            .respond_with(ResponseTemplate::new(302).insert_header("Location", format!("http://127.0.0.1:{}/target", port).as_str()))
+            .mount(&server)
+            .await;
+        Mock::given(path("/outside"))
+            .respond_with(ResponseTemplate::new(302).insert_header("Location", format!("http://localhost:{}/target", port).as_str()))
+            .mount(&server)
+            .await;
+        Mock::given(path("/target")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let client = get_client(Some(HttpClientConfig {
+            allowed_hosts: Some(vec!["127.0.0.1".to_string()]),
+            ..HttpClientConfig::default()
+        })).unwrap();
+
+        let response = client.get(format!("{}/inside", server.uri())).send().await.unwrap();
+        assert_eq!(response.url().path(), "/target");
+
+        let error = client.get(format!("{}/outside", server.uri())).send().await.unwrap_err();
+        assert!(error.is_redirect(), "{}", error);
+        let requested: Vec<String> = server.received_requests().await.unwrap().iter().map(|request| request.url.path().to_string()).collect();
+        assert_eq!(requested, ["/inside", "/target", "/outside"]);
+    }
+
+    #[tokio::test]
+    async fn test_read_body_limited_stops_at_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![b'x'; 1024 * 1024]))
+            .mount(&server)
+            .await;
+
+        let response = Client::new().get(server.uri()).send().await.unwrap();
+        let (body, truncated) = read_body_limited(response, Some(1000)).await.unwrap();
+        assert_eq!(body.len(), 1000);
+        assert!(truncated);
+
+        let response = Client::new().get(server.uri()).send().await.unwrap();
+        let (body, truncated) = read_body_limited(response, None).await.unwrap();
+        assert_eq!(body.len(), 1024 * 1024);
+        assert!(!truncated);
+    }
+
+    /// Serves empty 200 responses over keep-alive connections, counting the connections accepted.
+    async fn connection_counting_server() -> (String, Arc<AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buffer = [0u8; 4096];
+                    // Requests here have no body, so each read ends with a complete request.
+                    while matches!(stream.read(&mut buffer).await, Ok(n) if n > 0) {
+                        if stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (url, connections)
+    }
+
+    async fn connections_for_requests(reuse_connections: Option<bool>) -> usize {
+        let (url, connections) = connection_counting_server().await;
+        let client = get_client(Some(HttpClientConfig { reuse_connections, ..HttpClientConfig::default() })).unwrap();
+        for _ in 0..3 {
+            client.get(&url).send().await.unwrap().bytes().await.unwrap();
+        }
+        connections.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn test_connections_are_reused_by_default() {
+        assert_eq!(connections_for_requests(None).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_disabling_reuse_opens_a_connection_per_request() {
+        assert_eq!(connections_for_requests(Some(false)).await, 3);
+    }
 }
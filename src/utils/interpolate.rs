@@ -24,21 +24,37 @@ pub fn interpolate_config(workflow: &mut Workflow) {
 
     for api in workflow.apis.iter_mut() {
         api.url = interpolate_string(&api.url);
+        for url in api.urls.iter_mut().flatten() {
+            *url = interpolate_string(url);
+        }
         if let Some(body) = &mut api.body {
             *body = interpolate_string(body);
         }
+        for body in api.body_variants.iter_mut().flatten() {
+            *body = interpolate_string(body);
+        }
+        for value in api.form.iter_mut().flat_map(|form| form.values_mut()) {
+            *value = interpolate_string(value);
+        }
+        if let Some(query) = &mut api.graphql_query {
+            *query = interpolate_string(query);
+        }
         for header_value in api.headers.values_mut() {
             *header_value = interpolate_string(header_value);
         }
         // Note: This implementation does not interpolate 'name', 'method', or 'expected_field' as
         // they are less likely to contain environment variables, but you can add them if needed.
     }
+    if let Some(gate) = &mut workflow.gate {
+        gate.url = interpolate_string(&gate.url);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_yaml;
+    use std::collections::HashMap;
 
     // Adjusted to include a token placeholder in the `http_default_headers`
     fn load_test_settings() -> Workflow {
@@ -72,5 +88,16 @@ apis:
         // Clean up environment variables
         env::remove_var("API_URL");
     }
+
+    #[test]
+    fn test_form_values_are_interpolated() {
+        env::set_var("THUNDERHAWK_TEST_FORM_USER", "alice");
+
+        let mut settings = load_test_settings();
+        settings.apis[0].form = Some(HashMap::from([("username".to_string(), "${THUNDERHAWK_TEST_FORM_USER}".to_string())]));
+        interpolate_config(&mut settings);
+
+        assert_eq!(settings.apis[0].form.as_ref().unwrap()["username"], "alice");
+    }
 }
 
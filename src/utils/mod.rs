@@ -1,2 +1,4 @@
 pub mod http_client;
-pub mod interpolate;
\ No newline at end of file
+pub mod interpolate;
+pub mod output_format;
+pub mod template;
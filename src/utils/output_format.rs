@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use crate::loadtest::LoadTestMonitoringData;
+
+/// The representations the results endpoints can respond with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Csv,
+}
+
+impl OutputFormat {
+    /// Picks the output format from the value of an `Accept` header.
+    ///
+    /// The media range we can serve with the highest `q` value wins, and the earliest one
+    /// breaks ties; a range with `q=0` is refused. Anything unrecognized (or a missing
+    /// header) falls back to JSON.
+    pub fn from_accept(accept: Option<&str>) -> Self {
+        let accept = match accept {
+            Some(value) => value,
+            None => return OutputFormat::Json,
+        };
+
+        let mut best: Option<(f32, OutputFormat)> = None;
+        for media_range in accept.split(',') {
+            let mut parts = media_range.split(';');
+            let media_type = parts.next().unwrap_or_default().trim();
+            let format = if media_type.eq_ignore_ascii_case("text/csv") {
+                OutputFormat::Csv
+            } else if media_type.eq_ignore_ascii_case("application/json") || media_type == "*/*" {
+                OutputFormat::Json
+            } else {
+                continue;
+            };
+
+            // Parameters other than `q`, such as `charset=utf-8`, don't affect the choice.
+            let quality = parts
+                .filter_map(|param| param.split_once('='))
+                .find(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+                .map_or(1.0, |(_, value)| value.trim().parse::<f32>().unwrap_or(0.0));
+            if quality > 0.0 && best.is_none_or(|(best_quality, _)| quality > best_quality) {
+                best = Some((quality, format));
+            }
+        }
+
+        best.map_or(OutputFormat::Json, |(_, format)| format)
+    }
+
+    /// The `Content-Type` to send alongside a body in this format.
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Json => "application/json",
+            OutputFormat::Csv => "text/csv",
+        }
+    }
+}
+
+/// Renders load test results as CSV, one row per task, sorted by workflow and task name.
+pub fn load_test_data_to_csv(data: &HashMap<String, HashMap<String, LoadTestMonitoringData>>) -> String {
+    let mut csv = String::from(
+        "workflow,task,api_url,method,total_requests,success_count,failure_count,\
+median_response_time_ms,average_response_time_ms,min_response_time_ms,max_response_time_ms,\
+percentile_95th_response_time_ms,requests_per_second,average_bytes_per_response,status_code_distribution\n",
+    );
+
+    let mut workflow_names: Vec<&String> = data.keys().collect();
+    workflow_names.sort();
+
+    for workflow_name in workflow_names {
+        let tasks = &data[workflow_name];
+        let mut task_names: Vec<&String> = tasks.keys().collect();
+        task_names.sort();
+
+        for task_name in task_names {
+            let stats = &tasks[task_name];
+
+            let mut status_codes: Vec<(&u16, &usize)> = stats.status_code_distribution.iter().collect();
+            status_codes.sort();
+            let status_code_distribution = status_codes
+                .iter()
+                .map(|(code, count)| format!("{}:{}", code, count))
+                .collect::<Vec<_>>()
+                .join(";");
+
+            let fields = [
+                escape_csv_field(workflow_name),
+                escape_csv_field(task_name),
+                escape_csv_field(&stats.api_url),
+                format!("{:?}", stats.method),
+                stats.total_requests.to_string(),
+                stats.success_count.to_string(),
+                stats.failure_count.to_string(),
+                stats.median_response_time_ms.to_string(),
+                stats.average_response_time_ms.to_string(),
+                stats.min_response_time_ms.to_string(),
+                stats.max_response_time_ms.to_string(),
+                stats.percentile_95th_response_time_ms.to_string(),
+                stats.requests_per_second.to_string(),
+                stats.average_bytes_per_response.to_string(),
+                escape_csv_field(&status_code_distribution),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push('\n');
+        }
+    }
+
+    csv
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break (RFC 4180).
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_accept() {
+        assert_eq!(OutputFormat::from_accept(None), OutputFormat::Json);
+        assert_eq!(OutputFormat::from_accept(Some("application/json")), OutputFormat::Json);
+        assert_eq!(OutputFormat::from_accept(Some("text/csv")), OutputFormat::Csv);
+        assert_eq!(OutputFormat::from_accept(Some("text/csv;q=0.9, application/json")), OutputFormat::Json);
+        assert_eq!(OutputFormat::from_accept(Some("application/json;q=0.5, text/csv;charset=utf-8")), OutputFormat::Csv);
+        assert_eq!(OutputFormat::from_accept(Some("text/csv;q=0, application/json")), OutputFormat::Json);
+        assert_eq!(OutputFormat::from_accept(Some("text/csv;q=0.8, */*;q=0.8")), OutputFormat::Csv);
+        assert_eq!(OutputFormat::from_accept(Some("text/html, */*")), OutputFormat::Json);
+        assert_eq!(OutputFormat::from_accept(Some("text/html")), OutputFormat::Json);
+    }
+
+    #[test]
+    fn test_escape_csv_field() {
+        assert_eq!(escape_csv_field("plain"), "plain");
+        assert_eq!(escape_csv_field("a,b"), "\"a,b\"");
+        assert_eq!(escape_csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -0,0 +1,88 @@
+use std::{collections::HashMap, fs, sync::Arc, time::SystemTime};
+use handlebars::Handlebars;
+use serde_json::{json, Value};
+
+const TEMPLATE_NAME: &str = "body";
+
+/// A Handlebars request body template, compiled once and rendered again for every request.
+///
+/// Templates see `env`, the current `timestamp` (Unix seconds) and `timestamp_ms`, a fresh
+/// `uuid` and the configured `vars`. `env` holds only the environment variables the template
+/// was given by name, as they were when it was compiled, so it can't leak others. Values are
+/// inserted as they are, without HTML escaping, since bodies are usually JSON.
+#[derive(Debug, Clone)]
+pub struct BodyTemplate {
+    registry: Arc<Handlebars<'static>>,
+    env: Arc<Value>,
+}
+
+impl BodyTemplate {
+    /// Compiles the template in the file at `path`, exposing the environment variables named in
+    /// `env_names` that are set, and failing on syntax errors.
+    pub fn from_file(path: &str, env_names: &[String]) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading body template '{}': {}", path, e))?;
+        Self::parse(&source, env_names).map_err(|e| format!("Invalid body template '{}': {}", path, e))
+    }
+
+    fn parse(source: &str, env_names: &[String]) -> Result<Self, String> {
+        let mut registry = Handlebars::new();
+        registry.register_escape_fn(handlebars::no_escape);
+        registry.register_template_string(TEMPLATE_NAME, source).map_err(|e| e.to_string())?;
+        let env: HashMap<&str, String> = env_names.iter()
+            .filter_map(|name| std::env::var(name).ok().map(|value| (name.as_str(), value)))
+            .collect();
+        let env = json!(env);
+        Ok(BodyTemplate { registry: Arc::new(registry), env: Arc::new(env) })
+    }
+
+    /// Renders the template with a fresh per-request context.
+    pub fn render(&self, vars: Option<&Value>) -> Result<String, String> {
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let context = json!({
+            "env": &*self.env,
+            "uuid": uuid::Uuid::new_v4().to_string(),
+            "timestamp": now.as_secs(),
+            "timestamp_ms": now.as_millis() as u64,
+            "vars": vars.cloned().unwrap_or_else(|| json!({})),
+        });
+        self.registry.render(TEMPLATE_NAME, &context)
+            .map_err(|e| format!("Error rendering body template: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_errors_are_rejected() {
+        assert!(BodyTemplate::parse("{{#each vars.items}}unclosed", &[]).is_err());
+    }
+
+    #[test]
+    fn test_each_render_gets_fresh_request_values() {
+        let template = BodyTemplate::parse("{{uuid}}", &[]).unwrap();
+
+        assert_ne!(template.render(None).unwrap(), template.render(None).unwrap());
+    }
+
+    #[test]
+    fn test_environment_is_captured_at_compile_time() {
+        std::env::set_var("THUNDERHAWK_TEST_TEMPLATE_STAGE", "compiled");
+        let template = BodyTemplate::parse("{{env.THUNDERHAWK_TEST_TEMPLATE_STAGE}}", &["THUNDERHAWK_TEST_TEMPLATE_STAGE".to_string()]).unwrap();
+        std::env::set_var("THUNDERHAWK_TEST_TEMPLATE_STAGE", "rendered");
+
+        assert_eq!(template.render(None).unwrap(), "compiled");
+    }
+
+    #[test]
+    fn test_only_named_environment_variables_are_exposed() {
+        std::env::set_var("THUNDERHAWK_TEST_TEMPLATE_PUBLIC", "visible");
+        std::env::set_var("THUNDERHAWK_TEST_TEMPLATE_SECRET", "hidden");
+        let names = ["THUNDERHAWK_TEST_TEMPLATE_PUBLIC".to_string(), "THUNDERHAWK_TEST_TEMPLATE_UNSET".to_string()];
+        let template = BodyTemplate::parse("[{{env.THUNDERHAWK_TEST_TEMPLATE_PUBLIC}}][{{env.THUNDERHAWK_TEST_TEMPLATE_SECRET}}][{{env.THUNDERHAWK_TEST_TEMPLATE_UNSET}}]", &names).unwrap();
+
+        assert_eq!(template.render(None).unwrap(), "[visible][][]");
+    }
+}